@@ -47,6 +47,36 @@ fn bench_parsing(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_numbers(c: &mut Criterion) {
+    // GeoJSON-style coordinates: a long array of float pairs.
+    let mut coordinates = String::from("[");
+    for i in 0..1000 {
+        if i > 0 {
+            coordinates.push(',');
+        }
+        let lon = -180.0 + (i as f64) * 0.359_713_1;
+        let lat = -90.0 + (i as f64) * 0.179_856_3;
+        coordinates.push_str(&format!("[{},{}]", lon, lat));
+    }
+    coordinates.push(']');
+
+    let mut group = c.benchmark_group("JSON Numbers");
+
+    group.bench_function("My JsonValue::parse", |b| {
+        b.iter(|| {
+            let _: JsonValue = JsonValue::parse(black_box(&coordinates)).unwrap();
+        })
+    });
+
+    group.bench_function("serde_json::from_str", |b| {
+        b.iter(|| {
+            let _: Value = serde_json::from_str(black_box(&coordinates)).unwrap();
+        })
+    });
+
+    group.finish();
+}
+
 fn bench_stringifying(c: &mut Criterion) {
     // Create your native JsonValue
     let mut my_val_map = BTreeMap::new();
@@ -91,5 +121,5 @@ fn bench_stringifying(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_parsing, bench_stringifying);
+criterion_group!(benches, bench_parsing, bench_numbers, bench_stringifying);
 criterion_main!(benches);
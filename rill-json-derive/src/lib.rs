@@ -0,0 +1,519 @@
+//! # rill-json-derive
+//!
+//! Derive macros for the `ToJson` and `FromJson` traits of `rill-json`.
+//! Enable the `derive` feature of `rill-json` to use them; it re-exports
+//! both macros next to the traits.
+//!
+//! Structs with named fields map to JSON objects, newtype structs to their
+//! inner value, tuple structs to arrays and unit structs to `null`. Enums
+//! are externally tagged: a unit variant is its name as a string, any
+//! other variant is an object with the name as its single key.
+//!
+//! ## Attributes
+//!
+//! * `#[json(rename = "name")]` on a field or variant uses `name` as its
+//!   JSON key.
+//! * `#[json(default)]` on a field uses `Default::default()` when the
+//!   member is missing.
+//! * `#[json(skip)]` on a field leaves it out of the JSON, and fills it
+//!   with `Default::default()` when converting back.
+//! * `#[json(flatten)]` on a field merges the members of its object into
+//!   the containing object, and builds it from that whole object.
+#![forbid(unsafe_code)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, FieldsNamed, FieldsUnnamed,
+    Generics, Ident, Index, LitStr, Result, Type,
+};
+
+/// Derives `rill_json::ToJson`.
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_json(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `rill_json::FromJson`.
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_json(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+// --- Attributes ---
+
+/// The `#[json(...)]` options of one item.
+#[derive(Default)]
+struct Attrs {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+    flatten: bool,
+}
+
+/// Parses the `#[json(...)]` attributes in `attrs`, rejecting any option
+/// not listed in `allowed`.
+fn parse_attrs(attrs: &[Attribute], allowed: &[&str], item: &str) -> Result<Attrs> {
+    let mut parsed = Attrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+        attr.parse_nested_meta(|meta| {
+            let Some(name) = meta.path.get_ident().map(Ident::to_string) else {
+                return Err(meta.error("expected a json attribute name"));
+            };
+            if !allowed.contains(&name.as_str()) {
+                return Err(meta.error(format!("`{}` is not supported on {}", name, item)));
+            }
+            match name.as_str() {
+                "rename" => parsed.rename = Some(meta.value()?.parse::<LitStr>()?.value()),
+                "default" => parsed.default = true,
+                "skip" => parsed.skip = true,
+                "flatten" => parsed.flatten = true,
+                _ => unreachable!("checked against `allowed`"),
+            }
+            Ok(())
+        })?;
+    }
+    Ok(parsed)
+}
+
+/// A named field with its JSON key and options.
+struct NamedField<'a> {
+    ident: &'a Ident,
+    ty: &'a Type,
+    key: String,
+    attrs: Attrs,
+}
+
+fn named_fields(fields: &FieldsNamed) -> Result<Vec<NamedField<'_>>> {
+    fields
+        .named
+        .iter()
+        .map(|field| {
+            let attrs = parse_attrs(
+                &field.attrs,
+                &["rename", "default", "skip", "flatten"],
+                "fields",
+            )?;
+            if attrs.flatten && (attrs.rename.is_some() || attrs.default || attrs.skip) {
+                return Err(Error::new_spanned(
+                    field,
+                    "`flatten` cannot be combined with other json attributes",
+                ));
+            }
+            let ident = field.ident.as_ref().expect("named fields have identifiers");
+            Ok(NamedField {
+                ident,
+                ty: &field.ty,
+                key: attrs
+                    .rename
+                    .clone()
+                    .unwrap_or_else(|| ident.unraw().to_string()),
+                attrs,
+            })
+        })
+        .collect()
+}
+
+/// Checks that no field of a tuple struct or tuple variant has options.
+fn check_unnamed(fields: &FieldsUnnamed) -> Result<()> {
+    for field in &fields.unnamed {
+        parse_attrs(&field.attrs, &[], "tuple fields")?;
+    }
+    Ok(())
+}
+
+fn variant_key(variant: &syn::Variant) -> Result<String> {
+    let attrs = parse_attrs(&variant.attrs, &["rename"], "variants")?;
+    Ok(attrs
+        .rename
+        .unwrap_or_else(|| variant.ident.unraw().to_string()))
+}
+
+/// Adds `bound` to every type parameter of `generics`.
+fn with_bound(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(syn::parse2(bound.clone()).expect("valid bound"));
+    }
+    generics
+}
+
+// --- ToJson ---
+
+fn expand_to_json(input: &DeriveInput) -> Result<TokenStream2> {
+    parse_attrs(&input.attrs, &[], "containers")?;
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let fields = named_fields(fields)?;
+                object_to_json(&fields, |field| {
+                    let ident = field.ident;
+                    quote!(&self.#ident)
+                })
+            }
+            Fields::Unnamed(fields) => {
+                check_unnamed(fields)?;
+                let values = (0..fields.unnamed.len()).map(|i| {
+                    let index = Index::from(i);
+                    quote!(&self.#index)
+                });
+                tuple_to_json(values.collect())
+            }
+            Fields::Unit => quote!(::rill_json::JsonValue::Null),
+        },
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let key = variant_key(variant)?;
+                    let ident = &variant.ident;
+                    Ok(match &variant.fields {
+                        Fields::Unit => quote! {
+                            Self::#ident => ::rill_json::JsonValue::String(
+                                ::std::string::String::from(#key),
+                            ),
+                        },
+                        Fields::Unnamed(fields) => {
+                            check_unnamed(fields)?;
+                            let bindings: Vec<_> = (0..fields.unnamed.len())
+                                .map(|i| format_ident!("__field{}", i))
+                                .collect();
+                            let value =
+                                tuple_to_json(bindings.iter().map(|b| quote!(#b)).collect());
+                            let value = tagged(&key, value);
+                            quote!(Self::#ident(#(#bindings),*) => #value,)
+                        }
+                        Fields::Named(fields) => {
+                            let fields = named_fields(fields)?;
+                            let patterns = fields.iter().map(|field| {
+                                let ident = field.ident;
+                                if field.attrs.skip {
+                                    quote!(#ident: _)
+                                } else {
+                                    let binding = format_ident!("__field_{}", ident);
+                                    quote!(#ident: #binding)
+                                }
+                            });
+                            let value = object_to_json(&fields, |field| {
+                                let binding = format_ident!("__field_{}", field.ident);
+                                quote!(#binding)
+                            });
+                            let value = tagged(&key, value);
+                            quote!(Self::#ident { #(#patterns),* } => #value,)
+                        }
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if arms.is_empty() {
+                quote!(match *self {})
+            } else {
+                quote!(match self { #(#arms)* })
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "ToJson cannot be derived for unions",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, quote!(::rill_json::ToJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rill_json::ToJson for #name #ty_generics #where_clause {
+            fn to_json(&self) -> ::rill_json::JsonValue {
+                #body
+            }
+        }
+    })
+}
+
+/// Builds a JSON object from `fields`, reading each value through `access`.
+fn object_to_json(
+    fields: &[NamedField<'_>],
+    access: impl Fn(&NamedField<'_>) -> TokenStream2,
+) -> TokenStream2 {
+    let inserts = fields
+        .iter()
+        .filter(|field| !field.attrs.skip)
+        .map(|field| {
+            let value = access(field);
+            if field.attrs.flatten {
+                quote! {
+                    if let ::rill_json::JsonValue::Object(__members) =
+                        ::rill_json::ToJson::to_json(#value)
+                    {
+                        __object.extend(__members);
+                    }
+                }
+            } else {
+                let key = &field.key;
+                quote! {
+                    __object.insert(
+                        ::std::string::String::from(#key),
+                        ::rill_json::ToJson::to_json(#value),
+                    );
+                }
+            }
+        });
+    quote! {{
+        #[allow(unused_mut)]
+        let mut __object = ::std::collections::BTreeMap::new();
+        #(#inserts)*
+        ::rill_json::JsonValue::Object(__object)
+    }}
+}
+
+/// Builds the JSON for tuple fields: the value itself for a newtype, an
+/// array otherwise.
+fn tuple_to_json(values: Vec<TokenStream2>) -> TokenStream2 {
+    if let [value] = values.as_slice() {
+        quote!(::rill_json::ToJson::to_json(#value))
+    } else {
+        quote! {
+            ::rill_json::JsonValue::Array(::std::vec![
+                #(::rill_json::ToJson::to_json(#values)),*
+            ])
+        }
+    }
+}
+
+/// Wraps `value` in an object with `key` as its single member.
+fn tagged(key: &str, value: TokenStream2) -> TokenStream2 {
+    quote! {{
+        let mut __object = ::std::collections::BTreeMap::new();
+        __object.insert(::std::string::String::from(#key), #value);
+        ::rill_json::JsonValue::Object(__object)
+    }}
+}
+
+// --- FromJson ---
+
+fn expand_from_json(input: &DeriveInput) -> Result<TokenStream2> {
+    parse_attrs(&input.attrs, &[], "containers")?;
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => object_from_json(quote!(Self), &named_fields(fields)?, &[]),
+            Fields::Unnamed(fields) => {
+                check_unnamed(fields)?;
+                tuple_from_json(quote!(Self), fields, &[])
+            }
+            Fields::Unit => quote! {
+                match __value {
+                    ::rill_json::JsonValue::Null => ::std::result::Result::Ok(Self),
+                    __other => ::std::result::Result::Err(
+                        ::rill_json::FromJsonError::invalid_type("null", __other),
+                    ),
+                }
+            },
+        },
+        Data::Enum(data) => {
+            let mut unit_arms = Vec::new();
+            let mut tagged_arms = Vec::new();
+            for variant in &data.variants {
+                let key = variant_key(variant)?;
+                let ident = &variant.ident;
+                let keys = [key.as_str()];
+                let body = match &variant.fields {
+                    Fields::Unit => {
+                        unit_arms.push(quote!(#key => ::std::result::Result::Ok(Self::#ident),));
+                        quote! {
+                            match __value {
+                                ::rill_json::JsonValue::Null => {
+                                    ::std::result::Result::Ok(Self::#ident)
+                                }
+                                __other => ::std::result::Result::Err(
+                                    ::rill_json::FromJsonError::invalid_type("null", __other)
+                                        .at_key(#key),
+                                ),
+                            }
+                        }
+                    }
+                    Fields::Unnamed(fields) => {
+                        check_unnamed(fields)?;
+                        tuple_from_json(quote!(Self::#ident), fields, &keys)
+                    }
+                    Fields::Named(fields) => {
+                        object_from_json(quote!(Self::#ident), &named_fields(fields)?, &keys)
+                    }
+                };
+                tagged_arms.push(quote!(#key => { #body }));
+            }
+            quote! {
+                match __value {
+                    ::rill_json::JsonValue::String(__tag) => match __tag.as_str() {
+                        #(#unit_arms)*
+                        __other => ::std::result::Result::Err(::rill_json::FromJsonError::new(
+                            ::rill_json::FromJsonErrorKind::UnknownVariant(
+                                ::std::string::String::from(__other),
+                            ),
+                        )),
+                    },
+                    ::rill_json::JsonValue::Object(__object) if __object.len() == 1 => {
+                        let ::std::option::Option::Some((__tag, __value)) = __object.iter().next()
+                        else {
+                            ::std::unreachable!()
+                        };
+                        match __tag.as_str() {
+                            #(#tagged_arms)*
+                            __other => ::std::result::Result::Err(
+                                ::rill_json::FromJsonError::new(
+                                    ::rill_json::FromJsonErrorKind::UnknownVariant(
+                                        ::std::string::String::from(__other),
+                                    ),
+                                ),
+                            ),
+                        }
+                    }
+                    __other => ::std::result::Result::Err(
+                        ::rill_json::FromJsonError::invalid_type(
+                            "a string or an object with a single member",
+                            __other,
+                        ),
+                    ),
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "FromJson cannot be derived for unions",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, quote!(::rill_json::FromJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rill_json::FromJson for #name #ty_generics #where_clause {
+            fn from_json(
+                __value: &::rill_json::JsonValue,
+            ) -> ::std::result::Result<Self, ::rill_json::FromJsonError> {
+                #body
+            }
+        }
+    })
+}
+
+/// The `.at_key(...)` calls that move an error under the object members
+/// `keys`, innermost first.
+fn at_keys(keys: &[&str]) -> TokenStream2 {
+    quote!(#(.at_key(#keys))*)
+}
+
+/// Applies `?` to `result`, first moving its error under `keys`.
+fn try_at(result: TokenStream2, keys: &[&str]) -> TokenStream2 {
+    if keys.is_empty() {
+        quote!(#result?)
+    } else {
+        let at_keys = at_keys(keys);
+        quote!(#result.map_err(|__e| __e #at_keys)?)
+    }
+}
+
+/// Builds `ctor { ... }` from the object in `__value`. Errors are moved
+/// under `keys`, the path of the object itself.
+fn object_from_json(ctor: TokenStream2, fields: &[NamedField<'_>], keys: &[&str]) -> TokenStream2 {
+    let at_object = at_keys(keys);
+    let inits = fields.iter().map(|field| {
+        let ident = field.ident;
+        let ty = field.ty;
+        let key = field.key.as_str();
+        if field.attrs.skip {
+            return quote!(#ident: ::std::default::Default::default());
+        }
+        if field.attrs.flatten {
+            let value = try_at(
+                quote!(<#ty as ::rill_json::FromJson>::from_json(__value)),
+                keys,
+            );
+            return quote!(#ident: #value);
+        }
+        let missing = if field.attrs.default {
+            quote!(::std::result::Result::Ok(::std::default::Default::default()))
+        } else {
+            quote!(<#ty as ::rill_json::FromJson>::from_missing_field(#key))
+        };
+        let field_keys: Vec<&str> = std::iter::once(key).chain(keys.iter().copied()).collect();
+        let value = try_at(
+            quote! {
+                match __object.get(#key) {
+                    ::std::option::Option::Some(__member) => {
+                        <#ty as ::rill_json::FromJson>::from_json(__member)
+                    }
+                    ::std::option::Option::None => #missing,
+                }
+            },
+            &field_keys,
+        );
+        quote!(#ident: #value)
+    });
+    quote! {
+        let __object = match __value {
+            ::rill_json::JsonValue::Object(__object) => __object,
+            __other => {
+                return ::std::result::Result::Err(
+                    ::rill_json::FromJsonError::invalid_type("an object", __other) #at_object,
+                )
+            }
+        };
+        ::std::result::Result::Ok(#ctor { #(#inits),* })
+    }
+}
+
+/// Builds `ctor(...)` from `__value`: the value itself for a newtype, an
+/// array of the right length otherwise. Errors are moved under `keys`.
+fn tuple_from_json(ctor: TokenStream2, fields: &FieldsUnnamed, keys: &[&str]) -> TokenStream2 {
+    let types: Vec<&Type> = fields.unnamed.iter().map(|field| &field.ty).collect();
+    if let [ty] = types.as_slice() {
+        let value = try_at(
+            quote!(<#ty as ::rill_json::FromJson>::from_json(__value)),
+            keys,
+        );
+        return quote!(::std::result::Result::Ok(#ctor(#value)));
+    }
+
+    let at_array = at_keys(keys);
+    let len = types.len();
+    let values = types.iter().enumerate().map(|(i, ty)| {
+        quote! {
+            <#ty as ::rill_json::FromJson>::from_json(&__items[#i])
+                .map_err(|__e| __e.at_index(#i) #at_array)?
+        }
+    });
+    quote! {
+        let __items = match __value {
+            ::rill_json::JsonValue::Array(__items) if __items.len() == #len => __items,
+            ::rill_json::JsonValue::Array(__items) => {
+                return ::std::result::Result::Err(
+                    ::rill_json::FromJsonError::new(
+                        ::rill_json::FromJsonErrorKind::InvalidLength {
+                            expected: #len,
+                            found: __items.len(),
+                        },
+                    ) #at_array,
+                )
+            }
+            __other => {
+                return ::std::result::Result::Err(
+                    ::rill_json::FromJsonError::invalid_type("an array", __other) #at_array,
+                )
+            }
+        };
+        ::std::result::Result::Ok(#ctor(#(#values),*))
+    }
+}
@@ -0,0 +1,614 @@
+//! Contains the `ToJson` and `FromJson` traits, rill-json's own conversions
+//! between Rust types and `JsonValue`.
+//!
+//! These traits need no extra dependencies, so they are available where
+//! serde is not. With the `derive` feature, `#[derive(ToJson, FromJson)]`
+//! implements them for structs and enums.
+use crate::parser::push_pointer_token;
+use crate::{JsonNumber, JsonValue};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::str::FromStr;
+
+/// A type that can be converted into a `JsonValue`.
+///
+/// # Examples
+/// ```
+/// use rill_json::ToJson;
+///
+/// let value = vec![Some(1), None].to_json();
+/// assert_eq!(value.stringify().unwrap(), "[1,null]");
+/// ```
+pub trait ToJson {
+    /// Converts `self` into a `JsonValue`.
+    fn to_json(&self) -> JsonValue;
+}
+
+/// A type that can be built from a `JsonValue`.
+///
+/// # Examples
+/// ```
+/// use rill_json::{FromJson, JsonValue};
+///
+/// let value = JsonValue::parse(r#"[[1, 2], [3, "x"]]"#).unwrap();
+/// let error = Vec::<Vec<u8>>::from_json(&value).unwrap_err();
+/// assert_eq!(error.path, "/1/1");
+/// assert_eq!(error.to_string(), "Expected u8, found a string at /1/1");
+/// ```
+pub trait FromJson: Sized {
+    /// Builds a value of this type from `value`.
+    ///
+    /// # Errors
+    /// Returns a `FromJsonError` if `value` has the wrong shape for this
+    /// type.
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError>;
+
+    /// Called instead of `from_json` when the object member `field` is
+    /// absent.
+    ///
+    /// The default fails with `FromJsonErrorKind::MissingField`; `Option`
+    /// returns `None` instead.
+    ///
+    /// # Errors
+    /// Returns a `FromJsonError` if this type requires the member.
+    fn from_missing_field(field: &'static str) -> Result<Self, FromJsonError> {
+        Err(FromJsonError::new(FromJsonErrorKind::MissingField(field)))
+    }
+}
+
+/// The specific kind of a `FromJsonError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FromJsonErrorKind {
+    /// The value has the wrong JSON type.
+    InvalidType {
+        /// What the target type accepts.
+        expected: &'static str,
+        /// The JSON type that was found.
+        found: &'static str,
+    },
+    /// A number that does not fit in the named target type.
+    OutOfRange(&'static str),
+    /// An array with the wrong number of elements for a tuple or fixed-size array.
+    InvalidLength {
+        /// The number of elements the target type has.
+        expected: usize,
+        /// The number of elements that were found.
+        found: usize,
+    },
+    /// A required object member is absent.
+    MissingField(&'static str),
+    /// An enum tag that names no variant.
+    UnknownVariant(String),
+    /// Any other error, with its description.
+    Custom(String),
+}
+
+impl fmt::Display for FromJsonErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromJsonErrorKind::InvalidType { expected, found } => {
+                write!(f, "Expected {}, found {}", expected, found)
+            }
+            FromJsonErrorKind::OutOfRange(ty) => write!(f, "Number does not fit in {}", ty),
+            FromJsonErrorKind::InvalidLength { expected, found } => write!(
+                f,
+                "Expected an array of {} elements, found {}",
+                expected, found
+            ),
+            FromJsonErrorKind::MissingField(field) => write!(f, "Missing field '{}'", field),
+            FromJsonErrorKind::UnknownVariant(tag) => write!(f, "Unknown variant '{}'", tag),
+            FromJsonErrorKind::Custom(message) => f.write_str(message),
+        }
+    }
+}
+
+/// An error from `FromJson::from_json`, located by a JSON Pointer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FromJsonError {
+    /// What went wrong.
+    pub kind: FromJsonErrorKind,
+    /// A human-readable description of the error.
+    pub message: String,
+    /// The JSON Pointer (RFC 6901) to the failing value, e.g. `/users/0/id`.
+    /// Empty for the top-level value.
+    pub path: String,
+}
+
+impl FromJsonError {
+    /// Creates an error for the top-level value.
+    pub fn new(kind: FromJsonErrorKind) -> Self {
+        FromJsonError {
+            message: kind.to_string(),
+            kind,
+            path: String::new(),
+        }
+    }
+
+    /// Creates an `InvalidType` error for `found`.
+    pub fn invalid_type(expected: &'static str, found: &JsonValue) -> Self {
+        let found = match found {
+            JsonValue::Null => "null",
+            JsonValue::Boolean(_) => "a boolean",
+            JsonValue::Number(_) => "a number",
+            JsonValue::String(_) => "a string",
+            JsonValue::WtfString(_) => "a string with a lone surrogate",
+            JsonValue::Array(_) => "an array",
+            JsonValue::Object(_) => "an object",
+            JsonValue::Raw(_) => "a raw value",
+        };
+        Self::new(FromJsonErrorKind::InvalidType { expected, found })
+    }
+
+    /// Moves the error into the object member `key`, prepending it to
+    /// `path`.
+    pub fn at_key(mut self, key: &str) -> Self {
+        let mut path = String::with_capacity(key.len() + 1 + self.path.len());
+        push_pointer_token(&mut path, key);
+        path.push_str(&self.path);
+        self.path = path;
+        self
+    }
+
+    /// Moves the error into the array element `index`, prepending it to
+    /// `path`.
+    pub fn at_index(self, index: usize) -> Self {
+        self.at_key(&index.to_string())
+    }
+}
+
+impl fmt::Display for FromJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{} at {}", self.message, self.path)
+        }
+    }
+}
+
+impl std::error::Error for FromJsonError {}
+
+// --- ToJson ---
+
+impl ToJson for JsonValue {
+    fn to_json(&self) -> JsonValue {
+        self.clone()
+    }
+}
+
+impl ToJson for JsonNumber {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(self.clone())
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Boolean(*self)
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_owned())
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+impl ToJson for () {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Null
+    }
+}
+
+macro_rules! number_to_json {
+    ($($ty:ty),*) => {$(
+        impl ToJson for $ty {
+            fn to_json(&self) -> JsonValue {
+                JsonValue::from(*self)
+            }
+        }
+    )*};
+}
+
+number_to_json!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> JsonValue {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> JsonValue {
+        match self {
+            Some(value) => value.to_json(),
+            None => JsonValue::Null,
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson, const N: usize> ToJson for [T; N] {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+impl<K: fmt::Display, V: ToJson> ToJson for BTreeMap<K, V> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(
+            self.iter()
+                .map(|(key, value)| (key.to_string(), value.to_json()))
+                .collect(),
+        )
+    }
+}
+
+impl<K: fmt::Display, V: ToJson, S> ToJson for HashMap<K, V, S> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(
+            self.iter()
+                .map(|(key, value)| (key.to_string(), value.to_json()))
+                .collect(),
+        )
+    }
+}
+
+// --- FromJson ---
+
+impl FromJson for JsonValue {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        Ok(value.clone())
+    }
+}
+
+impl FromJson for JsonNumber {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Number(n) => Ok(n.clone()),
+            other => Err(FromJsonError::invalid_type("a number", other)),
+        }
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Boolean(b) => Ok(*b),
+            other => Err(FromJsonError::invalid_type("a boolean", other)),
+        }
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::String(s) => Ok(s.clone()),
+            other => Err(FromJsonError::invalid_type("a string", other)),
+        }
+    }
+}
+
+impl FromJson for char {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        if let JsonValue::String(s) = value {
+            let mut chars = s.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                return Ok(c);
+            }
+        }
+        Err(FromJsonError::invalid_type("a single character", value))
+    }
+}
+
+impl FromJson for () {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Null => Ok(()),
+            other => Err(FromJsonError::invalid_type("null", other)),
+        }
+    }
+}
+
+macro_rules! int_from_json {
+    ($wide:ident: $($ty:ident),*) => {$(
+        impl FromJson for $ty {
+            fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+                match value {
+                    JsonValue::Number(n) => n
+                        .$wide()
+                        .and_then(|n| $ty::try_from(n).ok())
+                        .ok_or_else(|| {
+                            FromJsonError::new(FromJsonErrorKind::OutOfRange(stringify!($ty)))
+                        }),
+                    other => Err(FromJsonError::invalid_type(stringify!($ty), other)),
+                }
+            }
+        }
+    )*};
+}
+
+int_from_json!(to_i128: i8, i16, i32, i64, i128, isize);
+int_from_json!(to_u128: u8, u16, u32, u64, u128, usize);
+
+impl FromJson for f64 {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            // Keep NaN and the infinities a `JsonValue` was built with.
+            JsonValue::Number(JsonNumber::F64(n)) => Ok(*n),
+            JsonValue::Number(n) => n
+                .to_f64()
+                .ok_or_else(|| FromJsonError::new(FromJsonErrorKind::OutOfRange("f64"))),
+            other => Err(FromJsonError::invalid_type("f64", other)),
+        }
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Number(_) => f64::from_json(value).map(|n| n as f32),
+            other => Err(FromJsonError::invalid_type("f32", other)),
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Box<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        T::from_json(value).map(Box::new)
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Null => Ok(None),
+            other => T::from_json(other).map(Some),
+        }
+    }
+
+    fn from_missing_field(_field: &'static str) -> Result<Self, FromJsonError> {
+        Ok(None)
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| T::from_json(item).map_err(|e| e.at_index(i)))
+                .collect(),
+            other => Err(FromJsonError::invalid_type("an array", other)),
+        }
+    }
+}
+
+/// Returns the elements of `value` if it is an array of exactly `len`
+/// elements.
+fn array_of_len(value: &JsonValue, len: usize) -> Result<&[JsonValue], FromJsonError> {
+    match value {
+        JsonValue::Array(items) if items.len() == len => Ok(items),
+        JsonValue::Array(items) => Err(FromJsonError::new(FromJsonErrorKind::InvalidLength {
+            expected: len,
+            found: items.len(),
+        })),
+        other => Err(FromJsonError::invalid_type("an array", other)),
+    }
+}
+
+impl<T: FromJson, const N: usize> FromJson for [T; N] {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        let items = array_of_len(value, N)?
+            .iter()
+            .enumerate()
+            .map(|(i, item)| T::from_json(item).map_err(|e| e.at_index(i)))
+            .collect::<Result<Vec<T>, _>>()?;
+        match items.try_into() {
+            Ok(array) => Ok(array),
+            Err(_) => unreachable!("length was checked above"),
+        }
+    }
+}
+
+/// Parses an object key with `FromStr`.
+fn key_from_str<K: FromStr>(key: &str) -> Result<K, FromJsonError> {
+    key.parse().map_err(|_| {
+        FromJsonError::new(FromJsonErrorKind::Custom(format!("Invalid key '{}'", key))).at_key(key)
+    })
+}
+
+impl<K: FromStr + Ord, V: FromJson> FromJson for BTreeMap<K, V> {
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Object(members) => members
+                .iter()
+                .map(|(key, value)| {
+                    let value = V::from_json(value).map_err(|e| e.at_key(key))?;
+                    Ok((key_from_str(key)?, value))
+                })
+                .collect(),
+            other => Err(FromJsonError::invalid_type("an object", other)),
+        }
+    }
+}
+
+impl<K, V, S> FromJson for HashMap<K, V, S>
+where
+    K: FromStr + Eq + Hash,
+    V: FromJson,
+    S: BuildHasher + Default,
+{
+    fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+        match value {
+            JsonValue::Object(members) => members
+                .iter()
+                .map(|(key, value)| {
+                    let value = V::from_json(value).map_err(|e| e.at_key(key))?;
+                    Ok((key_from_str(key)?, value))
+                })
+                .collect(),
+            other => Err(FromJsonError::invalid_type("an object", other)),
+        }
+    }
+}
+
+macro_rules! tuple_impls {
+    ($($len:literal => ($($i:tt $name:ident)+))+) => {$(
+        impl<$($name: ToJson),+> ToJson for ($($name,)+) {
+            fn to_json(&self) -> JsonValue {
+                JsonValue::Array(vec![$(self.$i.to_json()),+])
+            }
+        }
+
+        impl<$($name: FromJson),+> FromJson for ($($name,)+) {
+            fn from_json(value: &JsonValue) -> Result<Self, FromJsonError> {
+                let items = array_of_len(value, $len)?;
+                Ok(($($name::from_json(&items[$i]).map_err(|e| e.at_index($i))?,)+))
+            }
+        }
+    )+};
+}
+
+tuple_impls! {
+    1 => (0 T0)
+    2 => (0 T0 1 T1)
+    3 => (0 T0 1 T1 2 T2)
+    4 => (0 T0 1 T1 2 T2 3 T3)
+    5 => (0 T0 1 T1 2 T2 3 T3 4 T4)
+    6 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5)
+    7 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6)
+    8 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: ToJson + FromJson + PartialEq + fmt::Debug>(value: T, json: &str) {
+        let converted = value.to_json();
+        assert_eq!(converted.stringify().unwrap(), json);
+        assert_eq!(T::from_json(&converted).unwrap(), value);
+    }
+
+    #[test]
+    fn test_convert_round_trips() {
+        round_trip(true, "true");
+        round_trip(-7i8, "-7");
+        round_trip(u64::MAX, "18446744073709551615");
+        round_trip(i128::MIN, "-170141183460469231731687303715884105728");
+        round_trip(u128::MAX, "340282366920938463463374607431768211455");
+        round_trip(1.5f64, "1.5");
+        round_trip(0.1f32, "0.1");
+        round_trip('x', "\"x\"");
+        round_trip(String::from("a\"b"), r#""a\"b""#);
+        round_trip((), "null");
+        round_trip(Some(3u8), "3");
+        round_trip(None::<u8>, "null");
+        round_trip(Box::new(1i32), "1");
+        round_trip(vec![1u16, 2], "[1,2]");
+        round_trip([true, false], "[true,false]");
+        round_trip((1u8, String::from("a"), None::<bool>), r#"[1,"a",null]"#);
+
+        let mut btree = BTreeMap::new();
+        btree.insert(2u32, vec!['a']);
+        btree.insert(10u32, vec![]);
+        round_trip(btree, r#"{"10":[],"2":["a"]}"#);
+
+        let mut hash = HashMap::new();
+        hash.insert(String::from("k"), -1i64);
+        round_trip(hash, r#"{"k":-1}"#);
+    }
+
+    #[test]
+    fn test_convert_numbers() {
+        let value = JsonValue::parse("[255, 256, -1, 2.0, 2.5, 1e400]").unwrap();
+        let JsonValue::Array(items) = value else {
+            unreachable!()
+        };
+        assert_eq!(u8::from_json(&items[0]), Ok(255));
+        assert_eq!(
+            u8::from_json(&items[1]).unwrap_err().kind,
+            FromJsonErrorKind::OutOfRange("u8")
+        );
+        assert!(u64::from_json(&items[2]).is_err());
+        assert_eq!(i32::from_json(&items[3]), Ok(2));
+        assert!(i32::from_json(&items[4]).is_err());
+        assert_eq!(f64::from_json(&items[4]), Ok(2.5));
+        assert_eq!(f64::from_json(&items[5]), Ok(f64::INFINITY));
+
+        let raw = JsonValue::parse_with(
+            "123456789012345678901234567890",
+            &crate::ParserOptions::new().lossless_numbers(true),
+        )
+        .unwrap();
+        assert_eq!(u128::from_json(&raw), Ok(123456789012345678901234567890));
+        assert!(u64::from_json(&raw).is_err());
+    }
+
+    #[test]
+    fn test_convert_errors() {
+        let value = JsonValue::parse(r#"{"a/b": [[1, 2], [3]], "c": {"x~": "y"}}"#).unwrap();
+
+        let error = BTreeMap::<String, Vec<(u8, u8)>>::from_json(&value).unwrap_err();
+        assert_eq!(
+            error.kind,
+            FromJsonErrorKind::InvalidLength {
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(error.path, "/a~1b/1");
+
+        let JsonValue::Object(members) = &value else {
+            unreachable!()
+        };
+        let error = BTreeMap::<String, u8>::from_json(&members["c"]).unwrap_err();
+        assert_eq!(error.to_string(), "Expected u8, found a string at /x~0");
+
+        let error = BTreeMap::<u8, JsonValue>::from_json(&members["c"]).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid key 'x~' at /x~0");
+
+        let error = char::from_json(&JsonValue::String("ab".into())).unwrap_err();
+        assert_eq!(error.message, "Expected a single character, found a string");
+        assert_eq!(error.path, "");
+
+        assert_eq!(
+            u8::from_missing_field("id").unwrap_err().message,
+            "Missing field 'id'"
+        );
+        assert_eq!(Option::<u8>::from_missing_field("id"), Ok(None));
+    }
+}
@@ -0,0 +1,967 @@
+//! Contains the serde `Deserializer`, which drives a `StreamingParser`
+//! straight into any type that implements `serde::Deserialize`.
+//!
+//! No `JsonValue` is built along the way: each `ParserEvent` is handed to
+//! the type's `Visitor` as soon as it is parsed. Strings without escapes
+//! are borrowed from the input, so `&str` fields cost no allocation.
+//!
+//! This module is only available with the `serde` feature.
+
+use crate::{
+    parse_streaming_with, ErrorKind, JsonNumber, JsonValue, ParseError, ParserEvent, ParserOptions,
+    RawNumber, Span, SpannedEvent, StreamingParser, Wtf8String,
+};
+use serde::de::value::BytesDeserializer;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Unexpected, Visitor};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Deserializes an instance of `T` from a JSON string.
+///
+/// Borrowed fields (`&str`, `Cow<str>` with `#[serde(borrow)]`) borrow
+/// from `input` whenever the JSON string contains no escapes.
+///
+/// # Errors
+/// Returns a `ParseError` if the input is not valid JSON, or if it does
+/// not match `T` (an `ErrorKind::Custom` pointing at the offending value).
+///
+/// # Examples
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct User<'a> {
+///     name: &'a str,
+///     id: u32,
+///     email: Option<String>,
+/// }
+///
+/// let user: User = rill_json::from_str(r#"{"name": "Babbage", "id": 1815}"#).unwrap();
+/// assert_eq!(user, User { name: "Babbage", id: 1815, email: None });
+///
+/// let err = rill_json::from_str::<User>("{\n  \"name\": \"Ada\",\n  \"id\": -1\n}").unwrap_err();
+/// assert_eq!(err.message, "invalid value: integer `-1`, expected u32");
+/// assert_eq!((err.line, err.column), (3, 9));
+/// ```
+pub fn from_str<'a, T: de::Deserialize<'a>>(input: &'a str) -> Result<T, ParseError> {
+    from_str_with(input, &ParserOptions::default())
+}
+
+/// Deserializes an instance of `T` from a JSON string, enforcing the given
+/// `ParserOptions`.
+///
+/// # Errors
+/// See `from_str`. The limits in `options` are reported as they would be
+/// by `parse_streaming_with`.
+pub fn from_str_with<'a, T: de::Deserialize<'a>>(
+    input: &'a str,
+    options: &ParserOptions,
+) -> Result<T, ParseError> {
+    let mut deserializer = Deserializer::new(parse_streaming_with(input, options)?);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// A serde `Deserializer` that pulls its data from a `StreamingParser`.
+///
+/// Most code should call `from_str` instead. Use this directly to
+/// deserialize from a parser you configured yourself; call `end` afterwards
+/// to check that nothing follows the value.
+///
+/// # Examples
+/// ```
+/// use rill_json::de::Deserializer;
+/// use rill_json::parse_streaming;
+/// use serde::Deserialize;
+///
+/// let mut deserializer = Deserializer::new(parse_streaming("[1, 2, 3]").unwrap());
+/// let numbers = Vec::<u8>::deserialize(&mut deserializer).unwrap();
+/// deserializer.end().unwrap();
+/// assert_eq!(numbers, [1, 2, 3]);
+/// ```
+pub struct Deserializer<'de> {
+    /// The source of events.
+    parser: StreamingParser<'de>,
+    /// An event that was looked at but not consumed yet.
+    peeked: Option<SpannedEvent<'de>>,
+    /// The location of the most recently consumed event, used to locate
+    /// the errors raised by `Deserialize` implementations.
+    span: Span,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a `Deserializer` that reads from `parser`.
+    pub fn new(parser: StreamingParser<'de>) -> Self {
+        Deserializer {
+            parser,
+            peeked: None,
+            span: Span {
+                start: 0,
+                end: 0,
+                line: 1,
+                column: 1,
+            },
+        }
+    }
+
+    /// Checks that the input ends after the deserialized value.
+    ///
+    /// # Errors
+    /// Returns a `ParseError` if a token follows the value.
+    pub fn end(mut self) -> Result<(), ParseError> {
+        if self.peeked.is_some() {
+            return Err(self.error(ErrorKind::TrailingToken));
+        }
+        match self.parser.next_spanned() {
+            None => Ok(()),
+            Some(Err(e)) => Err(e),
+            Some(Ok(spanned)) => {
+                self.span = spanned.span;
+                Err(self.error(ErrorKind::TrailingToken))
+            }
+        }
+    }
+
+    /// Returns the next event without consuming it.
+    fn peek(&mut self) -> Result<&ParserEvent<'de>, ParseError> {
+        if self.peeked.is_none() {
+            match self.parser.next_spanned() {
+                Some(Ok(spanned)) => self.peeked = Some(spanned),
+                Some(Err(e)) => return Err(e),
+                None => return Err(self.end_of_input()),
+            }
+        }
+        Ok(&self
+            .peeked
+            .as_ref()
+            .expect("an event was just peeked")
+            .event)
+    }
+
+    /// Consumes the next event.
+    fn next_event(&mut self) -> Result<ParserEvent<'de>, ParseError> {
+        let spanned = match self.peeked.take() {
+            Some(spanned) => spanned,
+            None => match self.parser.next_spanned() {
+                Some(result) => result?,
+                None => return Err(self.end_of_input()),
+            },
+        };
+        self.span = spanned.span;
+        Ok(spanned.event)
+    }
+
+    /// Creates the error for running out of events. The parser only ends
+    /// early for an empty document; it reports every other truncation.
+    fn end_of_input(&self) -> ParseError {
+        // Every event spans at least one byte.
+        if self.span.end == 0 {
+            ParseError::new(ErrorKind::EmptyInput, 1, 1, 0)
+        } else {
+            self.error(ErrorKind::UnexpectedEndOfInput)
+        }
+    }
+
+    /// Returns the location of the most recent event, peeked or consumed.
+    fn location(&self) -> Span {
+        self.peeked.as_ref().map_or(self.span, |peeked| peeked.span)
+    }
+
+    /// Creates an error located at the most recent event.
+    fn error(&self, kind: ErrorKind) -> ParseError {
+        let span = self.location();
+        ParseError::new(kind, span.line, span.column, span.start)
+    }
+
+    /// Locates an error raised by a `Deserialize` implementation, which
+    /// knows nothing about the input, at the most recent event. Errors
+    /// that already have a location are returned unchanged.
+    fn locate(&self, error: ParseError) -> ParseError {
+        if error.line != 0 {
+            return error;
+        }
+        let span = self.location();
+        ParseError {
+            line: span.line,
+            column: span.column,
+            offset: span.start,
+            ..error
+        }
+    }
+
+    /// Consumes the event that closes an array or object after its visitor
+    /// returned, failing if the visitor stopped before the end.
+    fn end_container(&mut self, close: ParserEvent<'static>) -> Result<(), ParseError> {
+        let event = self.next_event()?;
+        if event == close {
+            return Ok(());
+        }
+        let message = if close == ParserEvent::EndArray {
+            "trailing elements in array"
+        } else {
+            "trailing members in object"
+        };
+        Err(self.error(ErrorKind::Custom(message.to_string())))
+    }
+}
+
+impl de::Error for ParseError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        // The `Deserializer` fills in the location (see `locate`).
+        ParseError::new(ErrorKind::Custom(msg.to_string()), 0, 0, 0)
+    }
+}
+
+/// Describes an event for serde's "invalid type" errors.
+fn unexpected<'a>(event: &'a ParserEvent<'_>) -> Unexpected<'a> {
+    match event {
+        ParserEvent::Null => Unexpected::Unit,
+        ParserEvent::Boolean(b) => Unexpected::Bool(*b),
+        ParserEvent::Number(JsonNumber::I64(n)) => Unexpected::Signed(*n),
+        ParserEvent::Number(JsonNumber::U64(n)) => Unexpected::Unsigned(*n),
+        ParserEvent::Number(JsonNumber::F64(n)) => Unexpected::Float(*n),
+        ParserEvent::Number(JsonNumber::Raw(_)) => Unexpected::Other("number"),
+        ParserEvent::String(s) => Unexpected::Str(s),
+        ParserEvent::WtfString(s) => Unexpected::Bytes(s.as_bytes()),
+        ParserEvent::StartArray => Unexpected::Seq,
+        ParserEvent::StartObject => Unexpected::Map,
+        ParserEvent::Key(_)
+        | ParserEvent::WtfKey(_)
+        | ParserEvent::EndArray
+        | ParserEvent::EndObject => Unexpected::Other("end of value"),
+    }
+}
+
+/// Hands a number to a visitor, picking the narrowest matching type for
+/// lossless (`JsonNumber::Raw`) numbers.
+fn visit_number<'de, V: Visitor<'de>>(
+    number: JsonNumber,
+    visitor: V,
+) -> Result<V::Value, ParseError> {
+    match number {
+        JsonNumber::I64(n) => visitor.visit_i64(n),
+        JsonNumber::U64(n) => visitor.visit_u64(n),
+        JsonNumber::F64(n) => visitor.visit_f64(n),
+        JsonNumber::Raw(raw) if raw.get().contains(['.', 'e', 'E']) => {
+            // `to_f64` refuses to overflow; a plain float parse saturates.
+            let n = raw
+                .get()
+                .parse()
+                .expect("Internal error: invalid raw number");
+            visitor.visit_f64(n)
+        }
+        JsonNumber::Raw(raw) => {
+            if let Some(n) = raw.to_i64() {
+                visitor.visit_i64(n)
+            } else if let Some(n) = raw.to_u64() {
+                visitor.visit_u64(n)
+            } else if let Some(n) = raw.to_i128() {
+                visitor.visit_i128(n)
+            } else if let Some(n) = raw.to_u128() {
+                visitor.visit_u128(n)
+            } else {
+                let kind = ErrorKind::IntegerOutOfRange(raw.get().to_string());
+                Err(ParseError::new(kind, 0, 0, 0))
+            }
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = ParseError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        let result = match self.next_event()? {
+            ParserEvent::Null => visitor.visit_unit(),
+            ParserEvent::Boolean(b) => visitor.visit_bool(b),
+            ParserEvent::Number(n) => visit_number(n, visitor),
+            ParserEvent::String(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            ParserEvent::String(Cow::Owned(s)) => visitor.visit_string(s),
+            // A `str` cannot hold a lone surrogate: hand over the WTF-8.
+            ParserEvent::WtfString(s) => visitor.visit_byte_buf(s.into_bytes()),
+            ParserEvent::StartArray => visitor
+                .visit_seq(SeqAccess { de: self })
+                .and_then(|value| self.end_container(ParserEvent::EndArray).map(|()| value)),
+            ParserEvent::StartObject => visitor
+                .visit_map(MapAccess { de: self })
+                .and_then(|value| self.end_container(ParserEvent::EndObject).map(|()| value)),
+            ParserEvent::Key(_)
+            | ParserEvent::WtfKey(_)
+            | ParserEvent::EndArray
+            | ParserEvent::EndObject => Err(self.error(ErrorKind::ExpectedValue)),
+        };
+        result.map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        let result = if *self.peek()? == ParserEvent::Null {
+            self.next_event()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(&mut *self)
+        };
+        result.map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Enums are externally tagged, as with serde_json: a unit variant is
+    /// a string (`"Red"`), any other variant an object with a single key
+    /// (`{"Rgb": [255, 0, 0]}`).
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        let result = match self.next_event()? {
+            ParserEvent::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            ParserEvent::StartObject => visitor
+                .visit_enum(VariantAccess { de: self })
+                .and_then(|value| self.end_container(ParserEvent::EndObject).map(|()| value)),
+            event => Err(de::Error::invalid_type(unexpected(&event), &visitor)),
+        };
+        result.map_err(|e| self.locate(e))
+    }
+
+    /// Skips unknown fields at the byte level, without building events.
+    /// The skipped value is still checked against the full grammar.
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        if self.peeked.is_none() && self.parser.skip_value()? {
+            visitor.visit_unit()
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Gives a visitor the elements of an array, up to its `]`.
+struct SeqAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_, 'de> {
+    type Error = ParseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ParseError> {
+        if *self.de.peek()? == ParserEvent::EndArray {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+/// Gives a visitor the members of an object, up to its `}`.
+struct MapAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_, 'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ParseError> {
+        if *self.de.peek()? == ParserEvent::EndObject {
+            return Ok(None);
+        }
+        match self.de.next_event()? {
+            ParserEvent::Key(key) => seed
+                .deserialize(KeyDeserializer { key })
+                .map(Some)
+                .map_err(|e| self.de.locate(e)),
+            ParserEvent::WtfKey(key) => seed
+                .deserialize(BytesDeserializer::new(key.as_bytes()))
+                .map(Some)
+                .map_err(|e| self.de.locate(e)),
+            _ => Err(self.de.error(ErrorKind::ExpectedKey)),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ParseError> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// Gives a visitor the variant of an enum written as `{"Variant": value}`.
+struct VariantAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess<'_, 'de> {
+    type Error = ParseError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), ParseError> {
+        match self.de.next_event()? {
+            ParserEvent::Key(key) => {
+                let variant = seed
+                    .deserialize(KeyDeserializer { key })
+                    .map_err(|e| self.de.locate(e))?;
+                Ok((variant, self))
+            }
+            ParserEvent::WtfKey(key) => {
+                let variant = seed
+                    .deserialize(BytesDeserializer::new(key.as_bytes()))
+                    .map_err(|e| self.de.locate(e))?;
+                Ok((variant, self))
+            }
+            _ => Err(self.de.error(ErrorKind::ExpectedKey)),
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'_, 'de> {
+    type Error = ParseError;
+
+    /// `{"Variant": null}` is accepted for a unit variant.
+    fn unit_variant(self) -> Result<(), ParseError> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ParseError> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+/// Deserializes an object key. Keys are always strings in JSON, but map
+/// keys of integer types are parsed from them, so `{"1": "a"}` can become
+/// a `HashMap<u32, String>`.
+struct KeyDeserializer<'de> {
+    key: Cow<'de, str>,
+}
+
+/// Implements the integer methods of `KeyDeserializer`.
+macro_rules! deserialize_integer_key {
+    ($($method:ident => $visit:ident,)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+            match self.key.parse() {
+                Ok(n) => visitor.$visit(n),
+                Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&self.key), &visitor)),
+            }
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = ParseError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        match self.key {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
+        }
+    }
+
+    deserialize_integer_key! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        visitor.visit_enum(self.key.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Builds `JsonValue`s and `JsonNumber`s from any serde `Deserializer`.
+///
+/// Integers are classified the way the parser classifies them: `I64` if
+/// they fit, `U64` for larger positive values, and a `Raw` number for
+/// 128-bit integers beyond both.
+struct JsonValueVisitor;
+
+impl JsonValueVisitor {
+    fn from_u128(n: u128) -> JsonNumber {
+        match u64::try_from(n) {
+            Ok(n) => Self::from_i128(n.into()),
+            Err(_) => JsonNumber::Raw(RawNumber::from_source(&n.to_string())),
+        }
+    }
+
+    fn from_i128(n: i128) -> JsonNumber {
+        if let Ok(n) = i64::try_from(n) {
+            JsonNumber::I64(n)
+        } else if let Ok(n) = u64::try_from(n) {
+            JsonNumber::U64(n)
+        } else {
+            JsonNumber::Raw(RawNumber::from_source(&n.to_string()))
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for JsonValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Boolean(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(JsonNumber::I64(v)))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(Self::from_i128(v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(Self::from_i128(v.into())))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(Self::from_u128(v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(JsonNumber::F64(v)))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(v))
+    }
+
+    /// WTF-8 bytes, as `LoneSurrogatePolicy::Preserve` hands them over, are
+    /// a string.
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<JsonValue, E> {
+        self.visit_byte_buf(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<JsonValue, E> {
+        Ok(match Wtf8StringVisitor.visit_byte_buf(v)?.into_string() {
+            Ok(s) => JsonValue::String(s),
+            Err(s) => JsonValue::WtfString(s),
+        })
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(value) = seq.next_element()? {
+            array.push(value);
+        }
+        Ok(JsonValue::Array(array))
+    }
+
+    /// As with `JsonValue::parse`, the last of several equal keys wins.
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut object = BTreeMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            object.insert(key, value);
+        }
+        Ok(JsonValue::Object(object))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for JsonValue {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonValueVisitor)
+    }
+}
+
+/// Builds a `Wtf8String` from a string or from WTF-8 bytes.
+struct Wtf8StringVisitor;
+
+impl Visitor<'_> for Wtf8StringVisitor {
+    type Value = Wtf8String;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string or WTF-8 bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Wtf8String, E> {
+        Ok(Wtf8String::from(v))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Wtf8String, E> {
+        Ok(Wtf8String::from(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Wtf8String, E> {
+        self.visit_byte_buf(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Wtf8String, E> {
+        Wtf8String::from_wtf8(v)
+            .ok_or_else(|| de::Error::invalid_value(Unexpected::Other("invalid WTF-8"), &self))
+    }
+}
+
+/// Accepts a string, or the WTF-8 bytes of a string or key holding a lone
+/// surrogate, as this crate's deserializer produces them under
+/// `LoneSurrogatePolicy::Preserve`.
+impl<'de> de::Deserialize<'de> for Wtf8String {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(Wtf8StringVisitor)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for JsonNumber {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match deserializer.deserialize_any(JsonValueVisitor)? {
+            JsonValue::Number(n) => Ok(n),
+            _ => Err(de::Error::custom("expected a JSON number")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Record<'a> {
+        name: &'a str,
+        label: Cow<'a, str>,
+        count: u8,
+        ratio: f64,
+        tags: Vec<String>,
+        parent: Option<Box<Record<'a>>>,
+        #[serde(default)]
+        flag: bool,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Point(i32, i32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[test]
+    fn test_deserialize_struct() {
+        let input = r#"{
+            "name": "root", "label": "a\tb", "count": 3, "ratio": 0.5,
+            "tags": ["x", "y"], "unknown": {"deep": [1, {"x": null}]},
+            "parent": {"name": "p", "label": "l", "count": 0, "ratio": -1,
+                       "tags": [], "parent": null, "flag": true}
+        }"#;
+        let record: Record = from_str(input).unwrap();
+        assert_eq!(record.name, "root");
+        // Escaped strings are unescaped into an owned buffer.
+        assert!(matches!(record.label, Cow::Owned(ref s) if s == "a\tb"));
+        assert_eq!(record.count, 3);
+        assert_eq!(record.ratio, 0.5);
+        assert_eq!(record.tags, ["x", "y"]);
+        assert!(!record.flag);
+        let parent = record.parent.unwrap();
+        assert_eq!((parent.name, parent.ratio, parent.flag), ("p", -1.0, true));
+        assert_eq!(parent.parent, None);
+    }
+
+    #[test]
+    fn test_deserialize_borrowed_str() {
+        let name: &str = from_str(r#""plain""#).unwrap();
+        assert_eq!(name, "plain");
+
+        // An escaped string cannot be borrowed from the input.
+        let err = from_str::<&str>(r#""esc\"aped""#).unwrap_err();
+        assert_eq!(
+            err.message,
+            "invalid type: string \"esc\\\"aped\", expected a borrowed string"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_lone_surrogates() {
+        let options = ParserOptions::new().lone_surrogates(crate::LoneSurrogatePolicy::Preserve);
+        let input = r#"{"k\uDC00": ["\uD800", "x"], "y": []}"#;
+        let map: BTreeMap<Wtf8String, Vec<Wtf8String>> = from_str_with(input, &options).unwrap();
+        let key = Wtf8String::from_utf16(&[0x6B, 0xDC00]);
+        assert_eq!(
+            map[&key],
+            [Wtf8String::from_utf16(&[0xD800]), Wtf8String::from("x")]
+        );
+        assert_eq!(map[&Wtf8String::from("y")], []);
+
+        let value: JsonValue = from_str_with(r#"["\uD800", "x"]"#, &options).unwrap();
+        assert_eq!(
+            value,
+            JsonValue::Array(vec![
+                JsonValue::WtfString(Wtf8String::from_utf16(&[0xD800])),
+                JsonValue::String("x".to_string()),
+            ])
+        );
+
+        // A `String` cannot hold the surrogate.
+        let err = from_str_with::<String>(r#""\uD800""#, &options).unwrap_err();
+        assert_eq!(err.message, "invalid value: byte array, expected a string");
+        assert_eq!(err.offset, 0);
+    }
+
+    #[test]
+    fn test_deserialize_enums() {
+        let shapes: Vec<Shape> = from_str(
+            r#"["Empty", {"Empty": null}, {"Circle": 1.5}, {"Point": [1, -2]},
+                {"Rect": {"w": 3, "h": 4}}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            shapes,
+            [
+                Shape::Empty,
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Point(1, -2),
+                Shape::Rect { w: 3, h: 4 },
+            ]
+        );
+
+        let err = from_str::<Shape>(r#""Hexagon""#).unwrap_err();
+        assert!(err.message.starts_with("unknown variant `Hexagon`"));
+        let err = from_str::<Shape>(r#"{"Circle": 1, "Empty": null}"#).unwrap_err();
+        assert_eq!(err.message, "trailing members in object");
+        assert_eq!(err.offset, 14);
+        let err = from_str::<Shape>("[]").unwrap_err();
+        assert_eq!(err.message, "invalid type: sequence, expected enum Shape");
+    }
+
+    #[test]
+    fn test_deserialize_any() {
+        let input = r#"{"a": [1, -2, 3.5, 18446744073709551615], "b": {"c": null, "d": "é"}}"#;
+        let value: serde_json::Value = from_str(input).unwrap();
+        assert_eq!(
+            value,
+            serde_json::from_str::<serde_json::Value>(input).unwrap()
+        );
+
+        // Lossless numbers are handed over as the narrowest type.
+        let options = ParserOptions::new().lossless_numbers(true);
+        let numbers: Vec<serde_json::Value> =
+            from_str_with("[1, -1, 1.5, 18446744073709551615]", &options).unwrap();
+        assert_eq!(
+            numbers,
+            [
+                serde_json::json!(1),
+                serde_json::json!(-1),
+                serde_json::json!(1.5),
+                serde_json::json!(u64::MAX)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_maps_and_tuples() {
+        let map: HashMap<u32, String> = from_str(r#"{"1": "a", "20": "b"}"#).unwrap();
+        assert_eq!(map[&20], "b");
+        let err = from_str::<HashMap<u32, String>>(r#"{"x": "a"}"#).unwrap_err();
+        assert_eq!(err.message, "invalid value: string \"x\", expected u32");
+        assert_eq!(err.offset, 1);
+
+        let map: BTreeMap<&str, (u8, bool)> = from_str(r#"{"k": [7, true]}"#).unwrap();
+        assert_eq!(map["k"], (7, true));
+        let err = from_str::<(u8, bool)>("[7, true, null]").unwrap_err();
+        assert_eq!(err.message, "trailing elements in array");
+        assert_eq!(err.offset, 10);
+        let err = from_str::<(u8, bool)>("[7]").unwrap_err();
+        assert_eq!(err.message, "invalid length 1, expected a tuple of size 2");
+    }
+
+    #[test]
+    fn test_deserialize_json_value() {
+        let input =
+            r#"{"a": [1, -2, 3.5, 18446744073709551615, "s"], "b": {"c": null}, "a": true}"#;
+        let value: JsonValue = from_str(input).unwrap();
+        assert_eq!(value, JsonValue::parse(input).unwrap());
+
+        // Through another format, the numbers keep their type.
+        let numbers: Vec<JsonNumber> =
+            serde_json::from_str("[1, -1, 1.0, 18446744073709551615]").unwrap();
+        assert_eq!(
+            numbers,
+            [
+                JsonNumber::I64(1),
+                JsonNumber::I64(-1),
+                JsonNumber::F64(1.0),
+                JsonNumber::U64(u64::MAX)
+            ]
+        );
+        let big: JsonValue = de::Deserialize::deserialize(de::value::U128Deserializer::<
+            de::value::Error,
+        >::new(u128::MAX))
+        .unwrap();
+        assert_eq!(big.stringify().unwrap(), u128::MAX.to_string());
+
+        let err = from_str::<JsonNumber>(r#""1""#).unwrap_err();
+        assert_eq!(err.message, "expected a JSON number");
+        assert_eq!(err.offset, 0);
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        // Type errors point at the offending value.
+        let err = from_str::<Vec<u8>>("[1,\n 2,\n 300]").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Custom(err.message.clone()));
+        assert_eq!(err.message, "invalid value: integer `300`, expected u8");
+        assert_eq!((err.line, err.column, err.offset), (3, 2, 9));
+
+        let err = from_str::<Record>(r#"{"name": "n"}"#).unwrap_err();
+        assert_eq!(err.message, "missing field `label`");
+        assert_eq!(err.offset, 12);
+
+        // Syntax errors are reported as by the parser.
+        let err = from_str::<Vec<u8>>("[1, 2").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnclosedArray);
+        let err = from_str::<u8>("1 2").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TrailingToken);
+        let err = from_str::<u8>("").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::EmptyInput);
+
+        let options = ParserOptions::new().max_depth(1);
+        let err = from_str_with::<Vec<Vec<u8>>>("[[1]]", &options).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DepthLimitExceeded);
+
+        // Ignored fields are skipped, but still checked.
+        #[derive(Deserialize, Debug)]
+        struct S {
+            #[allow(dead_code)]
+            a: u8,
+        }
+        let cases = [
+            (
+                r#"{"a":1,"zz":[1 2 3]}"#,
+                ErrorKind::ExpectedCommaOrArrayEnd,
+            ),
+            (r#"{"a":1,"zz":{"k" 1}}"#, ErrorKind::ExpectedColon),
+            (r#"{"a":1,"zz":[,,]}"#, ErrorKind::ExpectedValueOrArrayEnd),
+        ];
+        for (input, kind) in cases {
+            assert_eq!(
+                from_str::<S>(input).unwrap_err().kind,
+                kind,
+                "input {:?}",
+                input
+            );
+        }
+
+        // So are the parser's limits.
+        let cases = [
+            (
+                ParserOptions::new().max_string_length(3),
+                r#"{"a":1,"zz":["abcd"]}"#,
+                ErrorKind::StringTooLong,
+            ),
+            (
+                ParserOptions::new().max_key_length(3),
+                r#"{"a":1,"zz":{"abcd":1}}"#,
+                ErrorKind::KeyTooLong,
+            ),
+            (
+                ParserOptions::new().max_array_members(2),
+                r#"{"a":1,"zz":[1,2,3]}"#,
+                ErrorKind::TooManyArrayMembers,
+            ),
+            (
+                ParserOptions::new().max_object_members(2),
+                r#"{"a":1,"zz":{"x":1,"y":2,"z":3}}"#,
+                ErrorKind::TooManyObjectMembers,
+            ),
+            (
+                ParserOptions::new().max_events(6),
+                r#"{"a":1,"zz":[1,2,3]}"#,
+                ErrorKind::TooManyEvents,
+            ),
+        ];
+        for (options, input, kind) in cases {
+            assert_eq!(
+                from_str_with::<S>(input, &options).unwrap_err().kind,
+                kind,
+                "input {:?}",
+                input
+            );
+        }
+    }
+}
@@ -1,18 +1,378 @@
 //! Contains the primary `ParseError` type for the library.
-use std::fmt;
+use std::fmt::{self, Write};
+
+/// A machine-readable classification of everything that can go wrong
+/// while parsing.
+///
+/// Match on this instead of on `ParseError::message` to react to specific
+/// failures programmatically. Its `Display` implementation produces the
+/// human-readable message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    // --- Lexical errors (tokenizer) ---
+    /// A byte that cannot start any JSON token.
+    UnexpectedCharacter(char),
+    /// A literal (`true`, `false` or `null`) was misspelled.
+    ExpectedLiteral(&'static str),
+    /// A string was not closed before the end of input.
+    UnterminatedString,
+    /// A `/* */` comment was not closed before the end of input (JSON5).
+    UnterminatedComment,
+    /// A raw control character (U+0000 to U+001F) inside a string.
+    ControlCharacterInString,
+    /// An unknown escape sequence, such as `\z`.
+    InvalidEscape,
+    /// A `\u` escape with fewer than four hex digits.
+    IncompleteUnicodeEscape,
+    /// A `\u` escape containing a non-hex digit.
+    InvalidUnicodeEscape,
+    /// A `\u` escape that does not encode a valid Unicode scalar value.
+    InvalidCodePoint,
+    /// A `\u` escape holding half of a UTF-16 surrogate pair without the
+    /// other half (see `LoneSurrogatePolicy`).
+    LoneSurrogate,
+    /// A string that is not valid UTF-8.
+    InvalidUtf8,
+    /// A number with a superfluous leading zero, such as `0123`.
+    LeadingZero,
+    /// A `-` not followed by a digit, such as `-` or `--1`.
+    ExpectedIntegerDigit,
+    /// A decimal point not followed by a digit, such as `1.` or `1.e5`.
+    TrailingDecimalPoint,
+    /// An exponent without digits, such as `1e` or `1e+`.
+    ExpectedExponentDigit,
+    /// A number followed by a character that cannot continue it, such as
+    /// the second `.` in `1.2.3` or the `-` in `1-2`.
+    UnexpectedCharacterInNumber(char),
+    /// A malformed number. Holds the offending text.
+    InvalidNumber(String),
+    /// An integer outside the `i64`/`u64` range, or a JSON5 hexadecimal
+    /// integer beyond `u128` in lossless mode. Holds the offending text.
+    IntegerOutOfRange(String),
+
+    // --- Grammar errors (parser) ---
+    /// A value was expected.
+    ExpectedValue,
+    /// A value or `]` was expected after `[`.
+    ExpectedValueOrArrayEnd,
+    /// A `]` directly followed a comma, as in `[1,]`.
+    TrailingCommaInArray,
+    /// A `,` or `]` was expected after an array member.
+    ExpectedCommaOrArrayEnd,
+    /// A string key or `}` was expected after `{`.
+    ExpectedKeyOrObjectEnd,
+    /// A `}` directly followed a comma, as in `{"a":1,}`.
+    TrailingCommaInObject,
+    /// A string key was expected after a comma.
+    ExpectedKey,
+    /// A `:` was expected after an object key.
+    ExpectedColon,
+    /// A `,` or `}` was expected after an object member.
+    ExpectedCommaOrObjectEnd,
+    /// A token followed the end of the root value.
+    TrailingToken,
+    /// The input ended inside an object.
+    UnclosedObject,
+    /// The input ended inside an array.
+    UnclosedArray,
+    /// The input ended while a value was still expected.
+    UnexpectedEndOfInput,
+    /// The input contained no value at all.
+    EmptyInput,
+    /// A JSON text sequence did not start with a record separator (0x1E).
+    MissingRecordSeparator,
+    /// A top-level number or literal in a JSON text sequence was not
+    /// followed by whitespace, so it may have been truncated (RFC 7464).
+    PossiblyTruncated,
+    /// `StreamingParser::next_raw_value` was called while parsing JSON5,
+    /// whose source text cannot be embedded in strict JSON output.
+    RawValueInJson5,
+
+    // --- Limits (see `ParserOptions`) ---
+    /// The maximum nesting depth was exceeded.
+    DepthLimitExceeded,
+    /// The maximum input size was exceeded.
+    SizeLimitExceeded,
+    /// A string value exceeded the maximum string length.
+    StringTooLong,
+    /// An object key exceeded the maximum key length.
+    KeyTooLong,
+    /// An array exceeded the maximum number of members.
+    TooManyArrayMembers,
+    /// An object exceeded the maximum number of members.
+    TooManyObjectMembers,
+    /// The document exceeded the maximum number of events.
+    TooManyEvents,
+
+    // --- Input/output ---
+    /// The underlying reader failed. Holds the I/O error's description.
+    Io(String),
+
+    // --- Data conversion ---
+    /// The JSON was well-formed but did not match the type it was
+    /// converted into, such as a missing field or a string where a number
+    /// was expected. Holds the description.
+    ///
+    /// When raised by the serde `Deserializer`, the error is located at
+    /// the value that could not be converted.
+    Custom(String),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedCharacter(c) => write!(f, "Unexpected character '{}'", c),
+            ErrorKind::ExpectedLiteral(literal) => write!(f, "Expected '{}'", literal),
+            ErrorKind::UnterminatedString => f.write_str("Unterminated string"),
+            ErrorKind::UnterminatedComment => f.write_str("Unterminated comment"),
+            ErrorKind::ControlCharacterInString => {
+                f.write_str("Unescaped control character in string")
+            }
+            ErrorKind::InvalidEscape => f.write_str("Invalid escape sequence"),
+            ErrorKind::IncompleteUnicodeEscape => f.write_str("Incomplete Unicode escape"),
+            ErrorKind::InvalidUnicodeEscape => f.write_str("Non-hex char in Unicode escape"),
+            ErrorKind::InvalidCodePoint => f.write_str("Invalid Unicode code point"),
+            ErrorKind::LoneSurrogate => f.write_str("Unpaired surrogate in Unicode escape"),
+            ErrorKind::InvalidUtf8 => f.write_str("Invalid UTF-8 in string"),
+            ErrorKind::LeadingZero => f.write_str("Invalid number: leading zeros not allowed"),
+            ErrorKind::ExpectedIntegerDigit => {
+                f.write_str("Invalid number: expected a digit after '-'")
+            }
+            ErrorKind::TrailingDecimalPoint => {
+                f.write_str("Invalid number: cannot end with a decimal point")
+            }
+            ErrorKind::ExpectedExponentDigit => {
+                f.write_str("Invalid number: expected a digit in the exponent")
+            }
+            ErrorKind::UnexpectedCharacterInNumber(c) => {
+                write!(f, "Invalid number: unexpected '{}'", c)
+            }
+            ErrorKind::InvalidNumber(text) => write!(f, "Invalid number '{}'", text),
+            ErrorKind::IntegerOutOfRange(text) => write!(f, "Integer '{}' is out of range", text),
+            ErrorKind::ExpectedValue => f.write_str("Expected a value"),
+            ErrorKind::ExpectedValueOrArrayEnd => f.write_str("Expected value or ']'"),
+            ErrorKind::TrailingCommaInArray => f.write_str("Unexpected ']', expected a value"),
+            ErrorKind::ExpectedCommaOrArrayEnd => f.write_str("Expected ',' or ']'"),
+            ErrorKind::ExpectedKeyOrObjectEnd => f.write_str("Expected '}' or a string key"),
+            ErrorKind::TrailingCommaInObject => {
+                f.write_str("Unexpected '}', expected a string key")
+            }
+            ErrorKind::ExpectedKey => f.write_str("Expected a string key"),
+            ErrorKind::ExpectedColon => f.write_str("Expected ':'"),
+            ErrorKind::ExpectedCommaOrObjectEnd => f.write_str("Expected ',' or '}'"),
+            ErrorKind::TrailingToken => f.write_str("Unexpected trailing token"),
+            ErrorKind::UnclosedObject => f.write_str("Unclosed object"),
+            ErrorKind::UnclosedArray => f.write_str("Unclosed array"),
+            ErrorKind::UnexpectedEndOfInput => f.write_str("Unexpected end of input"),
+            ErrorKind::MissingRecordSeparator => f.write_str("Expected a record separator (0x1E)"),
+            ErrorKind::PossiblyTruncated => f.write_str("Top-level value may be truncated"),
+            ErrorKind::RawValueInJson5 => f.write_str("Raw values are not supported in JSON5"),
+            ErrorKind::EmptyInput => f.write_str("Empty input"),
+            ErrorKind::DepthLimitExceeded => f.write_str("Maximum nesting depth exceeded"),
+            ErrorKind::SizeLimitExceeded => f.write_str("Input exceeds maximum size limit"),
+            ErrorKind::StringTooLong => f.write_str("String exceeds maximum length"),
+            ErrorKind::KeyTooLong => f.write_str("Key exceeds maximum length"),
+            ErrorKind::TooManyArrayMembers => {
+                f.write_str("Array exceeds maximum number of members")
+            }
+            ErrorKind::TooManyObjectMembers => {
+                f.write_str("Object exceeds maximum number of members")
+            }
+            ErrorKind::TooManyEvents => f.write_str("Maximum number of events exceeded"),
+            ErrorKind::Io(description) => write!(f, "I/O error: {}", description),
+            ErrorKind::Custom(description) => f.write_str(description),
+        }
+    }
+}
 
 /// The primary error type for all parsing operations.
 ///
-/// This struct contains a human-readable error message and the
-/// location (line and column) where the error occurred.
-#[derive(Debug, PartialEq)]
+/// This struct contains a machine-readable `ErrorKind`, the human-readable
+/// message derived from it, and the location where the error occurred.
+///
+/// The location is given both as a byte offset and as a line and column.
+/// Lines are separated by `\n`. Columns count Unicode scalar values
+/// (`char`s), not bytes or UTF-16 code units, so `"é"` advances the column
+/// by one even though it is two bytes long.
+///
+/// Errors caused by the input ending inside an array or object (e.g.
+/// `ErrorKind::UnclosedArray`) point at the opening bracket that was never
+/// closed.
+#[derive(Debug, Clone, PartialEq)]
 pub struct ParseError {
-    /// A description of what went wrong.
+    /// What went wrong, in machine-readable form.
+    pub kind: ErrorKind,
+    /// A description of what went wrong (always `kind.to_string()`).
     pub message: String,
     /// The line number (1-indexed) where the error was detected.
     pub line: usize,
-    /// The column number (1-indexed) where the error was detected.
+    /// The column number (1-indexed, in `char`s) where the error was detected.
     pub column: usize,
+    /// The byte offset (0-indexed) into the input where the error was detected.
+    pub offset: usize,
+    /// An optional secondary location, such as "object opened here".
+    pub label: Option<Box<Label>>,
+}
+
+/// A secondary location attached to a `ParseError`.
+///
+/// The parser uses it to point at the opening bracket of the array or
+/// object an error occurred in. It is shown by `ParseError::render`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    /// A short description, e.g. `"object opened here"`.
+    pub message: String,
+    /// The line number (1-indexed) of the labelled location.
+    pub line: usize,
+    /// The column number (1-indexed, in `char`s) of the labelled location.
+    pub column: usize,
+    /// The byte offset (0-indexed) of the labelled location.
+    pub offset: usize,
+}
+
+impl ParseError {
+    /// Creates a new `ParseError`, deriving its message from `kind`.
+    pub fn new(kind: ErrorKind, line: usize, column: usize, offset: usize) -> Self {
+        ParseError {
+            message: kind.to_string(),
+            kind,
+            line,
+            column,
+            offset,
+            label: None,
+        }
+    }
+
+    /// Attaches a secondary label to the error.
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.label = Some(Box::new(label));
+        self
+    }
+
+    /// Moves the error (and its label) down by `lines` lines and `offset`
+    /// bytes. Used to locate an error from a single record within the
+    /// whole input.
+    ///
+    /// `columns` is added to locations on the record's first line, for
+    /// records that do not start at the beginning of a line.
+    pub(crate) fn shift(mut self, lines: usize, columns: usize, offset: usize) -> Self {
+        if self.line == 1 {
+            self.column += columns;
+        }
+        self.line += lines;
+        self.offset += offset;
+        if let Some(label) = self.label.as_mut() {
+            if label.line == 1 {
+                label.column += columns;
+            }
+            label.line += lines;
+            label.offset += offset;
+        }
+        self
+    }
+
+    /// Renders the error as a plain-text, compiler-style diagnostic.
+    ///
+    /// `source` must be the input that was parsed. The offending line is
+    /// printed with a caret under the error location, along with the
+    /// line of the secondary label, if any.
+    ///
+    /// # Examples
+    /// ```
+    /// use rill_json::JsonValue;
+    ///
+    /// let source = "{\n  \"id\": 1\n  \"name\": \"Ada\"\n}";
+    /// let err = JsonValue::parse(source).unwrap_err();
+    /// assert_eq!(
+    ///     err.render(source),
+    ///     "error: Expected ',' or '}'\n \
+    ///      --> 3:3\n  \
+    ///       |\n\
+    ///      1 | {\n  \
+    ///       | - object opened here\n\
+    ///      ...\n\
+    ///      3 |   \"name\": \"Ada\"\n  \
+    ///       |   ^ Expected ',' or '}'\n"
+    /// );
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        self.render_with(source, false)
+    }
+
+    /// Renders the error like `render`, but with ANSI colors for terminals.
+    pub fn render_ansi(&self, source: &str) -> String {
+        self.render_with(source, true)
+    }
+
+    /// Shared implementation of `render` and `render_ansi`.
+    fn render_with(&self, source: &str, color: bool) -> String {
+        // ANSI escape codes (or nothing, in plain-text mode).
+        let paint = |code: &'static str| if color { code } else { "" };
+        let (red, blue, bold, reset) = (
+            paint("\x1b[1;31m"),
+            paint("\x1b[1;34m"),
+            paint("\x1b[1m"),
+            paint("\x1b[0m"),
+        );
+
+        // Each annotation is (line, column, marker, color, text).
+        let mut annotations = vec![(self.line, self.column, '^', red, self.message.as_str())];
+        if let Some(label) = &self.label {
+            annotations.push((label.line, label.column, '-', blue, label.message.as_str()));
+        }
+        annotations.sort_by_key(|&(line, column, ..)| (line, column));
+
+        let lines: Vec<&str> = source.split('\n').collect();
+        let width = annotations
+            .iter()
+            .map(|&(line, ..)| line.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        // Writing to a `String` never fails, so the results are ignored.
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{}error{}{}: {}{}",
+            red, reset, bold, self.message, reset
+        );
+        let _ = writeln!(
+            out,
+            "{:width$}{}-->{} {}:{}",
+            "", blue, reset, self.line, self.column
+        );
+        let _ = writeln!(out, "{:width$} {}|{}", "", blue, reset);
+
+        let mut previous_line = None;
+        for &(line, column, marker, marker_color, text) in &annotations {
+            let source_line = match line.checked_sub(1).and_then(|i| lines.get(i)) {
+                Some(source_line) => source_line.trim_end_matches('\r'),
+                None => continue, // The source doesn't match the error.
+            };
+
+            if previous_line != Some(line) {
+                if previous_line.is_some_and(|previous| line > previous + 1) {
+                    let _ = writeln!(out, "{}...{}", blue, reset);
+                }
+                let _ = writeln!(out, "{}{:>width$} |{} {}", blue, line, reset, source_line);
+                previous_line = Some(line);
+            }
+
+            // Keep tabs so that the marker lines up with the source line.
+            let padding: String = source_line
+                .chars()
+                .take(column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let _ = writeln!(
+                out,
+                "{:width$} {}|{} {}{}{} {}{}",
+                "", blue, reset, padding, marker_color, marker, text, reset
+            );
+        }
+        out
+    }
 }
 
 // --- Error Formatting ---
@@ -27,6 +387,8 @@ impl fmt::Display for ParseError {
     }
 }
 
+impl std::error::Error for ParseError {}
+
 // --- Unit Tests ---
 #[cfg(test)]
 mod tests {
@@ -34,14 +396,82 @@ mod tests {
 
     #[test]
     fn test_error_display() {
-        let error = ParseError {
-            message: "Unexpected ']'".to_string(),
-            line: 10,
-            column: 5,
-        };
+        let error = ParseError::new(ErrorKind::TrailingCommaInArray, 10, 5, 120);
         assert_eq!(
             error.to_string(),
-            "Error: Unexpected ']' at line 10, column 5."
+            "Error: Unexpected ']', expected a value at line 10, column 5."
         );
     }
+
+    #[test]
+    fn test_error_message_derived_from_kind() {
+        let error = ParseError::new(ErrorKind::UnexpectedCharacter('?'), 1, 1, 0);
+        assert_eq!(error.message, "Unexpected character '?'");
+
+        let error = ParseError::new(ErrorKind::Io("broken pipe".to_string()), 1, 1, 0);
+        assert_eq!(error.message, "I/O error: broken pipe");
+
+        // `ParseError` plugs into the standard error machinery.
+        let boxed: Box<dyn std::error::Error> = Box::new(error);
+        assert!(boxed.to_string().contains("broken pipe"));
+    }
+
+    #[test]
+    fn test_error_render() {
+        // A single line, with the secondary label on the same line.
+        let error =
+            ParseError::new(ErrorKind::ExpectedCommaOrArrayEnd, 1, 7, 6).with_label(Label {
+                message: "array opened here".to_string(),
+                line: 1,
+                column: 1,
+                offset: 0,
+            });
+        assert_eq!(
+            error.render("[1, 2 3]"),
+            "error: Expected ',' or ']'\n \
+             --> 1:7\n  \
+              |\n\
+             1 | [1, 2 3]\n  \
+              | - array opened here\n  \
+              |       ^ Expected ',' or ']'\n"
+        );
+
+        // No label; tabs are preserved so the caret lines up.
+        let error = ParseError::new(ErrorKind::UnexpectedCharacter('?'), 2, 3, 4);
+        assert_eq!(
+            error.render("[\n\t\t?]"),
+            "error: Unexpected character '?'\n \
+             --> 2:3\n  \
+              |\n\
+             2 | \t\t?]\n  \
+              | \t\t^ Unexpected character '?'\n"
+        );
+
+        // A source that doesn't match the error still renders the header.
+        assert_eq!(
+            error.render(""),
+            "error: Unexpected character '?'\n --> 2:3\n  |\n"
+        );
+    }
+
+    #[test]
+    fn test_error_render_ansi() {
+        let error = ParseError::new(ErrorKind::UnclosedObject, 1, 1, 0);
+        let rendered = error.render_ansi("{");
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;31m^ Unclosed object\x1b[0m"));
+
+        // Stripping the escape codes gives back the plain rendering.
+        let mut stripped = String::new();
+        let mut in_escape = false;
+        for c in rendered.chars() {
+            match c {
+                '\x1b' => in_escape = true,
+                'm' if in_escape => in_escape = false,
+                _ if in_escape => {}
+                _ => stripped.push(c),
+            }
+        }
+        assert_eq!(stripped, error.render("{"));
+    }
 }
@@ -20,6 +20,12 @@
 //! * **Serializer Included:** Comes with `stringify()` and `stringify_pretty()`
 //!   to serialize your Rust data back to JSON.
 //! * **RFC 8259 Compliant:** Passes a full test suite for specification compliance.
+//! * **Serde Support:** With the optional `serde` feature, `from_str`
+//!   deserializes any `serde::Deserialize` type straight from the parser,
+//!   and `to_string` serializes any `serde::Serialize` type.
+//! * **Native Conversions:** The `ToJson` and `FromJson` traits convert
+//!   Rust types to and from `JsonValue` without serde, and the optional
+//!   `derive` feature derives them for your own structs and enums.
 //!
 //! ## Quick Start: 3 Ways to Use `rill-json`
 //!
@@ -87,26 +93,78 @@
 //! let pretty_string = json_object.stringify_pretty().unwrap();
 //! println!("{}", pretty_string);
 //! ```
+//!
+//! The `json!` macro builds the same value from JSON-like syntax, with Rust
+//! expressions for keys and values:
+//!
+//! ```
+//! use rill_json::json;
+//!
+//! let id = 1815;
+//! let json_object = json!({ "username": "ada_l", "id": id });
+//! assert_eq!(json_object.stringify().unwrap(), r#"{"id":1815,"username":"ada_l"}"#);
+//! ```
 
 // 1. Declare all the new modules.
+/// Contains the `ToJson` and `FromJson` conversion traits.
+pub mod convert;
+/// Contains the serde `Deserializer` (requires the `serde` feature).
+#[cfg(feature = "serde")]
+pub mod de;
 /// Contains the primary `ParseError` type for the library.
 pub mod error;
+/// Contains the `NdjsonParser` and `NdjsonReader` for newline-delimited JSON.
+pub mod ndjson;
+/// Contains `ParserOptions`, the configurable parsing limits.
+pub mod options;
 /// Contains the streaming `Parser` and its `ParserEvent` enum.
 pub mod parser;
+/// Contains the incremental, chunk-fed `PushParser`.
+pub mod push;
+/// Contains `RawValue` and `RawNumber`, JSON kept as its unparsed source text.
+pub mod raw;
+/// Contains the `ReaderParser`, which parses directly from `std::io::Read`.
+pub mod reader;
+/// Contains the `JsonSeqReader` and `JsonSeqWriter` for JSON text sequences.
+pub mod seq;
+/// Contains the serde `Serializer` (requires the `serde` feature).
+#[cfg(feature = "serde")]
+pub mod ser;
 /// Contains the `Token` and `TokenType` enums used internally.
 pub mod token;
 /// Contains the `JsonValue` enum and the serialization (stringify) logic.
 pub mod value;
+/// Contains `Wtf8String`, a string that can hold lone UTF-16 surrogates.
+pub mod wtf8;
 
+/// Fast, exact float parsing and shortest round-trip float formatting.
+/// This module is private to the crate.
+mod float;
+/// Defines the `json!` macro, exported at the crate root.
+mod macros;
 /// The internal, high-performance, byte-based tokenizer (lexer).
 /// This module is private to the crate.
 mod tokenizer;
 
 // 2. Re-export the public-facing types.
 // This creates the clean, top-level API for users.
-pub use error::ParseError;
-pub use parser::{ParserEvent, StreamingParser};
+pub use convert::{FromJson, FromJsonError, FromJsonErrorKind, ToJson};
+#[cfg(feature = "serde")]
+pub use de::{from_str, from_str_with};
+pub use error::{ErrorKind, Label, ParseError};
+pub use ndjson::{NdjsonError, NdjsonParser, NdjsonReader, NdjsonRecord, RejectedRecord};
+pub use options::{Dialect, LoneSurrogatePolicy, ParserOptions};
+pub use parser::{ParserEvent, Span, SpannedEvent, StreamingParser};
+pub use push::PushParser;
+pub use raw::{RawNumber, RawValue};
+pub use reader::ReaderParser;
+#[cfg(feature = "derive")]
+pub use rill_json_derive::{FromJson, ToJson};
+pub use seq::{JsonSeqReader, JsonSeqWriter};
+#[cfg(feature = "serde")]
+pub use ser::{to_string, to_string_pretty, to_writer, to_writer_pretty, SerializeError};
 pub use value::{JsonNumber, JsonValue}; // <-- Added JsonNumber
+pub use wtf8::Wtf8String;
 
 // --- Constants ---
 /// The default maximum nesting depth (e.g., `[[[]]]`) to prevent stack overflows.
@@ -142,22 +200,157 @@ const MAX_JSON_SIZE_BYTES: usize = 10 * 1024 * 1024;
 /// assert!(parser.next().is_none());
 /// ```
 pub fn parse_streaming(input: &str) -> Result<StreamingParser<'_>, ParseError> {
-    if input.len() > MAX_JSON_SIZE_BYTES {
-        return Err(ParseError {
-            message: "Input exceeds maximum size limit".to_string(),
-            line: 1,
-            column: 1,
-        });
+    parse_streaming_with(input, &ParserOptions::default())
+}
+
+/// Parses a JSON string slice into a `StreamingParser` that enforces the
+/// given `ParserOptions`.
+///
+/// # Errors
+/// Returns a `ParseError` if the input exceeds the configured
+/// `max_input_size` *before* parsing begins. The other limits are
+/// reported by the parser as it reaches them.
+///
+/// # Examples
+/// ```
+/// use rill_json::{parse_streaming_with, ParserOptions};
+///
+/// let options = ParserOptions::new().max_array_members(2);
+/// let result: Result<Vec<_>, _> = parse_streaming_with("[1, 2, 3]", &options)
+///     .unwrap()
+///     .collect();
+/// assert_eq!(
+///     result.unwrap_err().message,
+///     "Array exceeds maximum number of members"
+/// );
+/// ```
+pub fn parse_streaming_with<'a>(
+    input: &'a str,
+    options: &ParserOptions,
+) -> Result<StreamingParser<'a>, ParseError> {
+    if input.len() > options.max_input_size {
+        return Err(ParseError::new(ErrorKind::SizeLimitExceeded, 1, 1, 0));
     }
-    // We can call StreamingParser::new because it's public in parser.rs
-    Ok(StreamingParser::new(input, DEFAULT_MAX_DEPTH))
+    Ok(StreamingParser::with_options(input, options))
+}
+
+/// Parses JSON from any `std::io::Read` into a `ReaderParser`.
+///
+/// Use this instead of `parse_streaming` for inputs that are too large to
+/// hold in memory, such as multi-gigabyte files or network streams. The
+/// reader is consumed in fixed-size chunks, so no input size limit applies.
+///
+/// # Arguments
+/// * `reader` - The source of the JSON data. It does not need to be buffered.
+///
+/// # Examples
+/// ```
+/// use rill_json::{parse_reader, ParserEvent, JsonNumber};
+///
+/// let file = std::io::Cursor::new(r#"[1, "hello"]"#);
+/// let mut parser = parse_reader(file);
+///
+/// assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::StartArray);
+/// assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::Number(JsonNumber::I64(1)));
+/// assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::String("hello".into()));
+/// assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::EndArray);
+/// assert!(parser.next().is_none());
+/// ```
+pub fn parse_reader<R: std::io::Read>(reader: R) -> ReaderParser<R> {
+    ReaderParser::new(reader, DEFAULT_MAX_DEPTH)
+}
+
+/// Parses JSON from any `std::io::Read` into a `ReaderParser` that
+/// enforces the given `ParserOptions`.
+///
+/// Unlike `parse_reader`, this applies `max_input_size` to the total
+/// number of bytes read; use `usize::MAX` to disable it.
+pub fn parse_reader_with<R: std::io::Read>(reader: R, options: &ParserOptions) -> ReaderParser<R> {
+    ReaderParser::with_options(reader, options)
+}
+
+/// Parses newline-delimited JSON (NDJSON / JSON Lines) into an
+/// `NdjsonParser`, which yields one `JsonValue` per line.
+///
+/// Errors report the record number alongside the line and column in the
+/// whole input.
+///
+/// # Examples
+/// ```
+/// use rill_json::{parse_ndjson, JsonValue};
+///
+/// let logs = "{\"level\": \"info\"}\n{\"level\": \"warn\"}\n{\"level\" \"error\"}\n";
+/// let mut records = parse_ndjson(logs);
+/// assert!(matches!(records.next(), Some(Ok(JsonValue::Object(_)))));
+/// assert!(matches!(records.next(), Some(Ok(JsonValue::Object(_)))));
+///
+/// let err = records.next().unwrap().unwrap_err();
+/// assert_eq!((err.record, err.error.line), (3, 3));
+/// ```
+pub fn parse_ndjson(input: &str) -> NdjsonParser<'_> {
+    NdjsonParser::new(input, &ParserOptions::default())
+}
+
+/// Parses newline-delimited JSON into an `NdjsonParser` that applies the
+/// given `ParserOptions` to each record.
+///
+/// The input size limit applies to each record, not to the whole input.
+pub fn parse_ndjson_with<'a>(input: &'a str, options: &ParserOptions) -> NdjsonParser<'a> {
+    NdjsonParser::new(input, options)
+}
+
+/// Parses newline-delimited JSON from any `std::io::Read` into a
+/// fault-tolerant `NdjsonReader`.
+///
+/// Each record is yielded as a `Result` carrying its record number and
+/// byte offset. Malformed records are reported and skipped, so a corrupt
+/// line cannot abort a large file. Lines are limited to 10MB; see
+/// `parse_ndjson_reader_with`.
+pub fn parse_ndjson_reader<R: std::io::Read>(reader: R) -> NdjsonReader<R> {
+    NdjsonReader::new(reader, &ParserOptions::default())
+}
+
+/// Parses newline-delimited JSON from any `std::io::Read` into an
+/// `NdjsonReader` that applies the given `ParserOptions` to each record.
+///
+/// `max_input_size` limits the length of each line.
+pub fn parse_ndjson_reader_with<R: std::io::Read>(
+    reader: R,
+    options: &ParserOptions,
+) -> NdjsonReader<R> {
+    NdjsonReader::new(reader, options)
+}
+
+/// Parses a JSON text sequence (RFC 7464, `application/json-seq`) from any
+/// `std::io::Read` into a `JsonSeqReader`, which yields one `JsonValue`
+/// per record.
+///
+/// Malformed and truncated records are reported and skipped, as the RFC
+/// requires. Records are limited to 10MB; see `parse_json_seq_with`.
+pub fn parse_json_seq<R: std::io::Read>(reader: R) -> JsonSeqReader<R> {
+    JsonSeqReader::new(reader, &ParserOptions::default())
+}
+
+/// Parses a JSON text sequence from any `std::io::Read` into a
+/// `JsonSeqReader` that applies the given `ParserOptions` to each record.
+///
+/// `max_input_size` limits the length of each record.
+pub fn parse_json_seq_with<R: std::io::Read>(
+    reader: R,
+    options: &ParserOptions,
+) -> JsonSeqReader<R> {
+    JsonSeqReader::new(reader, options)
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeMap;
     // Use the public API we just defined
-    use super::{parse_streaming, JsonNumber, JsonValue, ParseError, ParserEvent, StreamingParser};
+    use super::{
+        parse_reader_with, parse_streaming, parse_streaming_with, Dialect, ErrorKind, JsonNumber,
+        JsonValue, LoneSurrogatePolicy, ParseError, ParserEvent, ParserOptions, PushParser, Span,
+        StreamingParser, Wtf8String,
+    };
     use serde_json::{self, Value as SerdeValue};
     use std::borrow::Cow;
 
@@ -352,6 +545,581 @@ mod tests {
         assert_eq!(err.column, 1);
     }
 
+    #[test]
+    fn test_streaming_error_kinds() {
+        let cases = [
+            ("[1, ?]", ErrorKind::UnexpectedCharacter('?')),
+            ("[tru]", ErrorKind::ExpectedLiteral("true")),
+            (r#""abc"#, ErrorKind::UnterminatedString),
+            ("\"\t\"", ErrorKind::ControlCharacterInString),
+            (r#""\z""#, ErrorKind::InvalidEscape),
+            (r#""\u12zz""#, ErrorKind::InvalidUnicodeEscape),
+            (r#""\u12""#, ErrorKind::IncompleteUnicodeEscape),
+            ("0123", ErrorKind::LeadingZero),
+            ("1.", ErrorKind::TrailingDecimalPoint),
+            ("1.5.2", ErrorKind::UnexpectedCharacterInNumber('.')),
+            ("-", ErrorKind::ExpectedIntegerDigit),
+            ("1e+", ErrorKind::ExpectedExponentDigit),
+            (
+                "99999999999999999999",
+                ErrorKind::IntegerOutOfRange("99999999999999999999".to_string()),
+            ),
+            ("[1 2]", ErrorKind::ExpectedCommaOrArrayEnd),
+            ("[,]", ErrorKind::ExpectedValueOrArrayEnd),
+            ("[1,]", ErrorKind::TrailingCommaInArray),
+            ("[1,:]", ErrorKind::ExpectedValue),
+            ("{1}", ErrorKind::ExpectedKeyOrObjectEnd),
+            (r#"{"a":1,}"#, ErrorKind::TrailingCommaInObject),
+            (r#"{"a":1,2}"#, ErrorKind::ExpectedKey),
+            (r#"{"a" 1}"#, ErrorKind::ExpectedColon),
+            (r#"{"a":1 2}"#, ErrorKind::ExpectedCommaOrObjectEnd),
+            ("1 2", ErrorKind::TrailingToken),
+            ("{", ErrorKind::UnclosedObject),
+            ("[", ErrorKind::UnclosedArray),
+        ];
+        for (input, kind) in cases {
+            let err = collect_events(input).unwrap_err();
+            assert_eq!(err.kind, kind, "input {:?}", input);
+            assert_eq!(err.message, kind.to_string());
+        }
+
+        let err = collect_events_with_depth("[[]]", 1).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DepthLimitExceeded);
+        assert_eq!(
+            JsonValue::parse("").unwrap_err().kind,
+            ErrorKind::EmptyInput
+        );
+    }
+
+    #[test]
+    fn test_streaming_error_locations() {
+        // Every error carries a byte offset alongside its line and column.
+        let err = collect_events("{\n  \"a\": [1,\n  ?]}").unwrap_err();
+        assert_eq!((err.line, err.column, err.offset), (3, 3, 15));
+
+        // Columns count chars, while offsets count bytes.
+        let err = collect_events(r#"["é", "ü" true]"#).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ExpectedCommaOrArrayEnd);
+        assert_eq!((err.line, err.column, err.offset), (1, 11, 12));
+
+        // End-of-input errors point at the innermost unclosed bracket.
+        let err = collect_events("{\"a\": [1, 2,\n  [3]").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnclosedArray);
+        assert_eq!((err.line, err.column, err.offset), (1, 7, 6));
+
+        let err = collect_events("[{\"a\": 1").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnclosedObject);
+        assert_eq!((err.line, err.column, err.offset), (1, 2, 1));
+
+        // The push parser tracks offsets across chunk boundaries.
+        let mut push = PushParser::default();
+        push.feed(b"[1,\n").unwrap();
+        push.feed(b" 2, 3,").unwrap();
+        push.finish();
+        let err = std::iter::from_fn(|| push.next_event())
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(err.kind, ErrorKind::UnclosedArray);
+        assert_eq!((err.line, err.column, err.offset), (1, 1, 0));
+
+        let mut push = PushParser::default();
+        push.feed(b"[1,\n").unwrap();
+        push.feed(b" 2 3]").unwrap();
+        let err = std::iter::from_fn(|| push.next_event())
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!((err.line, err.column, err.offset), (2, 4, 7));
+    }
+
+    #[test]
+    fn test_streaming_spans() {
+        let input = "{\"k\\u00e9\": [1.5e3, \"\\u00e9\"],\n \"é\": null}";
+        let mut parser = parse_streaming(input).unwrap();
+        let spans: Vec<(ParserEvent, Span)> = std::iter::from_fn(|| parser.next_spanned())
+            .map(|spanned| spanned.map(|spanned| (spanned.event, spanned.span)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let texts: Vec<&str> = spans
+            .iter()
+            .map(|(_, span)| &input[span.start..span.end])
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "{",
+                "\"k\\u00e9\"",
+                "[",
+                "1.5e3",
+                "\"\\u00e9\"",
+                "]",
+                "\"é\"",
+                "null",
+                "}"
+            ]
+        );
+
+        // Line and column locate the start of each span.
+        let (event, span) = &spans[6];
+        assert_eq!(event, &ParserEvent::Key(Cow::Borrowed("é")));
+        assert_eq!((span.line, span.column), (2, 2));
+        let (_, span) = &spans[7];
+        assert_eq!((span.line, span.column, span.start), (2, 7, 38));
+
+        // Mixing `next` and `next_spanned` advances the same parser.
+        let mut parser = parse_streaming("[true, false]").unwrap();
+        assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::StartArray);
+        let spanned = parser.next_spanned().unwrap().unwrap();
+        assert_eq!(spanned.event, ParserEvent::Boolean(true));
+        assert_eq!(spanned.span.start..spanned.span.end, 1..5);
+        assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::Boolean(false));
+    }
+
+    #[test]
+    fn test_streaming_current_path() {
+        let input = r#"{"users": [{"id": 7}, []], "a/b": {"m~n": null}, "": 1}"#;
+        let mut parser = parse_streaming(input).unwrap();
+        assert_eq!((parser.current_path(), parser.depth()), (String::new(), 0));
+
+        let mut paths = Vec::new();
+        while let Some(event) = parser.next() {
+            event.unwrap();
+            paths.push((parser.current_path(), parser.depth()));
+        }
+        let expected = [
+            ("", 1),            // {
+            ("/users", 1),      // "users"
+            ("/users", 2),      // [
+            ("/users/0", 3),    // {
+            ("/users/0/id", 3), // "id"
+            ("/users/0/id", 3), // 7
+            ("/users/0", 2),    // }
+            ("/users/1", 3),    // [
+            ("/users/1", 2),    // ]
+            ("/users", 1),      // ]
+            ("/a~1b", 1),       // "a/b"
+            ("/a~1b", 2),       // {
+            ("/a~1b/m~0n", 2),  // "m~n"
+            ("/a~1b/m~0n", 2),  // null
+            ("/a~1b", 1),       // }
+            ("/", 1),           // ""
+            ("/", 1),           // 1
+            ("", 0),            // }
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(path, depth)| (path.to_string(), *depth))
+            .collect();
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn test_streaming_skip_value() {
+        let input = r#"{"skip": {"a": [1, -2.5e3, "x\"]\u00e9"], "b": {}}, "keep": [true, [null], "s"], "n": 1}"#;
+        let mut parser = parse_streaming(input).unwrap();
+        assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::StartObject);
+        assert_eq!(
+            parser.next().unwrap().unwrap(),
+            ParserEvent::Key(Cow::Borrowed("skip"))
+        );
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(parser.current_path(), "/skip");
+        assert_eq!(
+            parser.next().unwrap().unwrap(),
+            ParserEvent::Key(Cow::Borrowed("keep"))
+        );
+
+        // Inside an array, each call skips one element.
+        assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::StartArray);
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(parser.current_path(), "/keep/1");
+        assert_eq!(
+            parser.next().unwrap().unwrap(),
+            ParserEvent::String(Cow::Borrowed("s"))
+        );
+        assert_eq!(parser.current_path(), "/keep/2");
+        // Nothing left to skip: the end event is left for `next`.
+        assert_eq!(parser.skip_value(), Ok(false));
+        assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::EndArray);
+
+        let rest: Vec<_> = parser.collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            rest,
+            vec![
+                ParserEvent::Key(Cow::Borrowed("n")),
+                ParserEvent::Number(JsonNumber::I64(1)),
+                ParserEvent::EndObject,
+            ]
+        );
+
+        // Skipping the whole document.
+        let mut parser = parse_streaming(" [[], {}] ").unwrap();
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(parser.skip_value(), Ok(false));
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn test_streaming_skip_value_errors() {
+        fn skip_after_key(input: &str) -> ParseError {
+            let mut parser = parse_streaming(input).unwrap();
+            parser.next().unwrap().unwrap(); // StartObject
+            parser.next().unwrap().unwrap(); // Key
+            parser.skip_value().unwrap_err()
+        }
+
+        let cases = [
+            (
+                r#"{"a": [1, {"b": 2]}"#,
+                ErrorKind::ExpectedCommaOrObjectEnd,
+            ),
+            (r#"{"a": ["x]}"#, ErrorKind::UnterminatedString),
+            (r#"{"a": ["\q"]}"#, ErrorKind::InvalidEscape),
+            (r#"{"a": ["\u12zz"]}"#, ErrorKind::InvalidUnicodeEscape),
+            ("{\"a\": [\"\n\"]}", ErrorKind::ControlCharacterInString),
+            (r#"{"a": [nul]}"#, ErrorKind::ExpectedLiteral("null")),
+            (r#"{"a": [?]}"#, ErrorKind::UnexpectedCharacter('?')),
+            (r#"{"a" 1}"#, ErrorKind::ExpectedColon),
+            (r#"{"a": ]"#, ErrorKind::ExpectedValue),
+        ];
+        for (input, kind) in cases {
+            assert_eq!(skip_after_key(input).kind, kind, "input {:?}", input);
+        }
+
+        // Missing and duplicated separators are reported exactly as when
+        // the value is parsed.
+        let cases = [
+            (r#"{"a": [1 2]}"#, ErrorKind::ExpectedCommaOrArrayEnd, 9),
+            (r#"{"a": [1,,2]}"#, ErrorKind::ExpectedValue, 9),
+            (r#"{"a": [,1]}"#, ErrorKind::ExpectedValueOrArrayEnd, 7),
+            (r#"{"a": [1,]}"#, ErrorKind::TrailingCommaInArray, 9),
+            (r#"{"a": [1:2]}"#, ErrorKind::ExpectedCommaOrArrayEnd, 8),
+            (r#"{"a": {"x" 1}}"#, ErrorKind::ExpectedColon, 11),
+            (r#"{"a": {"x"::1}}"#, ErrorKind::ExpectedValue, 11),
+            (
+                r#"{"a": {"x": 1 "y": 2}}"#,
+                ErrorKind::ExpectedCommaOrObjectEnd,
+                14,
+            ),
+            (r#"{"a": {"x": 1,, "y": 2}}"#, ErrorKind::ExpectedKey, 14),
+            (r#"{"a": {"x": 1,}}"#, ErrorKind::TrailingCommaInObject, 14),
+            (r#"{"a": {1: 2}}"#, ErrorKind::ExpectedKeyOrObjectEnd, 7),
+            (r#"{"a": {"x", 1}}"#, ErrorKind::ExpectedColon, 10),
+        ];
+        for (input, kind, offset) in cases {
+            let err = skip_after_key(input);
+            assert_eq!(
+                (&err.kind, err.offset),
+                (&kind, offset),
+                "input {:?}",
+                input
+            );
+            assert_eq!(collect_events(input).unwrap_err(), err, "input {:?}", input);
+        }
+
+        // Unclosed brackets are reported where they were opened.
+        let err = skip_after_key("{\"a\": [1,\n {\"b\": [2]");
+        assert_eq!(err.kind, ErrorKind::UnclosedObject);
+        assert_eq!((err.line, err.column, err.offset), (2, 2, 11));
+
+        // The depth limit still applies to skipped values.
+        let options = ParserOptions::new().max_depth(3);
+        let mut parser = parse_streaming_with("[[[[]]]]", &options).unwrap();
+        parser.next().unwrap().unwrap();
+        let err = parser.skip_value().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DepthLimitExceeded);
+        assert_eq!(err.column, 4);
+
+        // A value cannot be skipped where a key is expected.
+        let mut parser = parse_streaming(r#"{"a": 1}"#).unwrap();
+        parser.next().unwrap().unwrap();
+        assert_eq!(
+            parser.skip_value().unwrap_err().kind,
+            ErrorKind::ExpectedKeyOrObjectEnd
+        );
+    }
+
+    #[test]
+    fn test_streaming_skip_value_limits() {
+        // Skipped values are held to the same limits as parsed ones.
+        let cases = [
+            (
+                ParserOptions::new()
+                    .max_string_length(3)
+                    .max_array_members(2),
+                r#"["aaaaaaaaaaaaaa", 1,2,3,4,5,6,7,8,9]"#,
+                ErrorKind::StringTooLong,
+            ),
+            (
+                ParserOptions::new().max_string_length(3),
+                r#"["\u00e9\u00e9"]"#,
+                ErrorKind::StringTooLong,
+            ),
+            (
+                ParserOptions::new().max_key_length(2),
+                r#"[{"abc": 1}]"#,
+                ErrorKind::KeyTooLong,
+            ),
+            (
+                ParserOptions::new().max_array_members(2),
+                "[[1, 2], [1, 2, 3]]",
+                ErrorKind::TooManyArrayMembers,
+            ),
+            (
+                ParserOptions::new().max_object_members(2),
+                r#"{"x": 1, "y": 2, "z": 3}"#,
+                ErrorKind::TooManyObjectMembers,
+            ),
+            (
+                ParserOptions::new().max_events(5),
+                "[1, 2, 3, 4]",
+                ErrorKind::TooManyEvents,
+            ),
+            (
+                ParserOptions::new().max_depth(2),
+                "[[1]]",
+                ErrorKind::DepthLimitExceeded,
+            ),
+        ];
+        for (options, value, kind) in cases {
+            let input = format!(r#"{{"a": {}, "b": 1}}"#, value);
+            let mut parser = parse_streaming_with(&input, &options).unwrap();
+            parser.next().unwrap().unwrap(); // StartObject
+            parser.next().unwrap().unwrap(); // Key
+            let err = parser.skip_value().unwrap_err();
+            assert_eq!(err.kind, kind, "input {:?}", input);
+
+            // The error is exactly the one parsing the value reports.
+            let parsed = parse_streaming_with(&input, &options)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap_err();
+            assert_eq!(parsed, err, "input {:?}", input);
+        }
+
+        // Within the limits, skipping succeeds.
+        let options = ParserOptions::new().max_string_length(3).max_key_length(3);
+        let input = r#"{"a": [{"abc": "\u00e9a"}], "b": 1}"#;
+        let mut parser = parse_streaming_with(input, &options).unwrap();
+        parser.next().unwrap().unwrap();
+        parser.next().unwrap().unwrap();
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(
+            parser.next().unwrap().unwrap(),
+            ParserEvent::Key(Cow::Borrowed("b"))
+        );
+    }
+
+    #[test]
+    fn test_json5_dialect() {
+        let options = ParserOptions::new().dialect(Dialect::Json5);
+        let input = r#"
+            // A human-edited config file.
+            {
+                name: 'rill',            /* single quotes */
+                "quoted": "still fine",
+                true: null,              // reserved words are valid keys
+                ports: [0x50, +443, .5, 5., -Infinity, NaN,],
+                motd: 'line one \
+line two',
+            }
+        "#;
+        let events: Vec<_> = parse_streaming_with(input, &options)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events[1], ParserEvent::Key(Cow::Borrowed("name")));
+        assert_eq!(events[2], ParserEvent::String(Cow::Borrowed("rill")));
+        assert_eq!(events[5], ParserEvent::Key(Cow::Borrowed("true")));
+        assert_eq!(events[6], ParserEvent::Null);
+        assert_eq!(
+            &events[8..14],
+            &[
+                ParserEvent::StartArray,
+                ParserEvent::Number(JsonNumber::I64(80)),
+                ParserEvent::Number(JsonNumber::I64(443)),
+                ParserEvent::Number(JsonNumber::F64(0.5)),
+                ParserEvent::Number(JsonNumber::F64(5.0)),
+                ParserEvent::Number(JsonNumber::F64(f64::NEG_INFINITY)),
+            ]
+        );
+        assert!(matches!(events[14], ParserEvent::Number(JsonNumber::F64(n)) if n.is_nan()));
+        assert_eq!(events[15], ParserEvent::EndArray);
+        assert_eq!(
+            events[17],
+            ParserEvent::String(Cow::Owned("line one line two".to_string()))
+        );
+        assert_eq!(events[18], ParserEvent::EndObject);
+        assert_eq!(events.len(), 19);
+
+        // Unknown identifiers are only valid as keys.
+        let err = parse_streaming_with("{a: b}", &options)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ExpectedValue);
+        assert_eq!(err.column, 5);
+
+        // A single trailing comma is allowed, but not two.
+        let err = parse_streaming_with("[1,,]", &options)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ExpectedValue);
+
+        // skip_value understands JSON5 too.
+        let mut parser =
+            parse_streaming_with("{a: [/* ] */ 'x]', {b: 1,},], c: 2}", &options).unwrap();
+        parser.next().unwrap().unwrap();
+        parser.next().unwrap().unwrap();
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(
+            parser.next().unwrap().unwrap(),
+            ParserEvent::Key(Cow::Borrowed("c"))
+        );
+
+        // Strict JSON remains the default.
+        let err = collect_events("{a: 1}").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnexpectedCharacter('a'));
+        let err = collect_events("[1,]").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TrailingCommaInArray);
+    }
+
+    #[test]
+    fn test_surrogate_pairs() {
+        // Pairs decode like serde_json does, in strings and in keys.
+        let input = r#"{"\uD83D\uDE00": ["\ud834\udd1e", "x\uDBFF\uDFFFy"]}"#;
+        let value = JsonValue::parse(input).unwrap();
+        let expected: SerdeValue = serde_json::from_str(input).unwrap();
+        assert_eq!(value.stringify().unwrap(), expected.to_string());
+
+        // The policy reaches every parser.
+        let input = r#"["\uDE00", "\uD83D"]"#;
+        let options = ParserOptions::new().lone_surrogates(LoneSurrogatePolicy::Replace);
+        let replaced = vec![
+            ParserEvent::StartArray,
+            ParserEvent::String(Cow::Owned("\u{FFFD}".to_string())),
+            ParserEvent::String(Cow::Owned("\u{FFFD}".to_string())),
+            ParserEvent::EndArray,
+        ];
+        let events: Vec<_> = parse_streaming_with(input, &options)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events, replaced);
+
+        let mut push = PushParser::with_options(&options);
+        for chunk in input.as_bytes().chunks(3) {
+            push.feed(chunk).unwrap();
+        }
+        push.finish();
+        let events: Vec<_> = std::iter::from_fn(|| push.next_event())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events, replaced);
+
+        // Lone surrogates are rejected by default.
+        let err = collect_events(input).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LoneSurrogate);
+        assert_eq!(err.message, "Unpaired surrogate in Unicode escape");
+        assert_eq!((err.line, err.column), (1, 9));
+    }
+
+    #[test]
+    fn test_preserved_lone_surrogates() {
+        let options = ParserOptions::new().lone_surrogates(LoneSurrogatePolicy::Preserve);
+        let input = r#"{"k\uDFAA": ["\uD83D", "a\uDE00\uD83Db", "\uD83D\uDE00"]}"#;
+        let preserved = vec![
+            ParserEvent::StartObject,
+            ParserEvent::WtfKey(Wtf8String::from_utf16(&[0x6B, 0xDFAA])),
+            ParserEvent::StartArray,
+            ParserEvent::WtfString(Wtf8String::from_utf16(&[0xD83D])),
+            ParserEvent::WtfString(Wtf8String::from_utf16(&[0x61, 0xDE00, 0xD83D, 0x62])),
+            // A pair needs no WTF-8.
+            ParserEvent::String(Cow::Owned("\u{1F600}".to_string())),
+            ParserEvent::EndArray,
+            ParserEvent::EndObject,
+        ];
+
+        // The policy reaches every parser.
+        let events: Vec<_> = parse_streaming_with(input, &options)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events, preserved);
+
+        let events: Vec<_> = parse_reader_with(input.as_bytes(), &options)
+            .map(|event| event.map(ParserEvent::into_owned))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events, preserved);
+
+        let mut push = PushParser::with_options(&options);
+        for chunk in input.as_bytes().chunks(3) {
+            push.feed(chunk).unwrap();
+        }
+        push.finish();
+        let events: Vec<_> = std::iter::from_fn(|| push.next_event())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events, preserved);
+
+        // Skipping a value with lone surrogates succeeds.
+        let mut parser = parse_streaming_with(input, &options).unwrap();
+        parser.next().unwrap().unwrap();
+        parser.next().unwrap().unwrap();
+        assert_eq!(parser.skip_value(), Ok(true));
+        assert_eq!(parser.next(), Some(Ok(ParserEvent::EndObject)));
+
+        // Limits count the WTF-8 bytes, whether parsed or skipped.
+        let input = r#"{"a": "\uD800\uD800"}"#;
+        for (max, ok) in [(5, false), (6, true)] {
+            let options = options.clone().max_string_length(max);
+            let parsed = parse_streaming_with(input, &options)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>();
+            assert_eq!(parsed.is_ok(), ok);
+            let mut parser = parse_streaming_with(input, &options).unwrap();
+            parser.next().unwrap().unwrap();
+            parser.next().unwrap().unwrap();
+            match parser.skip_value() {
+                Ok(skipped) => assert!(ok && skipped),
+                Err(err) => assert_eq!((ok, err.kind), (false, ErrorKind::StringTooLong)),
+            }
+        }
+        let options = options.max_key_length(3);
+        let err = parse_streaming_with(r#"{"a\uD800": 1}"#, &options)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::KeyTooLong);
+
+        // A `JsonValue` keeps the surrogates and writes them back out.
+        let options = ParserOptions::new().lone_surrogates(LoneSurrogatePolicy::Preserve);
+        let value =
+            JsonValue::parse_with(r#"["\uDE00x", "\u00e9\uD83D\uDE00"]"#, &options).unwrap();
+        assert_eq!(
+            value,
+            JsonValue::Array(vec![
+                JsonValue::WtfString(Wtf8String::from_utf16(&[0xDE00, 0x78])),
+                JsonValue::String("\u{E9}\u{1F600}".to_string()),
+            ])
+        );
+        assert_eq!(value.stringify().unwrap(), r#"["\ude00x","é😀"]"#);
+        assert_eq!(
+            value.stringify_pretty().unwrap(),
+            "[\n  \"\\ude00x\",\n  \"é😀\"\n]"
+        );
+        let text = value.stringify().unwrap();
+        assert_eq!(JsonValue::parse_with(&text, &options).unwrap(), value);
+
+        // Object keys are `String`s, so one with a lone surrogate is an error.
+        let err = JsonValue::parse_with(r#"{"a": 1, "b\uD800": 2}"#, &options).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LoneSurrogate);
+        assert_eq!((err.line, err.column, err.offset), (1, 10, 9));
+    }
+
     #[test]
     fn test_streaming_security_limits() {
         let evil_input = "[".repeat(101) + &"]".repeat(101);
@@ -369,6 +1137,76 @@ mod tests {
         assert!(err.is_ok());
     }
 
+    #[test]
+    fn test_streaming_parser_options() {
+        fn collect_with(input: &str, options: &ParserOptions) -> Result<(), ParseError> {
+            parse_streaming_with(input, options)?
+                .collect::<Result<Vec<_>, _>>()
+                .map(|_| ())
+        }
+
+        let options = ParserOptions::new().max_input_size(4);
+        assert!(collect_with("[12]", &options).is_ok());
+        let err = collect_with("[123]", &options).unwrap_err();
+        assert_eq!(err.message, "Input exceeds maximum size limit");
+
+        let options = ParserOptions::new().max_depth(1);
+        let err = collect_with("[[]]", &options).unwrap_err();
+        assert_eq!(err.message, "Maximum nesting depth exceeded");
+        assert_eq!(err.column, 2);
+
+        let options = ParserOptions::new().max_string_length(3);
+        assert!(collect_with(r#"{"long key": "abc"}"#, &options).is_ok());
+        let err = collect_with(r#"["abcd"]"#, &options).unwrap_err();
+        assert_eq!(err.message, "String exceeds maximum length");
+        assert_eq!(err.column, 2);
+
+        let options = ParserOptions::new().max_key_length(3);
+        assert!(collect_with(r#"{"abc": "long value"}"#, &options).is_ok());
+        let err = collect_with(r#"{"abcd": 1}"#, &options).unwrap_err();
+        assert_eq!(err.message, "Key exceeds maximum length");
+
+        // Member counts are per container, not per document.
+        let options = ParserOptions::new().max_array_members(2);
+        assert!(collect_with("[[1, 2], [3, 4], {\"a\": [5, 6]}]", &options).is_err());
+        assert!(collect_with("[[1, 2], {\"a\": [5, 6]}]", &options).is_ok());
+        let err = collect_with("[1, [], 3]", &options).unwrap_err();
+        assert_eq!(err.message, "Array exceeds maximum number of members");
+        assert_eq!(err.column, 9);
+
+        let options = ParserOptions::new().max_object_members(1);
+        assert!(collect_with(r#"{"a": {"b": [1, 2, 3]}}"#, &options).is_ok());
+        let err = collect_with(r#"{"a": 1, "b": 2}"#, &options).unwrap_err();
+        assert_eq!(err.message, "Object exceeds maximum number of members");
+
+        let options = ParserOptions::new().max_events(4);
+        assert!(collect_with("[1, 2]", &options).is_ok());
+        let err = collect_with("[1, 2, 3]", &options).unwrap_err();
+        assert_eq!(err.message, "Maximum number of events exceeded");
+
+        // The same options drive the in-memory parser and the push parser.
+        let options = ParserOptions::new().max_array_members(1);
+        let err = JsonValue::parse_with("[1, 2]", &options).unwrap_err();
+        assert_eq!(err.message, "Array exceeds maximum number of members");
+
+        // The push parser refuses a chunk that goes past the size limit
+        // before buffering it.
+        let mut push = PushParser::with_options(&ParserOptions::new().max_input_size(3));
+        push.feed(b"[1").unwrap();
+        assert_eq!(push.next_event().unwrap().unwrap(), ParserEvent::StartArray);
+        let err = push.feed(b", 2]").unwrap_err();
+        assert_eq!(err.message, "Input exceeds maximum size limit");
+        assert_eq!(err.offset, 1);
+        assert!(push.next_event().is_none());
+        assert!(push.is_done());
+
+        let options = ParserOptions::new().max_input_size(3);
+        let err = parse_reader_with(&b"[1, 2]"[..], &options)
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(err.kind, ErrorKind::SizeLimitExceeded);
+    }
+
     #[test]
     fn test_stringify_basic() {
         // Input: A native map {"key": "value", "items": [1, None]}
@@ -451,6 +1289,111 @@ mod tests {
         assert_eq!(arr.stringify().unwrap(), r#"[1,"test",true,null,{}]"#);
     }
 
+    #[test]
+    fn test_stringify_float_round_trip() {
+        let cases = [
+            (1.0, "1.0"),
+            (-0.0, "-0.0"),
+            (1e300, "1e300"),
+            (1.5e-9, "1.5e-9"),
+            (0.1 + 0.2, "0.30000000000000004"),
+        ];
+        for (num, expected) in cases {
+            let value = JsonValue::Number(JsonNumber::F64(num));
+            let text = value.stringify().unwrap();
+            assert_eq!(text, expected);
+            assert_eq!(JsonValue::parse(&text).unwrap(), value);
+        }
+        assert_eq!(
+            JsonValue::Array(vec![JsonValue::Number(JsonNumber::F64(2.0))])
+                .stringify_pretty()
+                .unwrap(),
+            "[\n  2.0\n]"
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_serde_json_conversions() {
+        use super::{RawNumber, RawValue, SerializeError};
+
+        let input = r#"{"a": [1, -1, 18446744073709551615, 1.0, 2.5], "b": {"c": null, "d": "x"}}"#;
+        let value = JsonValue::parse(input).unwrap();
+        let native: serde_json::Value = serde_json::from_str(input).unwrap();
+        assert_eq!(serde_json::Value::try_from(value.clone()).unwrap(), native);
+        assert_eq!(JsonValue::from(native), value);
+
+        // Floats stay floats, even when they are whole numbers.
+        let one = serde_json::Value::try_from(JsonValue::Number(JsonNumber::F64(1.0))).unwrap();
+        assert!(one.is_f64());
+
+        let options = ParserOptions::new().lossless_numbers(true);
+        let raw = JsonValue::parse_with("[7, 1e400]", &options).unwrap();
+        let JsonValue::Array(raw) = raw else { panic!() };
+        assert_eq!(
+            serde_json::Value::try_from(raw[0].clone()).unwrap(),
+            serde_json::json!(7)
+        );
+        assert!(serde_json::Value::try_from(raw[1].clone()).is_err());
+        assert!(matches!(
+            serde_json::Value::try_from(JsonValue::Number(JsonNumber::F64(f64::NAN))),
+            Err(SerializeError::NonFiniteFloat)
+        ));
+
+        let raw = RawValue::new(r#"{"k": [1, "v"]}"#).unwrap().into_owned();
+        assert_eq!(
+            serde_json::Value::try_from(JsonValue::Raw(raw)).unwrap(),
+            serde_json::json!({"k": [1, "v"]})
+        );
+
+        // Raw numbers convert only if serde_json keeps their exact value.
+        let exact = [
+            ("-9223372036854775808", serde_json::json!(i64::MIN)),
+            ("18446744073709551615", serde_json::json!(u64::MAX)),
+            ("1.50", serde_json::json!(1.5)),
+            ("15e-1", serde_json::json!(1.5)),
+            ("1E2", serde_json::json!(100.0)),
+            ("0.1", serde_json::json!(0.1)),
+            ("-0", serde_json::json!(-0.0)),
+            ("1.7976931348623157e308", serde_json::json!(f64::MAX)),
+        ];
+        for (text, expected) in exact {
+            let n = JsonNumber::Raw(RawNumber::new(text).unwrap());
+            assert_eq!(
+                serde_json::Value::try_from(JsonValue::Number(n)).unwrap(),
+                expected,
+                "number {}",
+                text
+            );
+        }
+        let rounded = [
+            "340282366920938463463374607431768211455",
+            "12345678901234567890123",
+            "18446744073709551616",
+            "0.10000000000000000000001",
+            "9007199254740993.0",
+            "1e-400",
+        ];
+        for text in rounded {
+            let n = JsonNumber::Raw(RawNumber::new(text).unwrap());
+            let err = serde_json::Number::try_from(n).unwrap_err();
+            assert!(matches!(err, SerializeError::Custom(_)), "number {}", text);
+
+            // Numbers within raw values too.
+            let input = format!(r#"{{"a": [{}]}}"#, text);
+            let mut parser = parse_streaming_with(&input, &options).unwrap();
+            let raw = parser.next_raw_value().unwrap().unwrap().into_owned();
+            assert!(serde_json::Value::try_from(JsonValue::Raw(raw)).is_err());
+        }
+
+        // `serde_json` strings cannot hold a lone surrogate.
+        let wtf = JsonValue::WtfString(super::Wtf8String::from_utf16(&[0xD800]));
+        assert!(matches!(
+            serde_json::Value::try_from(wtf),
+            Err(SerializeError::Custom(_))
+        ));
+    }
+
     #[test]
     fn test_stringify_string_escapes() {
         // Test all escapes from Stage 8
@@ -0,0 +1,186 @@
+//! Contains the `json!` macro for building a `JsonValue` from JSON-like
+//! syntax.
+
+/// Builds a `JsonValue` from JSON-like syntax.
+///
+/// `null`, `true`, `false`, arrays and objects are written as in JSON.
+/// Any other value is a Rust expression, converted with
+/// `JsonValue::from`. An object key is a string literal or any other
+/// expression that implements `Display`; wrap keys of more than one
+/// token in parentheses.
+///
+/// # Examples
+/// ```
+/// use rill_json::json;
+///
+/// let id = 1815;
+/// let field = "projects";
+/// let user = json!({
+///     "username": "ada_l",
+///     "id": id,
+///     "email": None::<String>,
+///     field: ["Analytical Engine", "Difference Engine"],
+///     (format!("{}_count", field)): 2,
+///     "active": false,
+/// });
+///
+/// assert_eq!(
+///     user.stringify().unwrap(),
+///     r#"{"active":false,"email":null,"id":1815,"projects":["Analytical Engine","Difference Engine"],"projects_count":2,"username":"ada_l"}"#
+/// );
+/// ```
+#[macro_export]
+macro_rules! json {
+    // --- Arrays: collect each element as an expression ---
+    (@array [$($elems:expr,)*]) => {
+        ::std::vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] null $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(null),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] true $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(true),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] false $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(false),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!([$($array)*]),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] {$($object:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!({$($object)*}),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($next),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($last),])
+    };
+
+    // --- Objects: munch key tokens up to the colon, then one value ---
+    (@object $object:ident ()) => {};
+    (@object $object:ident ($($key:tt)+) : $($rest:tt)*) => {
+        $crate::json!(@member $object ($($key)+) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::json!(@object $object ($($key)* $next) $($rest)*);
+    };
+    (@member $object:ident ($($key:tt)+) null $(, $($rest:tt)*)?) => {
+        $crate::json!(@insert $object ($($key)+) $crate::json!(null));
+        $crate::json!(@object $object () $($($rest)*)?);
+    };
+    (@member $object:ident ($($key:tt)+) true $(, $($rest:tt)*)?) => {
+        $crate::json!(@insert $object ($($key)+) $crate::json!(true));
+        $crate::json!(@object $object () $($($rest)*)?);
+    };
+    (@member $object:ident ($($key:tt)+) false $(, $($rest:tt)*)?) => {
+        $crate::json!(@insert $object ($($key)+) $crate::json!(false));
+        $crate::json!(@object $object () $($($rest)*)?);
+    };
+    (@member $object:ident ($($key:tt)+) [$($array:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::json!(@insert $object ($($key)+) $crate::json!([$($array)*]));
+        $crate::json!(@object $object () $($($rest)*)?);
+    };
+    (@member $object:ident ($($key:tt)+) {$($inner:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::json!(@insert $object ($($key)+) $crate::json!({$($inner)*}));
+        $crate::json!(@object $object () $($($rest)*)?);
+    };
+    (@member $object:ident ($($key:tt)+) $value:expr, $($rest:tt)*) => {
+        $crate::json!(@insert $object ($($key)+) $crate::json!($value));
+        $crate::json!(@object $object () $($rest)*);
+    };
+    (@member $object:ident ($($key:tt)+) $value:expr) => {
+        $crate::json!(@insert $object ($($key)+) $crate::json!($value));
+    };
+    (@insert $object:ident ($($key:tt)+) $value:expr) => {
+        $object.insert(::std::string::ToString::to_string(&($($key)+)), $value);
+    };
+
+    // --- Values ---
+    (null) => {
+        $crate::JsonValue::Null
+    };
+    (true) => {
+        $crate::JsonValue::Boolean(true)
+    };
+    (false) => {
+        $crate::JsonValue::Boolean(false)
+    };
+    ([]) => {
+        $crate::JsonValue::Array(::std::vec::Vec::new())
+    };
+    ([$($array:tt)+]) => {
+        $crate::JsonValue::Array($crate::json!(@array [] $($array)+))
+    };
+    ({}) => {
+        $crate::JsonValue::Object(::std::collections::BTreeMap::new())
+    };
+    ({$($members:tt)+}) => {
+        $crate::JsonValue::Object({
+            let mut object = ::std::collections::BTreeMap::new();
+            $crate::json!(@object object () $($members)+);
+            object
+        })
+    };
+    ($other:expr) => {
+        $crate::JsonValue::from($other)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{JsonNumber, JsonValue};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_json_macro_literals() {
+        assert_eq!(json!(null), JsonValue::Null);
+        assert_eq!(json!(true), JsonValue::Boolean(true));
+        assert_eq!(json!(-1), JsonValue::Number(JsonNumber::I64(-1)));
+        assert_eq!(
+            json!(u64::MAX),
+            JsonValue::Number(JsonNumber::U64(u64::MAX))
+        );
+        assert_eq!(json!(0.5), JsonValue::Number(JsonNumber::F64(0.5)));
+        assert_eq!(json!("a"), JsonValue::String("a".into()));
+        assert_eq!(json!([]), JsonValue::Array(vec![]));
+        assert_eq!(json!({}), JsonValue::Object(BTreeMap::new()));
+    }
+
+    #[test]
+    fn test_json_macro_nesting() {
+        let value = json!({
+            "a": [1, null, true, [false], {"b": "c"}],
+            "d": {},
+            "e": {"f": [], "g": null,},
+            "h": -2.5
+        });
+        assert_eq!(
+            value,
+            JsonValue::parse(
+                r#"{"a": [1, null, true, [false], {"b": "c"}], "d": {},
+                    "e": {"f": [], "g": null}, "h": -2.5}"#
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_json_macro_interpolation() {
+        let name = String::from("Ada");
+        let scores = vec![1u8, 2];
+        let key = "scores";
+        let missing: Option<i32> = None;
+        let nested = json!([1, 2]);
+        let value = json!({
+            "name": name,
+            key: scores,
+            (key.len()): missing,
+            (format!("{}!", "k")): [nested, 1 + 2, "x".repeat(2)],
+        });
+        assert_eq!(
+            value.stringify().unwrap(),
+            r#"{"6":null,"k!":[[1,2],3,"xx"],"name":"Ada","scores":[1,2]}"#
+        );
+    }
+}
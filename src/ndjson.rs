@@ -0,0 +1,478 @@
+//! Contains the `NdjsonParser` and `NdjsonReader`, which parse
+//! newline-delimited JSON.
+//!
+//! NDJSON (also known as JSON Lines) holds one complete JSON document per
+//! line, which is a common format for logs and data exports. A single
+//! `StreamingParser` rejects the second document as a trailing token, so
+//! these parsers split the input into lines and parse each on its own.
+
+use crate::error::{ErrorKind, ParseError};
+use crate::options::ParserOptions;
+use crate::reader::read_delimited;
+use crate::value::JsonValue;
+use memchr::memchr;
+use std::fmt;
+use std::io::{self, BufReader, Read};
+
+/// A newline-delimited JSON parser.
+///
+/// This struct is an `Iterator` that yields one `JsonValue` per record.
+/// It is created by the `parse_ndjson` function.
+///
+/// Blank lines are skipped, and a trailing `\r` is ignored, so files with
+/// Windows line endings parse too. Iteration stops after the first
+/// invalid record.
+pub struct NdjsonParser<'a> {
+    /// The part of the input that has not been parsed yet.
+    rest: &'a str,
+    /// The byte offset of `rest` within the whole input.
+    offset: usize,
+    /// The line number (1-indexed) of the first line of `rest`.
+    line: usize,
+    /// The number of records parsed so far.
+    records: usize,
+    /// The limits applied to each record.
+    options: ParserOptions,
+    /// Set after an error, which ends the iteration.
+    failed: bool,
+}
+
+/// An error in one record of an NDJSON input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NdjsonError {
+    /// The number (1-indexed) of the offending record. Blank lines are
+    /// not records, so this can be smaller than the line number.
+    pub record: usize,
+    /// The error itself. Its line and byte offset are relative to the
+    /// whole input, not to the record.
+    pub error: ParseError,
+}
+
+impl<'a> NdjsonParser<'a> {
+    /// Creates a new `NdjsonParser` that applies `options` to each record.
+    ///
+    /// This is called by the `parse_ndjson` and `parse_ndjson_with`
+    /// functions in `lib.rs`. The input size limit applies to each record
+    /// separately, not to the whole input.
+    pub fn new(input: &'a str, options: &ParserOptions) -> Self {
+        NdjsonParser {
+            rest: input,
+            offset: 0,
+            line: 1,
+            records: 0,
+            options: options.clone(),
+            failed: false,
+        }
+    }
+}
+
+/// A fault-tolerant NDJSON parser that pulls its input from a
+/// `std::io::Read`.
+///
+/// This struct is an `Iterator` that yields one `Result` per record. It is
+/// created by the `parse_ndjson_reader` function. Unlike `NdjsonParser`,
+/// a malformed record does not end the iteration: the reader reports it
+/// and resynchronizes at the next newline, so one corrupt line cannot
+/// abort a huge file. Only I/O errors end the iteration.
+///
+/// Memory use is bounded by the longest line: a line longer than the
+/// configured `max_input_size` is rejected without being buffered.
+///
+/// # Examples
+/// ```
+/// use rill_json::parse_ndjson_reader;
+///
+/// let logs = "{\"id\": 1}\n{\"id\": oops}\n{\"id\": 3}\n";
+/// let mut reader = parse_ndjson_reader(logs.as_bytes()).keep_rejected(true);
+///
+/// let ids: Vec<usize> = reader.by_ref().filter_map(Result::ok).map(|r| r.record).collect();
+/// assert_eq!(ids, [1, 3]);
+///
+/// let rejected = reader.rejected();
+/// assert_eq!(rejected.len(), 1);
+/// assert_eq!(rejected[0].error.offset, 17);
+/// assert_eq!(rejected[0].text, b"{\"id\": oops}");
+/// ```
+pub struct NdjsonReader<R> {
+    /// The buffered source of the input.
+    reader: BufReader<R>,
+    /// The bytes of the line being read.
+    line: Vec<u8>,
+    /// The byte offset of the next unread byte.
+    offset: usize,
+    /// The number of lines read so far.
+    lines: usize,
+    /// The number of records read so far.
+    records: usize,
+    /// The limits applied to each record.
+    options: ParserOptions,
+    /// The rejected records, if they are being kept.
+    rejected: Vec<RejectedRecord>,
+    /// Whether rejected records are kept in `rejected`.
+    keep_rejected: bool,
+    /// The number of rejected records, whether or not they are kept.
+    rejected_count: usize,
+    /// Set after an I/O error, which ends the iteration.
+    failed: bool,
+}
+
+/// A successfully parsed NDJSON record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NdjsonRecord {
+    /// The number (1-indexed) of the record. Blank lines are not records.
+    pub record: usize,
+    /// The line (1-indexed) the record was found on.
+    pub line: usize,
+    /// The byte offset where the record's line starts.
+    pub offset: usize,
+    /// The parsed document.
+    pub value: JsonValue,
+}
+
+/// A record rejected by `NdjsonReader`, kept for quarantine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedRecord {
+    /// The number (1-indexed) of the record.
+    pub record: usize,
+    /// Why the record was rejected. Its line and byte offset are relative
+    /// to the whole input.
+    pub error: ParseError,
+    /// The raw bytes of the line, without the line ending. Lines longer
+    /// than `max_input_size` are truncated to that length.
+    pub text: Vec<u8>,
+}
+
+impl<R: Read> NdjsonReader<R> {
+    /// Creates a new `NdjsonReader` that applies `options` to each record.
+    ///
+    /// This is called by the `parse_ndjson_reader` and
+    /// `parse_ndjson_reader_with` functions in `lib.rs`.
+    pub fn new(reader: R, options: &ParserOptions) -> Self {
+        NdjsonReader {
+            reader: BufReader::new(reader),
+            line: Vec::new(),
+            offset: 0,
+            lines: 0,
+            records: 0,
+            options: options.clone(),
+            rejected: Vec::new(),
+            keep_rejected: false,
+            rejected_count: 0,
+            failed: false,
+        }
+    }
+
+    /// Sets whether rejected records are kept, to be inspected with
+    /// `rejected` (off by default).
+    pub fn keep_rejected(mut self, keep: bool) -> Self {
+        self.keep_rejected = keep;
+        self
+    }
+
+    /// Returns the records rejected so far, if `keep_rejected` is on.
+    pub fn rejected(&self) -> &[RejectedRecord] {
+        &self.rejected
+    }
+
+    /// Takes the records rejected so far, e.g. to flush them to a
+    /// quarantine file periodically.
+    pub fn take_rejected(&mut self) -> Vec<RejectedRecord> {
+        std::mem::take(&mut self.rejected)
+    }
+
+    /// Returns the number of records rejected so far. This is counted
+    /// even when `keep_rejected` is off.
+    pub fn rejected_count(&self) -> usize {
+        self.rejected_count
+    }
+
+    /// Consumes the reader, returning the underlying reader.
+    ///
+    /// Any input that was buffered but not yet parsed is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Reads the next line into `self.line`, without its line ending.
+    ///
+    /// Returns `Ok(None)` at the end of input, and otherwise whether the
+    /// line had to be truncated because it exceeds `max_input_size`.
+    fn read_line(&mut self) -> io::Result<Option<bool>> {
+        self.line.clear();
+        let read = read_delimited(
+            &mut self.reader,
+            b'\n',
+            &mut self.line,
+            self.options.max_input_size,
+        )?;
+        if read.consumed == 0 {
+            return Ok(None);
+        }
+        self.offset += read.consumed;
+        if !read.truncated && self.line.last() == Some(&b'\r') {
+            self.line.pop();
+        }
+        Ok(Some(read.truncated))
+    }
+
+    /// Parses the line in `self.line`, which starts at `offset`.
+    fn parse_line(&self, truncated: bool, offset: usize) -> Result<JsonValue, ParseError> {
+        if truncated {
+            return Err(ParseError::new(
+                ErrorKind::SizeLimitExceeded,
+                self.lines,
+                1,
+                offset,
+            ));
+        }
+        let text = std::str::from_utf8(&self.line).map_err(|e| {
+            let valid = &self.line[..e.valid_up_to()];
+            // This is safe: the prefix was just validated.
+            let column = std::str::from_utf8(valid).unwrap().chars().count() + 1;
+            ParseError::new(
+                ErrorKind::InvalidUtf8,
+                self.lines,
+                column,
+                offset + e.valid_up_to(),
+            )
+        })?;
+        JsonValue::parse_with(text, &self.options).map_err(|e| e.shift(self.lines - 1, 0, offset))
+    }
+}
+
+impl<R: Read> Iterator for NdjsonReader<R> {
+    type Item = Result<NdjsonRecord, NdjsonError>;
+
+    /// Reads and parses the next non-blank line.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            let offset = self.offset;
+            let truncated = match self.read_line() {
+                Ok(Some(truncated)) => truncated,
+                Ok(None) => return None,
+                Err(e) => {
+                    self.failed = true;
+                    let error = ParseError::new(
+                        ErrorKind::Io(e.to_string()),
+                        self.lines + 1,
+                        1,
+                        self.offset,
+                    );
+                    return Some(Err(NdjsonError {
+                        record: self.records + 1,
+                        error,
+                    }));
+                }
+            };
+            self.lines += 1;
+
+            if !truncated && self.line.iter().all(|b| matches!(b, b' ' | b'\t')) {
+                continue; // Blank lines are not records.
+            }
+            self.records += 1;
+
+            return Some(match self.parse_line(truncated, offset) {
+                Ok(value) => Ok(NdjsonRecord {
+                    record: self.records,
+                    line: self.lines,
+                    offset,
+                    value,
+                }),
+                Err(error) => {
+                    self.rejected_count += 1;
+                    if self.keep_rejected {
+                        self.rejected.push(RejectedRecord {
+                            record: self.records,
+                            error: error.clone(),
+                            text: self.line.clone(),
+                        });
+                    }
+                    Err(NdjsonError {
+                        record: self.records,
+                        error,
+                    })
+                }
+            });
+        }
+    }
+}
+
+impl Iterator for NdjsonParser<'_> {
+    type Item = Result<JsonValue, NdjsonError>;
+
+    /// Parses the next non-blank line.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+
+            let (line, consumed) = match memchr(b'\n', self.rest.as_bytes()) {
+                Some(end) => (&self.rest[..end], end + 1),
+                None => (self.rest, self.rest.len()),
+            };
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let (line_number, line_offset) = (self.line, self.offset);
+            self.rest = &self.rest[consumed..];
+            self.offset += consumed;
+            self.line += 1;
+
+            if line.bytes().all(|b| matches!(b, b' ' | b'\t')) {
+                continue; // Blank lines are not records.
+            }
+
+            self.records += 1;
+            return Some(JsonValue::parse_with(line, &self.options).map_err(|error| {
+                self.failed = true;
+                NdjsonError {
+                    record: self.records,
+                    error: error.shift(line_number - 1, 0, line_offset),
+                }
+            }));
+        }
+    }
+}
+
+impl fmt::Display for NdjsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Error in record {}: {} at line {}, column {}.",
+            self.record, self.error.message, self.error.line, self.error.column
+        )
+    }
+}
+
+impl std::error::Error for NdjsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::{parse_ndjson, parse_ndjson_reader, parse_ndjson_reader_with, JsonNumber};
+
+    #[test]
+    fn test_ndjson_yields_each_record() {
+        let input = "{\"a\": 1}\r\n\n  \n[true]\n\"last\"";
+        let values: Vec<_> = parse_ndjson(input).collect::<Result<_, _>>().unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values[1], JsonValue::Array(vec![JsonValue::Boolean(true)]));
+        assert_eq!(values[2], JsonValue::String("last".to_string()));
+
+        assert_eq!(parse_ndjson("").count(), 0);
+        assert_eq!(parse_ndjson("\n\n").count(), 0);
+        assert_eq!(
+            parse_ndjson("1\n2\n")
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            vec![
+                JsonValue::Number(JsonNumber::I64(1)),
+                JsonValue::Number(JsonNumber::I64(2))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ndjson_errors_carry_record_and_line() {
+        let input = "{\"ok\": 1}\n\n{\"ok\": 2}\n{\"bad\" 3}\n{\"ok\": 4}";
+        let mut parser = parse_ndjson(input);
+        assert!(parser.next().unwrap().is_ok());
+        assert!(parser.next().unwrap().is_ok());
+
+        let err = parser.next().unwrap().unwrap_err();
+        assert_eq!(err.record, 3);
+        assert_eq!(err.error.kind, ErrorKind::ExpectedColon);
+        assert_eq!((err.error.line, err.error.column), (4, 8));
+        assert_eq!(&input[err.error.offset..err.error.offset + 1], "3");
+        assert_eq!(
+            err.to_string(),
+            "Error in record 3: Expected ':' at line 4, column 8."
+        );
+        // Iteration stops at the first bad record.
+        assert!(parser.next().is_none());
+
+        // A document may not span several lines.
+        let err = parse_ndjson("[1,\n2]").next().unwrap().unwrap_err();
+        assert_eq!((err.record, err.error.kind), (1, ErrorKind::UnclosedArray));
+    }
+
+    #[test]
+    fn test_ndjson_reader_resynchronizes_after_bad_records() {
+        let input = b"{\"id\": 1}\n{\"id\": \n\n[1, 2]\r\n\"a\xffb\"\n{\"id\": 5}";
+        let mut reader = parse_ndjson_reader(&input[..]).keep_rejected(true);
+        let results: Vec<_> = reader.by_ref().collect();
+        assert_eq!(results.len(), 5);
+
+        let first = results[0].as_ref().unwrap();
+        assert_eq!((first.record, first.line, first.offset), (1, 1, 0));
+
+        let err = results[1].as_ref().unwrap_err();
+        assert_eq!(err.record, 2);
+        assert_eq!(err.error.kind, ErrorKind::UnclosedObject);
+        assert_eq!((err.error.line, err.error.offset), (2, 10));
+
+        let third = results[2].as_ref().unwrap();
+        assert_eq!((third.record, third.line, third.offset), (3, 4, 19));
+
+        let err = results[3].as_ref().unwrap_err();
+        assert_eq!(err.error.kind, ErrorKind::InvalidUtf8);
+        assert_eq!((err.error.line, err.error.column), (5, 3));
+
+        let last = results[4].as_ref().unwrap();
+        assert_eq!((last.record, last.line), (5, 6));
+
+        assert_eq!(reader.rejected_count(), 2);
+        let rejected = reader.take_rejected();
+        assert_eq!(rejected[0].text, b"{\"id\": ");
+        assert_eq!(rejected[1].record, 4);
+        assert!(reader.rejected().is_empty());
+    }
+
+    #[test]
+    fn test_ndjson_reader_limits_line_length() {
+        let options = ParserOptions::new().max_input_size(8);
+        let input = "[1, 2]\n[1, 2, 3, 4, 5, 6]\n[3]\n";
+        let mut reader = parse_ndjson_reader_with(input.as_bytes(), &options).keep_rejected(true);
+        let records: Vec<_> = reader.by_ref().collect();
+        assert_eq!(records.len(), 3);
+        assert!(records[0].is_ok());
+        let err = records[1].as_ref().unwrap_err();
+        assert_eq!(err.error.kind, ErrorKind::SizeLimitExceeded);
+        assert_eq!((err.error.line, err.error.offset), (2, 7));
+        assert_eq!(reader.rejected()[0].text, b"[1, 2, 3");
+        assert_eq!(records[2].as_ref().unwrap().line, 3);
+    }
+
+    #[test]
+    fn test_ndjson_reader_stops_on_io_error() {
+        struct Broken(bool);
+        impl Read for Broken {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if std::mem::replace(&mut self.0, true) {
+                    return Err(io::Error::other("disk on fire"));
+                }
+                buf[..4].copy_from_slice(b"[1]\n");
+                Ok(4)
+            }
+        }
+
+        let mut reader = parse_ndjson_reader(Broken(false));
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.error.kind, ErrorKind::Io("disk on fire".to_string()));
+        assert_eq!(err.record, 2);
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,242 @@
+//! Contains `ParserOptions`, the configurable limits used by every parser.
+
+use crate::{DEFAULT_MAX_DEPTH, MAX_JSON_SIZE_BYTES};
+
+/// Configurable limits for parsing untrusted JSON.
+///
+/// `ParserOptions` is a builder: start from `ParserOptions::new()` (or
+/// `Default`) and chain the setters you need. The same options can be
+/// passed to `parse_streaming_with`, `parse_reader_with`,
+/// `JsonValue::parse_with` and `PushParser::with_options`.
+///
+/// Each limit fails with its own error, so services can enforce separate
+/// quotas on untrusted payloads. All lengths are measured in bytes, and
+/// string lengths are measured *after* escapes have been decoded.
+///
+/// # Examples
+/// ```
+/// use rill_json::{parse_streaming_with, ParserOptions};
+///
+/// let options = ParserOptions::new().max_depth(2).max_string_length(5);
+///
+/// let events: Result<Vec<_>, _> = parse_streaming_with("[[1]]", &options).unwrap().collect();
+/// assert!(events.is_ok());
+///
+/// let err = parse_streaming_with(r#"["too long"]"#, &options)
+///     .unwrap()
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap_err();
+/// assert_eq!(err.message, "String exceeds maximum length");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// The maximum nesting depth of arrays and objects.
+    pub(crate) max_depth: usize,
+    /// The maximum size of the whole input, in bytes.
+    pub(crate) max_input_size: usize,
+    /// The maximum length of a string value, in bytes.
+    pub(crate) max_string_length: usize,
+    /// The maximum length of an object key, in bytes.
+    pub(crate) max_key_length: usize,
+    /// The maximum number of values in a single array.
+    pub(crate) max_array_members: usize,
+    /// The maximum number of key/value pairs in a single object.
+    pub(crate) max_object_members: usize,
+    /// The maximum number of `ParserEvent`s in the whole document.
+    pub(crate) max_events: usize,
+    /// The accepted JSON dialect.
+    pub(crate) dialect: Dialect,
+    /// What to do with a `\u` escape that is half of a surrogate pair.
+    pub(crate) lone_surrogates: LoneSurrogatePolicy,
+    /// Whether numbers are kept as their source text.
+    pub(crate) lossless_numbers: bool,
+}
+
+/// The flavour of JSON accepted by the parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Dialect {
+    /// Strict RFC 8259 JSON. This is the default.
+    #[default]
+    Json,
+    /// [JSON5](https://spec.json5.org), a superset of JSON meant for
+    /// human-edited files such as configuration.
+    ///
+    /// On top of JSON, it accepts `//` and `/* */` comments, trailing
+    /// commas, single-quoted strings, unquoted identifier keys, hexadecimal
+    /// numbers, leading and trailing decimal points, explicit `+` signs,
+    /// `Infinity` and `NaN`, the extra JSON5 escapes (`\v`, `\0`, `\xHH`)
+    /// and line continuations in strings.
+    Json5,
+}
+
+/// How to decode a `\u` escape that encodes a lone UTF-16 surrogate.
+///
+/// JSON escapes characters outside the Basic Multilingual Plane as a
+/// surrogate pair, such as `"\uD83D\uDE00"`, which always decodes to the
+/// character it encodes. A high surrogate (`\uD800` to `\uDBFF`) that is
+/// not followed by an escaped low surrogate (`\uDC00` to `\uDFFF`), or a
+/// low surrogate on its own, encodes no character at all; RFC 8259 leaves
+/// its meaning to the implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum LoneSurrogatePolicy {
+    /// Fail with `ErrorKind::LoneSurrogate`. This is the default.
+    #[default]
+    Error,
+    /// Decode the escape as U+FFFD REPLACEMENT CHARACTER.
+    ///
+    /// This loses the surrogate: a Rust `str` cannot hold one. Use
+    /// `Preserve` to keep it.
+    Replace,
+    /// Keep the surrogate. A string or key that holds one is reported as
+    /// a `Wtf8String`, in a `ParserEvent::WtfString` or
+    /// `ParserEvent::WtfKey` event instead of `String` or `Key`, and in a
+    /// `JsonValue::WtfString`. Writing it back out as JSON escapes the
+    /// surrogate as `\uXXXX` again, so nothing is lost.
+    ///
+    /// A `JsonValue` object key is a `String`, so `JsonValue::parse_with`
+    /// fails with `ErrorKind::LoneSurrogate` on a key that holds a lone
+    /// surrogate; read such keys from the streaming events instead.
+    ///
+    /// With serde, such strings and keys reach the visitor as their WTF-8
+    /// bytes, which `Wtf8String` and `JsonValue` accept.
+    Preserve,
+}
+
+impl ParserOptions {
+    /// Creates the default options.
+    ///
+    /// The nesting depth is limited to 100 and the input size to 10MB;
+    /// every other limit is disabled.
+    pub fn new() -> Self {
+        ParserOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_input_size: MAX_JSON_SIZE_BYTES,
+            max_string_length: usize::MAX,
+            max_key_length: usize::MAX,
+            max_array_members: usize::MAX,
+            max_object_members: usize::MAX,
+            max_events: usize::MAX,
+            dialect: Dialect::Json,
+            lone_surrogates: LoneSurrogatePolicy::Error,
+            lossless_numbers: false,
+        }
+    }
+
+    /// Sets the maximum nesting depth (e.g., `[[[]]]` has a depth of 3).
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the maximum size of the input, in bytes.
+    ///
+    /// Use `usize::MAX` to disable the limit.
+    pub fn max_input_size(mut self, max_input_size: usize) -> Self {
+        self.max_input_size = max_input_size;
+        self
+    }
+
+    /// Sets the maximum length of a string value, in bytes.
+    pub fn max_string_length(mut self, max_string_length: usize) -> Self {
+        self.max_string_length = max_string_length;
+        self
+    }
+
+    /// Sets the maximum length of an object key, in bytes.
+    pub fn max_key_length(mut self, max_key_length: usize) -> Self {
+        self.max_key_length = max_key_length;
+        self
+    }
+
+    /// Sets the maximum number of values in a single array.
+    pub fn max_array_members(mut self, max_array_members: usize) -> Self {
+        self.max_array_members = max_array_members;
+        self
+    }
+
+    /// Sets the maximum number of key/value pairs in a single object.
+    pub fn max_object_members(mut self, max_object_members: usize) -> Self {
+        self.max_object_members = max_object_members;
+        self
+    }
+
+    /// Sets the maximum number of `ParserEvent`s in the whole document.
+    pub fn max_events(mut self, max_events: usize) -> Self {
+        self.max_events = max_events;
+        self
+    }
+
+    /// Sets the accepted JSON dialect (strict JSON by default).
+    ///
+    /// # Examples
+    /// ```
+    /// use rill_json::{Dialect, JsonValue, ParserOptions};
+    ///
+    /// let options = ParserOptions::new().dialect(Dialect::Json5);
+    /// let config = "{\n  // Comments are allowed\n  port: 0x50,\n  hosts: ['a', 'b',],\n}";
+    /// let value = JsonValue::parse_with(config, &options).unwrap();
+    /// assert_eq!(value.stringify().unwrap(), r#"{"hosts":["a","b"],"port":80}"#);
+    ///
+    /// assert!(JsonValue::parse(config).is_err());
+    /// ```
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Sets how lone surrogates in `\u` escapes are decoded (rejected by
+    /// default).
+    ///
+    /// # Examples
+    /// ```
+    /// use rill_json::{JsonValue, LoneSurrogatePolicy, ParserOptions};
+    ///
+    /// let input = r#"["\uD83D\uDE00", "\uD83D!"]"#;
+    /// assert!(JsonValue::parse(input).is_err());
+    ///
+    /// let options = ParserOptions::new().lone_surrogates(LoneSurrogatePolicy::Replace);
+    /// let value = JsonValue::parse_with(input, &options).unwrap();
+    /// assert_eq!(value.stringify().unwrap(), "[\"\u{1F600}\",\"\u{FFFD}!\"]");
+    /// ```
+    pub fn lone_surrogates(mut self, policy: LoneSurrogatePolicy) -> Self {
+        self.lone_surrogates = policy;
+        self
+    }
+
+    /// Keeps every number as its exact source text, in a
+    /// `JsonNumber::Raw`, instead of converting it (off by default).
+    ///
+    /// Use this when numbers must survive a round trip unchanged, such as
+    /// monetary amounts, integers beyond `u64`, or `-0`. In the JSON5
+    /// dialect, a number that is not valid JSON is kept as the JSON text of
+    /// the same value: `0x1F` as `31`, `+1` as `1` and `.5` as `0.5`.
+    /// `Infinity` and `NaN` have no JSON text, and are still converted.
+    ///
+    /// # Examples
+    /// ```
+    /// use rill_json::{JsonNumber, JsonValue, ParserOptions};
+    ///
+    /// let options = ParserOptions::new().lossless_numbers(true);
+    /// let input = r#"{"amount":1234.10,"id":123456789012345678901234567890,"zero":-0}"#;
+    /// let value = JsonValue::parse_with(input, &options).unwrap();
+    /// assert_eq!(value.stringify().unwrap(), input);
+    ///
+    /// if let JsonValue::Object(map) = &value {
+    ///     let JsonValue::Number(JsonNumber::Raw(amount)) = &map["amount"] else { panic!() };
+    ///     assert_eq!(amount.get(), "1234.10");
+    ///     assert_eq!(amount.to_f64(), Some(1234.1));
+    /// }
+    /// ```
+    pub fn lossless_numbers(mut self, lossless: bool) -> Self {
+        self.lossless_numbers = lossless;
+        self
+    }
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions::new()
+    }
+}
@@ -4,12 +4,15 @@
 //! as a state machine that consumes `Token`s from the `Tokenizer` and
 //! emits `ParserEvent`s.
 
-use crate::error::ParseError;
+use crate::error::{ErrorKind, Label, ParseError};
+use crate::options::{Dialect, ParserOptions};
+use crate::raw::RawValue;
 use crate::token::{Token, TokenType};
 use crate::tokenizer::Tokenizer;
 use crate::value::JsonNumber;
+use crate::wtf8::Wtf8String;
 use std::borrow::Cow;
-use std::iter::Peekable;
+use std::ops::Range;
 
 /// A single event emitted by the `StreamingParser`.
 ///
@@ -30,6 +33,12 @@ pub enum ParserEvent<'a> {
     Key(Cow<'a, str>),
     /// A JSON string value (e.g., `... : "value"`).
     String(Cow<'a, str>),
+    /// An object key holding a lone UTF-16 surrogate (e.g., `"\uD800"`),
+    /// reported instead of `Key` under `LoneSurrogatePolicy::Preserve`.
+    WtfKey(Wtf8String),
+    /// A string value holding a lone UTF-16 surrogate, reported instead of
+    /// `String` under `LoneSurrogatePolicy::Preserve`.
+    WtfString(Wtf8String),
     /// A JSON number value (e.g., `123`, `-0.5`, `1e10`).
     Number(JsonNumber),
     /// A JSON boolean value (`true` or `false`).
@@ -38,6 +47,47 @@ pub enum ParserEvent<'a> {
     Null,
 }
 
+/// The location of an event in the input.
+///
+/// `start` and `end` are byte offsets, so `&input[span.start..span.end]`
+/// is the source text of the token that produced the event (e.g. the
+/// quoted, still-escaped string, or the opening `{` of an object).
+/// `line` and `column` locate `start`, with columns counted in characters.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    /// The 0-indexed byte offset where the event's token starts.
+    pub start: usize,
+    /// The 0-indexed byte offset just past the end of the event's token.
+    pub end: usize,
+    /// The line (1-indexed) where the event's token starts.
+    pub line: usize,
+    /// The column (1-indexed) where the event's token starts.
+    pub column: usize,
+}
+
+/// A `ParserEvent` together with its location in the input.
+///
+/// Yielded by `StreamingParser::next_spanned`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedEvent<'a> {
+    /// The event itself.
+    pub event: ParserEvent<'a>,
+    /// Where the event came from.
+    pub span: Span,
+}
+
+impl Span {
+    /// Creates the span covering a single token.
+    fn of(token: &Token) -> Self {
+        Span {
+            start: token.offset,
+            end: token.end,
+            line: token.line,
+            column: token.column,
+        }
+    }
+}
+
 /// Internal state machine for the parser.
 ///
 /// This enum tracks what the parser *expects* to see next,
@@ -57,399 +107,928 @@ enum ParserState {
     ExpectObjectCommaOrEnd,     // After value in object - expect ',' or '}'
 }
 
+impl ParserState {
+    /// Returns `true` in the states where the next token must be a value.
+    fn expects_value(&self) -> bool {
+        matches!(
+            self,
+            ParserState::ExpectValue
+                | ParserState::ExpectArrayFirstValueOrEnd
+                | ParserState::ExpectArrayValue
+                | ParserState::ExpectObjectValue
+        )
+    }
+}
+
+/// The grammar state machine shared by every parser front-end.
+///
+/// It consumes one `Token` at a time and decides which `ParserEvent` (if
+/// any) that token produces. Keeping it separate from the tokenizer lets
+/// the slice-based `StreamingParser` and the chunk-fed `PushParser` share
+/// exactly the same grammar rules.
+#[derive(Debug, Clone)]
+pub(crate) struct StateMachine {
+    /// A stack of states, used for tracking nested objects and arrays.
+    state_stack: Vec<ParserState>,
+    /// The configured limits (nesting depth, string lengths, ...).
+    options: ParserOptions,
+    /// The *current* nesting depth of the parser.
+    depth: usize,
+    /// Bookkeeping for each open array or object, innermost last.
+    frames: Vec<Frame>,
+    /// The number of events emitted so far.
+    events: usize,
+}
+
+/// Bookkeeping for one open array or object.
+#[derive(Debug, Clone)]
+struct Frame {
+    /// `true` for an object, `false` for an array.
+    object: bool,
+    /// The number of members seen so far.
+    members: usize,
+    /// The most recent key, for an object. The buffer is reused between
+    /// keys, so tracking it does not allocate for every member.
+    key: String,
+    /// The line of the opening bracket.
+    line: usize,
+    /// The column of the opening bracket.
+    column: usize,
+    /// The byte offset of the opening bracket.
+    offset: usize,
+}
+
+/// Returns the `String` event for a string token, or its `Key` event if
+/// `key` is set.
+fn string_event<'a>(kind: &TokenType<'a>, key: bool) -> ParserEvent<'a> {
+    match (kind, key) {
+        (TokenType::String(s), false) => ParserEvent::String(s.clone()),
+        (TokenType::String(s), true) => ParserEvent::Key(s.clone()),
+        (TokenType::WtfString(s), false) => ParserEvent::WtfString(s.clone()),
+        (TokenType::WtfString(s), true) => ParserEvent::WtfKey(s.clone()),
+        _ => unreachable!("not a string token"),
+    }
+}
+
+/// Appends `/token` to a JSON Pointer (RFC 6901), escaping `~` as `~0`
+/// and `/` as `~1`.
+pub(crate) fn push_pointer_token(path: &mut String, token: &str) {
+    path.push('/');
+    for c in token.chars() {
+        match c {
+            '~' => path.push_str("~0"),
+            '/' => path.push_str("~1"),
+            _ => path.push(c),
+        }
+    }
+}
+
 /// The main streaming JSON parser.
 ///
 /// This struct is an `Iterator` that yields `Result<ParserEvent, ParseError>`.
 /// It is created by the `parse_streaming` function.
 pub struct StreamingParser<'a> {
+    /// The input string, kept for slicing out raw values.
+    input: &'a str,
     /// The internal tokenizer (lexer) that breaks the input string into `Token`s.
-    tokenizer: Peekable<Tokenizer<'a>>,
-    /// A stack of states, used for tracking nested objects and arrays.
-    state_stack: Vec<ParserState>,
-    /// The maximum allowed nesting depth to prevent DoS attacks.
-    max_depth: usize,
-    /// The *current* nesting depth of the parser.
-    depth: usize,
+    tokenizer: Tokenizer<'a>,
+    /// The grammar state machine that turns tokens into events.
+    machine: StateMachine,
 }
 
 impl<'a> StreamingParser<'a> {
     /// Creates a new `StreamingParser` for a given input string.
     ///
-    /// This is called by the `parse_streaming` function in `lib.rs`.
+    /// Only the nesting depth is limited; see `with_options` for the others.
     pub fn new(input: &'a str, max_depth: usize) -> Self {
+        Self::with_options(input, &ParserOptions::new().max_depth(max_depth))
+    }
+
+    /// Creates a new `StreamingParser` that enforces the given limits.
+    ///
+    /// This is called by the `parse_streaming_with` function in `lib.rs`,
+    /// which also checks the input size limit before parsing begins.
+    pub fn with_options(input: &'a str, options: &ParserOptions) -> Self {
         StreamingParser {
-            tokenizer: Tokenizer::new(input).peekable(),
-            state_stack: vec![ParserState::ExpectValue],
-            max_depth,
-            depth: 0,
+            input,
+            tokenizer: Tokenizer::new(input)
+                .with_dialect(options.dialect)
+                .with_lone_surrogates(options.lone_surrogates)
+                .with_lossless_numbers(options.lossless_numbers),
+            machine: StateMachine::new(options),
         }
     }
 
-    /// A helper function to create a `ParseError` from a token's location.
-    fn error_from_token(&self, message: String, token: &Token<'a>) -> ParseError {
-        ParseError {
-            message,
-            line: token.line,
-            column: token.column,
-        }
+    /// Returns the JSON Pointer (RFC 6901) of the most recently returned
+    /// event, e.g. `/users/3/email`.
+    ///
+    /// For a `Key` event the pointer already names the member's value, and
+    /// for `StartObject`/`StartArray` and the matching end events it names
+    /// the container itself. Before the first event it is `""`, which
+    /// refers to the whole document.
+    ///
+    /// # Examples
+    /// ```
+    /// use rill_json::{parse_streaming, ParserEvent};
+    ///
+    /// let mut parser = parse_streaming(r#"{"users": [{"id": 1}, {"email": "a@b.c"}]}"#).unwrap();
+    /// while let Some(event) = parser.next() {
+    ///     if let ParserEvent::String(email) = event.unwrap() {
+    ///         assert_eq!(email, "a@b.c");
+    ///         assert_eq!(parser.current_path(), "/users/1/email");
+    ///         assert_eq!(parser.depth(), 3);
+    ///     }
+    /// }
+    /// ```
+    pub fn current_path(&self) -> String {
+        self.machine.current_path()
     }
-}
 
-/// The main implementation of the parser's `Iterator` trait.
-/// This is where the state machine logic lives.
-impl<'a> Iterator for StreamingParser<'a> {
-    type Item = Result<ParserEvent<'a>, ParseError>;
+    /// Returns the current nesting depth: the number of arrays and objects
+    /// that are open after the most recently returned event.
+    pub fn depth(&self) -> usize {
+        self.machine.depth()
+    }
 
-    /// Consumes the next token and advances the parser's state.
-    fn next(&mut self) -> Option<Self::Item> {
-        // Get the next token from the tokenizer.
-        let token_result = self.tokenizer.next();
+    /// Skips over the next value, however deeply nested, without emitting
+    /// its events.
+    ///
+    /// This is much cheaper than pulling and discarding the events: the
+    /// value is scanned at the byte level, so strings are not unescaped and
+    /// numbers are not converted. Malformed input inside the value (an
+    /// unterminated string, a bad escape, mismatched brackets, ...) is
+    /// still reported as an error, and so is a value that breaks one of the
+    /// `ParserOptions` limits, exactly as if it had been parsed.
+    ///
+    /// Call it after a `Key` event to skip that member's value, or inside
+    /// an array to skip the next element. It returns `Ok(false)` if there is
+    /// no value to skip because the enclosing array or object ends (the end
+    /// event is left for `next`), or because the document is complete.
+    ///
+    /// # Examples
+    /// ```
+    /// use rill_json::{parse_streaming, JsonNumber, ParserEvent};
+    ///
+    /// let input = r#"{"huge": [{"a": [1, 2, "\n"]}, 3], "id": 7}"#;
+    /// let mut parser = parse_streaming(input).unwrap();
+    /// let mut id = None;
+    /// while let Some(event) = parser.next() {
+    ///     match event.unwrap() {
+    ///         ParserEvent::Key(key) if key == "id" => id = parser.next(),
+    ///         ParserEvent::Key(_) => {
+    ///             parser.skip_value().unwrap();
+    ///         }
+    ///         _ => {}
+    ///     }
+    /// }
+    /// assert_eq!(id.unwrap().unwrap(), ParserEvent::Number(JsonNumber::I64(7)));
+    /// ```
+    pub fn skip_value(&mut self) -> Result<bool, ParseError> {
+        self.skip().map(|range| range.is_some())
+    }
 
-        let mut current_token = match token_result {
-            Some(Ok(token)) => Some(token),
-            Some(Err(e)) => return Some(Err(e)), // Tokenizer error
-            None => None,                        // End of input
-        };
+    /// Returns the exact source text of the next value, without emitting
+    /// its events.
+    ///
+    /// This is useful for forwarding a sub-document untouched: the value is
+    /// scanned (and checked) exactly as by `skip_value`, and the returned
+    /// `RawValue` borrows its text, formatting included, from the input.
+    /// Embed it in a `JsonValue` with `JsonValue::Raw` to write it back out
+    /// verbatim. Returns `Ok(None)` where `skip_value` would return
+    /// `Ok(false)`.
+    ///
+    /// # Errors
+    /// Under `Dialect::Json5` this always fails with
+    /// `ErrorKind::RawValueInJson5`, without consuming anything: JSON5
+    /// text is not valid JSON, so it cannot be written back out verbatim.
+    ///
+    /// # Examples
+    /// ```
+    /// use rill_json::parse_streaming;
+    ///
+    /// let input = r#"{"id": 7, "payload": {"b": [1, 2],  "a": "\u00e9"}}"#;
+    /// let mut parser = parse_streaming(input).unwrap();
+    /// parser.next(); // StartObject
+    /// parser.next(); // Key("id")
+    /// parser.next(); // Number(7)
+    /// parser.next(); // Key("payload")
+    ///
+    /// let raw = parser.next_raw_value().unwrap().unwrap();
+    /// assert_eq!(raw.get(), r#"{"b": [1, 2],  "a": "\u00e9"}"#);
+    /// ```
+    pub fn next_raw_value(&mut self) -> Result<Option<RawValue<'a>>, ParseError> {
+        if self.machine.json5() {
+            self.tokenizer.peek_byte();
+            let (line, column, offset) = self.tokenizer.location();
+            return Err(ParseError::new(
+                ErrorKind::RawValueInJson5,
+                line,
+                column,
+                offset,
+            ));
+        }
+        let input = self.input;
+        self.skip()
+            .map(|range| range.map(|range| RawValue::from_source(&input[range])))
+    }
 
-        // Loop handles "non-event" tokens (like `,` or `:`)
-        // that advance the state but don't emit a `ParserEvent`.
+    /// Skips the next value, returning its byte range in the input, or
+    /// `None` if there is no value to skip.
+    fn skip(&mut self) -> Result<Option<Range<usize>>, ParseError> {
+        // Loop handles the `:` or `,` that may precede the value.
         loop {
-            let state_tuple = (current_token.as_ref(), self.state_stack.last());
-
-            // Determine the current token and state.
-            let (token, state) = match state_tuple {
-                (Some(token), Some(state)) => (token, state.clone()),
-                // End of input, but we're still in a nested state.
-                (None, Some(state)) => {
-                    if *state == ParserState::ExpectValue && self.state_stack.len() == 1 {
-                        // We expected a value, but got clean EOF. Valid for empty input.
-                        // The tokenizer.next() would have returned None, so we'd be in (None, None).
-                        // This path is for cases like `[1,` and then EOF.
-                        // A clean EOF on ExpectValue (e.g. empty string) is handled by (None, None)
-                    }
-                    // Handle clean EOF on empty input
-                    if *state == ParserState::ExpectValue && self.state_stack.len() == 1 {
-                        if let Some(_tok) = &current_token {
-                            // This case should not be hit if current_token is None
-                        } else {
-                            // This means tokenizer.next() returned None *and* state is ExpectValue
-                            return None;
-                        }
-                    }
+            let byte = match self.tokenizer.peek_byte() {
+                Some(byte) => byte,
+                None => return self.machine.end_of_input().map(|()| None),
+            };
 
-                    // Return a more specific error message based on the parser's state
-                    let msg = match state {
-                        ParserState::ExpectObjectCommaOrEnd
-                        | ParserState::ExpectObjectFirstKeyOrEnd
-                        | ParserState::ExpectObjectKey
-                        | ParserState::ExpectObjectColon
-                        | ParserState::ExpectObjectValue => "Unclosed object",
-                        ParserState::ExpectArrayCommaOrEnd
-                        | ParserState::ExpectArrayFirstValueOrEnd
-                        | ParserState::ExpectArrayValue => "Unclosed array",
-                        _ => "Unexpected end of input, unclosed structure",
-                    };
+            if matches!(byte, b']' | b'}') && self.machine.can_close(byte) {
+                return Ok(None);
+            }
 
-                    return Some(Err(ParseError {
-                        message: msg.to_string(),
-                        line: 0, // We don't have a token for location info
-                        column: 0,
-                    }));
-                }
-                (None, None) => return None, // Clean end
-                // We have a token, but the state stack is empty (parser finished).
-                (Some(token), None) => {
-                    return Some(Err(
-                        self.error_from_token("Unexpected trailing token".to_string(), token)
-                    ));
+            if self.tokenizer.is_value_start(byte) {
+                let (line, column, start) = self.tokenizer.location();
+                if !self.machine.expects_value() {
+                    // Let the grammar reject a value in the wrong place (e.g.
+                    // where a key is expected), as it would when parsing.
+                    let placeholder = Token {
+                        kind: TokenType::Null,
+                        line,
+                        column,
+                        offset: start,
+                        end: start,
+                    };
+                    self.machine.handle_token(&placeholder)?;
                 }
-            };
 
-            // This is the main state machine logic.
-            let result = match (state, &token.kind) {
-                // --- Root level or nested value expected ---
-                (ParserState::ExpectValue, TokenType::LeftBracket) => {
-                    if self.depth >= self.max_depth {
-                        return Some(Err(self.error_from_token(
-                            "Maximum nesting depth exceeded".to_string(),
-                            token,
-                        )));
+                // Feed the value's tokens through the grammar without
+                // building their events, until it is complete again.
+                let depth = self.machine.depth();
+                loop {
+                    match self.tokenizer.next_skipped() {
+                        Some(Ok((token, string_len))) => {
+                            self.machine.handle_skipped(&token, string_len)?;
+                        }
+                        Some(Err(e)) => return Err(self.machine.add_context(e)),
+                        None => return self.machine.end_of_input().map(|()| None),
                     }
-                    self.depth += 1;
-                    self.state_stack.pop();
-                    self.state_stack
-                        .push(ParserState::ExpectArrayFirstValueOrEnd);
-                    Ok(Some(ParserEvent::StartArray))
-                }
-                (ParserState::ExpectValue, TokenType::LeftBrace) => {
-                    if self.depth >= self.max_depth {
-                        return Some(Err(self.error_from_token(
-                            "Maximum nesting depth exceeded".to_string(),
-                            token,
-                        )));
+                    if self.machine.depth() == depth {
+                        let (_, _, end) = self.tokenizer.location();
+                        return Ok(Some(start..end));
                     }
-                    self.depth += 1;
-                    self.state_stack.pop();
-                    self.state_stack
-                        .push(ParserState::ExpectObjectFirstKeyOrEnd);
-                    Ok(Some(ParserEvent::StartObject))
-                }
-                (ParserState::ExpectValue, TokenType::String(s)) => {
-                    self.state_stack.pop();
-                    Ok(Some(ParserEvent::String(s.clone())))
-                }
-                (ParserState::ExpectValue, TokenType::Number(n)) => {
-                    self.state_stack.pop();
-                    Ok(Some(ParserEvent::Number(*n)))
-                }
-                (ParserState::ExpectValue, TokenType::Boolean(b)) => {
-                    self.state_stack.pop();
-                    Ok(Some(ParserEvent::Boolean(*b)))
-                }
-                (ParserState::ExpectValue, TokenType::Null) => {
-                    self.state_stack.pop();
-                    Ok(Some(ParserEvent::Null))
-                }
-                (ParserState::ExpectValue, _) => {
-                    Err(self.error_from_token("Expected a value".to_string(), token))
                 }
+            }
 
-                // --- Inside Array: expecting first value or ']' (empty array) ---
-                (ParserState::ExpectArrayFirstValueOrEnd, TokenType::RightBracket) => {
-                    self.depth -= 1;
-                    self.state_stack.pop();
-                    Ok(Some(ParserEvent::EndArray))
-                }
-                (ParserState::ExpectArrayFirstValueOrEnd, TokenType::LeftBracket) => {
-                    if self.depth >= self.max_depth {
-                        return Some(Err(self.error_from_token(
-                            "Maximum nesting depth exceeded".to_string(),
-                            token,
-                        )));
-                    }
-                    self.depth += 1;
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    self.state_stack
-                        .push(ParserState::ExpectArrayFirstValueOrEnd);
-                    Ok(Some(ParserEvent::StartArray))
-                }
-                (ParserState::ExpectArrayFirstValueOrEnd, TokenType::LeftBrace) => {
-                    if self.depth >= self.max_depth {
-                        return Some(Err(self.error_from_token(
-                            "Maximum nesting depth exceeded".to_string(),
-                            token,
-                        )));
-                    }
-                    self.depth += 1;
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    self.state_stack
-                        .push(ParserState::ExpectObjectFirstKeyOrEnd);
-                    Ok(Some(ParserEvent::StartObject))
-                }
-                (ParserState::ExpectArrayFirstValueOrEnd, TokenType::String(s)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    Ok(Some(ParserEvent::String(s.clone())))
-                }
-                (ParserState::ExpectArrayFirstValueOrEnd, TokenType::Number(n)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    Ok(Some(ParserEvent::Number(*n)))
-                }
-                (ParserState::ExpectArrayFirstValueOrEnd, TokenType::Boolean(b)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    Ok(Some(ParserEvent::Boolean(*b)))
-                }
-                (ParserState::ExpectArrayFirstValueOrEnd, TokenType::Null) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    Ok(Some(ParserEvent::Null))
-                }
-                (ParserState::ExpectArrayFirstValueOrEnd, _) => {
-                    Err(self.error_from_token("Expected value or ']'".to_string(), token))
+            // A separator, or an invalid token: the grammar decides.
+            match self.tokenizer.next() {
+                Some(Ok(token)) => {
+                    self.machine.handle_token(&token)?;
                 }
+                Some(Err(e)) => return Err(self.machine.add_context(e)),
+                None => return self.machine.end_of_input().map(|()| None),
+            }
+        }
+    }
 
-                // --- Inside Array: after comma, expecting value (no ']' allowed) ---
-                (ParserState::ExpectArrayValue, TokenType::LeftBracket) => {
-                    if self.depth >= self.max_depth {
-                        return Some(Err(self.error_from_token(
-                            "Maximum nesting depth exceeded".to_string(),
-                            token,
-                        )));
-                    }
-                    self.depth += 1;
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    self.state_stack
-                        .push(ParserState::ExpectArrayFirstValueOrEnd);
-                    Ok(Some(ParserEvent::StartArray))
-                }
-                (ParserState::ExpectArrayValue, TokenType::LeftBrace) => {
-                    if self.depth >= self.max_depth {
-                        return Some(Err(self.error_from_token(
-                            "Maximum nesting depth exceeded".to_string(),
-                            token,
-                        )));
-                    }
-                    self.depth += 1;
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    self.state_stack
-                        .push(ParserState::ExpectObjectFirstKeyOrEnd);
-                    Ok(Some(ParserEvent::StartObject))
-                }
-                (ParserState::ExpectArrayValue, TokenType::String(s)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    Ok(Some(ParserEvent::String(s.clone())))
-                }
-                (ParserState::ExpectArrayValue, TokenType::Number(n)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    Ok(Some(ParserEvent::Number(*n)))
-                }
-                (ParserState::ExpectArrayValue, TokenType::Boolean(b)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    Ok(Some(ParserEvent::Boolean(*b)))
-                }
-                (ParserState::ExpectArrayValue, TokenType::Null) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
-                    Ok(Some(ParserEvent::Null))
-                }
-                // Check for invalid trailing comma `[1,,2]`
-                (ParserState::ExpectArrayValue, TokenType::RightBracket) => {
-                    Err(self
-                        .error_from_token("Unexpected ']', expected a value".to_string(), token))
-                }
-                (ParserState::ExpectArrayValue, _) => {
-                    Err(self.error_from_token("Expected a value".to_string(), token))
+    /// Returns the next event together with its location in the input.
+    ///
+    /// This advances the same iterator as `next`, so the two can be mixed
+    /// freely; use it when you need to point back at the source, e.g. to
+    /// report a bad value for a known key.
+    ///
+    /// # Examples
+    /// ```
+    /// use rill_json::{parse_streaming, ParserEvent, Span};
+    ///
+    /// let input = "{\n  \"port\": \"eighty\"\n}";
+    /// let mut parser = parse_streaming(input).unwrap();
+    /// parser.next(); // StartObject
+    /// parser.next(); // Key("port")
+    ///
+    /// let value = parser.next_spanned().unwrap().unwrap();
+    /// assert_eq!(value.event, ParserEvent::String("eighty".into()));
+    /// assert_eq!(value.span, Span { start: 12, end: 20, line: 2, column: 11 });
+    /// assert_eq!(&input[value.span.start..value.span.end], "\"eighty\"");
+    /// ```
+    pub fn next_spanned(&mut self) -> Option<Result<SpannedEvent<'a>, ParseError>> {
+        // Loop handles "non-event" tokens (like `,` or `:`)
+        // that advance the state but don't emit a `ParserEvent`.
+        loop {
+            let token = match self.tokenizer.next() {
+                Some(Ok(token)) => token,
+                Some(Err(e)) => return Some(Err(self.machine.add_context(e))), // Tokenizer error
+                None => {
+                    // End of input: either a clean end or an unclosed structure.
+                    return self.machine.end_of_input().err().map(Err);
                 }
+            };
 
-                // --- Inside Array: after a value, expecting ',' or ']' ---
-                (ParserState::ExpectArrayCommaOrEnd, TokenType::Comma) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayValue;
-                    Ok(None) // Comma is consumed, state changes, but no event emitted.
-                }
-                (ParserState::ExpectArrayCommaOrEnd, TokenType::RightBracket) => {
-                    self.depth -= 1;
-                    self.state_stack.pop();
-                    Ok(Some(ParserEvent::EndArray))
-                }
-                (ParserState::ExpectArrayCommaOrEnd, _) => {
-                    Err(self.error_from_token("Expected ',' or ']'".to_string(), token))
+            match self.machine.handle_token(&token) {
+                // We have an event to emit. Return it.
+                Ok(Some(event)) => {
+                    let span = Span::of(&token);
+                    return Some(Ok(SpannedEvent { event, span }));
                 }
+                // This was a non-event token (like `,` or `:`).
+                // We loop again to get the *next* token.
+                Ok(None) => continue,
+                // A parsing error occurred.
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
 
-                // --- Inside Object: expecting first key or '}' (empty object) ---
-                (ParserState::ExpectObjectFirstKeyOrEnd, TokenType::String(s)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectColon;
-                    Ok(Some(ParserEvent::Key(s.clone())))
-                }
-                (ParserState::ExpectObjectFirstKeyOrEnd, TokenType::RightBrace) => {
-                    self.depth -= 1;
-                    self.state_stack.pop();
-                    Ok(Some(ParserEvent::EndObject))
-                }
-                (ParserState::ExpectObjectFirstKeyOrEnd, _) => {
-                    Err(self.error_from_token("Expected '}' or a string key".to_string(), token))
-                }
+impl<'a> ParserEvent<'a> {
+    /// Converts the event into one that owns all of its data.
+    ///
+    /// This is useful when the event must outlive the input it was parsed
+    /// from, e.g. when the input is a reusable buffer.
+    pub fn into_owned(self) -> ParserEvent<'static> {
+        match self {
+            ParserEvent::StartObject => ParserEvent::StartObject,
+            ParserEvent::EndObject => ParserEvent::EndObject,
+            ParserEvent::StartArray => ParserEvent::StartArray,
+            ParserEvent::EndArray => ParserEvent::EndArray,
+            ParserEvent::Key(k) => ParserEvent::Key(Cow::Owned(k.into_owned())),
+            ParserEvent::String(s) => ParserEvent::String(Cow::Owned(s.into_owned())),
+            ParserEvent::WtfKey(k) => ParserEvent::WtfKey(k),
+            ParserEvent::WtfString(s) => ParserEvent::WtfString(s),
+            ParserEvent::Number(n) => ParserEvent::Number(n),
+            ParserEvent::Boolean(b) => ParserEvent::Boolean(b),
+            ParserEvent::Null => ParserEvent::Null,
+        }
+    }
+}
 
-                // --- Inside Object: after comma, expecting key (no '}' allowed) ---
-                (ParserState::ExpectObjectKey, TokenType::String(s)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectColon;
-                    Ok(Some(ParserEvent::Key(s.clone())))
-                }
-                // Check for invalid trailing comma `{"key":1,}`
-                (ParserState::ExpectObjectKey, TokenType::RightBrace) => Err(self
-                    .error_from_token("Unexpected '}', expected a string key".to_string(), token)),
-                (ParserState::ExpectObjectKey, _) => {
-                    Err(self.error_from_token("Expected a string key".to_string(), token))
-                }
+impl StateMachine {
+    /// Creates a state machine expecting a single root value.
+    pub(crate) fn new(options: &ParserOptions) -> Self {
+        StateMachine {
+            state_stack: vec![ParserState::ExpectValue],
+            options: options.clone(),
+            depth: 0,
+            frames: Vec::new(),
+            events: 0,
+        }
+    }
 
-                // --- Inside Object: after key, expecting ':' ---
-                (ParserState::ExpectObjectColon, TokenType::Colon) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectValue;
-                    Ok(None) // Colon is consumed, state changes, no event emitted.
-                }
-                (ParserState::ExpectObjectColon, _) => {
-                    Err(self.error_from_token("Expected ':'".to_string(), token))
-                }
+    /// A helper function to create a `ParseError` from a token's location.
+    fn error_from_token(&self, kind: ErrorKind, token: &Token<'_>) -> ParseError {
+        ParseError::new(kind, token.line, token.column, token.offset)
+    }
 
-                // --- Inside Object: after ':', expecting value ---
-                (ParserState::ExpectObjectValue, TokenType::LeftBracket) => {
-                    if self.depth >= self.max_depth {
-                        return Some(Err(self.error_from_token(
-                            "Maximum nesting depth exceeded".to_string(),
-                            token,
-                        )));
-                    }
-                    self.depth += 1;
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
-                    self.state_stack
-                        .push(ParserState::ExpectArrayFirstValueOrEnd);
-                    Ok(Some(ParserEvent::StartArray))
-                }
-                (ParserState::ExpectObjectValue, TokenType::LeftBrace) => {
-                    if self.depth >= self.max_depth {
-                        return Some(Err(self.error_from_token(
-                            "Maximum nesting depth exceeded".to_string(),
-                            token,
-                        )));
-                    }
-                    self.depth += 1;
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
-                    self.state_stack
-                        .push(ParserState::ExpectObjectFirstKeyOrEnd);
-                    Ok(Some(ParserEvent::StartObject))
-                }
-                (ParserState::ExpectObjectValue, TokenType::String(s)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
-                    Ok(Some(ParserEvent::String(s.clone())))
+    /// Advances the state machine by one token.
+    ///
+    /// Returns `Ok(Some(event))` if the token produced an event, or
+    /// `Ok(None)` for "non-event" tokens (like `,` or `:`) that only
+    /// advance the state.
+    pub(crate) fn handle_token<'a>(
+        &mut self,
+        token: &Token<'a>,
+    ) -> Result<Option<ParserEvent<'a>>, ParseError> {
+        self.handle(token, None)
+    }
+
+    /// Advances the state machine by one token of a value being skipped,
+    /// as returned by `Tokenizer::next_skipped`. The token's string is a
+    /// placeholder, so the limits are checked against `string_len`, the
+    /// length of the string it stands for.
+    pub(crate) fn handle_skipped(
+        &mut self,
+        token: &Token<'_>,
+        string_len: usize,
+    ) -> Result<(), ParseError> {
+        self.handle(token, Some(string_len)).map(|_| ())
+    }
+
+    /// Advances the state machine by one token. `string_len`, if given,
+    /// overrides the length of a string token for the limit checks.
+    fn handle<'a>(
+        &mut self,
+        token: &Token<'a>,
+        string_len: Option<usize>,
+    ) -> Result<Option<ParserEvent<'a>>, ParseError> {
+        // We have a token, but the state stack is empty (parser finished).
+        let state = match self.state_stack.last() {
+            Some(state) => state.clone(),
+            None => {
+                return Err(self.error_from_token(ErrorKind::TrailingToken, token));
+            }
+        };
+
+        let in_array = matches!(
+            state,
+            ParserState::ExpectArrayFirstValueOrEnd | ParserState::ExpectArrayValue
+        );
+
+        // A JSON5 identifier in value position may name a literal. Any
+        // other identifier is left for `transition` to reject.
+        let resolved;
+        let token = match &token.kind {
+            TokenType::Identifier(name) if state.expects_value() => {
+                let kind = match *name {
+                    "true" => TokenType::Boolean(true),
+                    "false" => TokenType::Boolean(false),
+                    "null" => TokenType::Null,
+                    "Infinity" => TokenType::Number(JsonNumber::F64(f64::INFINITY)),
+                    "NaN" => TokenType::Number(JsonNumber::F64(f64::NAN)),
+                    _ => token.kind.clone(),
+                };
+                resolved = Token {
+                    kind,
+                    ..token.clone()
+                };
+                &resolved
+            }
+            _ => token,
+        };
+
+        match self.transition(state, token) {
+            Ok(Some(event)) => {
+                self.check_limits(&event, in_array, token, string_len)
+                    .map_err(|e| self.add_context(e))?;
+                Ok(Some(event))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => Err(self.add_context(e)),
+        }
+    }
+
+    /// Returns `true` when parsing JSON5 rather than strict JSON.
+    fn json5(&self) -> bool {
+        self.options.dialect == Dialect::Json5
+    }
+
+    /// Returns `true` if `closer` (`]` or `}`) may come next, ending the
+    /// innermost container.
+    pub(crate) fn can_close(&self, closer: u8) -> bool {
+        match self.state_stack.last() {
+            Some(ParserState::ExpectArrayValue) if self.json5() => closer == b']',
+            Some(ParserState::ExpectObjectKey) if self.json5() => closer == b'}',
+            Some(ParserState::ExpectArrayFirstValueOrEnd | ParserState::ExpectArrayCommaOrEnd) => {
+                closer == b']'
+            }
+            Some(ParserState::ExpectObjectFirstKeyOrEnd | ParserState::ExpectObjectCommaOrEnd) => {
+                closer == b'}'
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the next token must be a value.
+    pub(crate) fn expects_value(&self) -> bool {
+        self.state_stack
+            .last()
+            .is_some_and(ParserState::expects_value)
+    }
+
+    /// Returns the current nesting depth (the number of open containers).
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the JSON Pointer (RFC 6901) of the most recent event.
+    ///
+    /// Each open object contributes its current key, and each open array
+    /// the index of its current member. Containers that have no member yet
+    /// contribute nothing, so right after `StartObject` the pointer still
+    /// refers to the object itself.
+    pub(crate) fn current_path(&self) -> String {
+        let mut path = String::new();
+        for frame in &self.frames {
+            if frame.members == 0 {
+                break;
+            }
+            if frame.object {
+                push_pointer_token(&mut path, &frame.key);
+            } else {
+                push_pointer_token(&mut path, &(frame.members - 1).to_string());
+            }
+        }
+        path
+    }
+
+    /// Labels an error with the opening bracket of the innermost open
+    /// array or object, so diagnostics can show where it started.
+    pub(crate) fn add_context(&self, error: ParseError) -> ParseError {
+        match self.frames.last() {
+            Some(frame) if error.label.is_none() && frame.offset != error.offset => {
+                let message = if frame.object {
+                    "object opened here"
+                } else {
+                    "array opened here"
+                };
+                error.with_label(Label {
+                    message: message.to_string(),
+                    line: frame.line,
+                    column: frame.column,
+                    offset: frame.offset,
+                })
+            }
+            _ => error,
+        }
+    }
+
+    /// Enforces the configured size limits on a freshly produced event,
+    /// and keeps the stack of open containers up to date.
+    ///
+    /// `in_array` tells whether the event was produced directly inside an
+    /// array, in which case it counts as one of that array's members.
+    /// `string_len` overrides the length of a `String` or `Key` event.
+    fn check_limits(
+        &mut self,
+        event: &ParserEvent<'_>,
+        in_array: bool,
+        token: &Token<'_>,
+        string_len: Option<usize>,
+    ) -> Result<(), ParseError> {
+        self.events += 1;
+        if self.events > self.options.max_events {
+            return Err(self.error_from_token(ErrorKind::TooManyEvents, token));
+        }
+
+        let max_string_length = self.options.max_string_length;
+        match event {
+            ParserEvent::String(s) if string_len.unwrap_or(s.len()) > max_string_length => {
+                return Err(self.error_from_token(ErrorKind::StringTooLong, token));
+            }
+            ParserEvent::WtfString(s) if string_len.unwrap_or(s.len()) > max_string_length => {
+                return Err(self.error_from_token(ErrorKind::StringTooLong, token));
+            }
+            ParserEvent::Key(key) => {
+                self.start_member(key, string_len.unwrap_or(key.len()), token)?;
+            }
+            // The pointer can only show a lone surrogate as U+FFFD, which
+            // takes as many bytes.
+            ParserEvent::WtfKey(key) => {
+                let len = string_len.unwrap_or(key.len());
+                self.start_member(&key.to_string_lossy(), len, token)?;
+            }
+            ParserEvent::EndArray | ParserEvent::EndObject => {
+                self.frames.pop();
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        // Any other event produced inside an array starts a new array member.
+        if in_array {
+            if let Some(frame) = self.frames.last_mut() {
+                frame.members += 1;
+                if frame.members > self.options.max_array_members {
+                    return Err(self.error_from_token(ErrorKind::TooManyArrayMembers, token));
                 }
-                (ParserState::ExpectObjectValue, TokenType::Number(n)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
-                    Ok(Some(ParserEvent::Number(*n)))
+            }
+        }
+
+        if matches!(event, ParserEvent::StartArray | ParserEvent::StartObject) {
+            self.frames.push(Frame {
+                object: matches!(event, ParserEvent::StartObject),
+                members: 0,
+                key: String::new(),
+                line: token.line,
+                column: token.column,
+                offset: token.offset,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks a key of `len` bytes, and starts a new member of the
+    /// innermost object.
+    fn start_member(&mut self, key: &str, len: usize, token: &Token<'_>) -> Result<(), ParseError> {
+        if len > self.options.max_key_length {
+            return Err(self.error_from_token(ErrorKind::KeyTooLong, token));
+        }
+        if let Some(frame) = self.frames.last_mut() {
+            frame.key.clear();
+            frame.key.push_str(key);
+            frame.members += 1;
+            if frame.members > self.options.max_object_members {
+                return Err(self.error_from_token(ErrorKind::TooManyObjectMembers, token));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the grammar rules for a single token in the given state.
+    fn transition<'a>(
+        &mut self,
+        state: ParserState,
+        token: &Token<'a>,
+    ) -> Result<Option<ParserEvent<'a>>, ParseError> {
+        // This is the main state machine logic.
+        match (state, &token.kind) {
+            // --- Root level or nested value expected ---
+            (ParserState::ExpectValue, TokenType::LeftBracket) => {
+                if self.depth >= self.options.max_depth {
+                    return Err(self.error_from_token(ErrorKind::DepthLimitExceeded, token));
                 }
-                (ParserState::ExpectObjectValue, TokenType::Boolean(b)) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
-                    Ok(Some(ParserEvent::Boolean(*b)))
+                self.depth += 1;
+                self.state_stack.pop();
+                self.state_stack
+                    .push(ParserState::ExpectArrayFirstValueOrEnd);
+                Ok(Some(ParserEvent::StartArray))
+            }
+            (ParserState::ExpectValue, TokenType::LeftBrace) => {
+                if self.depth >= self.options.max_depth {
+                    return Err(self.error_from_token(ErrorKind::DepthLimitExceeded, token));
                 }
-                (ParserState::ExpectObjectValue, TokenType::Null) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
-                    Ok(Some(ParserEvent::Null))
+                self.depth += 1;
+                self.state_stack.pop();
+                self.state_stack
+                    .push(ParserState::ExpectObjectFirstKeyOrEnd);
+                Ok(Some(ParserEvent::StartObject))
+            }
+            (ParserState::ExpectValue, TokenType::String(_) | TokenType::WtfString(_)) => {
+                self.state_stack.pop();
+                Ok(Some(string_event(&token.kind, false)))
+            }
+            (ParserState::ExpectValue, TokenType::Number(n)) => {
+                self.state_stack.pop();
+                Ok(Some(ParserEvent::Number(n.clone())))
+            }
+            (ParserState::ExpectValue, TokenType::Boolean(b)) => {
+                self.state_stack.pop();
+                Ok(Some(ParserEvent::Boolean(*b)))
+            }
+            (ParserState::ExpectValue, TokenType::Null) => {
+                self.state_stack.pop();
+                Ok(Some(ParserEvent::Null))
+            }
+            (ParserState::ExpectValue, _) => {
+                Err(self.error_from_token(ErrorKind::ExpectedValue, token))
+            }
+
+            // --- Inside Array: expecting first value or ']' (empty array) ---
+            (ParserState::ExpectArrayFirstValueOrEnd, TokenType::RightBracket) => {
+                self.depth -= 1;
+                self.state_stack.pop();
+                Ok(Some(ParserEvent::EndArray))
+            }
+            (ParserState::ExpectArrayFirstValueOrEnd, TokenType::LeftBracket) => {
+                if self.depth >= self.options.max_depth {
+                    return Err(self.error_from_token(ErrorKind::DepthLimitExceeded, token));
                 }
-                (ParserState::ExpectObjectValue, _) => {
-                    Err(self.error_from_token("Expected a value".to_string(), token))
+                self.depth += 1;
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                self.state_stack
+                    .push(ParserState::ExpectArrayFirstValueOrEnd);
+                Ok(Some(ParserEvent::StartArray))
+            }
+            (ParserState::ExpectArrayFirstValueOrEnd, TokenType::LeftBrace) => {
+                if self.depth >= self.options.max_depth {
+                    return Err(self.error_from_token(ErrorKind::DepthLimitExceeded, token));
                 }
+                self.depth += 1;
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                self.state_stack
+                    .push(ParserState::ExpectObjectFirstKeyOrEnd);
+                Ok(Some(ParserEvent::StartObject))
+            }
+            (
+                ParserState::ExpectArrayFirstValueOrEnd,
+                TokenType::String(_) | TokenType::WtfString(_),
+            ) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                Ok(Some(string_event(&token.kind, false)))
+            }
+            (ParserState::ExpectArrayFirstValueOrEnd, TokenType::Number(n)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                Ok(Some(ParserEvent::Number(n.clone())))
+            }
+            (ParserState::ExpectArrayFirstValueOrEnd, TokenType::Boolean(b)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                Ok(Some(ParserEvent::Boolean(*b)))
+            }
+            (ParserState::ExpectArrayFirstValueOrEnd, TokenType::Null) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                Ok(Some(ParserEvent::Null))
+            }
+            (ParserState::ExpectArrayFirstValueOrEnd, _) => {
+                Err(self.error_from_token(ErrorKind::ExpectedValueOrArrayEnd, token))
+            }
 
-                // --- Inside Object: after value, expecting ',' or '}' ---
-                (ParserState::ExpectObjectCommaOrEnd, TokenType::Comma) => {
-                    *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectKey;
-                    Ok(None) // Comma consumed, state changes, no event.
+            // --- Inside Array: after comma, expecting value (no ']' allowed) ---
+            (ParserState::ExpectArrayValue, TokenType::LeftBracket) => {
+                if self.depth >= self.options.max_depth {
+                    return Err(self.error_from_token(ErrorKind::DepthLimitExceeded, token));
                 }
-                (ParserState::ExpectObjectCommaOrEnd, TokenType::RightBrace) => {
-                    self.depth -= 1;
-                    self.state_stack.pop();
-                    Ok(Some(ParserEvent::EndObject))
-                }
-                (ParserState::ExpectObjectCommaOrEnd, _) => {
-                    Err(self.error_from_token("Expected ',' or '}'".to_string(), token))
+                self.depth += 1;
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                self.state_stack
+                    .push(ParserState::ExpectArrayFirstValueOrEnd);
+                Ok(Some(ParserEvent::StartArray))
+            }
+            (ParserState::ExpectArrayValue, TokenType::LeftBrace) => {
+                if self.depth >= self.options.max_depth {
+                    return Err(self.error_from_token(ErrorKind::DepthLimitExceeded, token));
                 }
-            };
+                self.depth += 1;
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                self.state_stack
+                    .push(ParserState::ExpectObjectFirstKeyOrEnd);
+                Ok(Some(ParserEvent::StartObject))
+            }
+            (ParserState::ExpectArrayValue, TokenType::String(_) | TokenType::WtfString(_)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                Ok(Some(string_event(&token.kind, false)))
+            }
+            (ParserState::ExpectArrayValue, TokenType::Number(n)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                Ok(Some(ParserEvent::Number(n.clone())))
+            }
+            (ParserState::ExpectArrayValue, TokenType::Boolean(b)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                Ok(Some(ParserEvent::Boolean(*b)))
+            }
+            (ParserState::ExpectArrayValue, TokenType::Null) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayCommaOrEnd;
+                Ok(Some(ParserEvent::Null))
+            }
+            // JSON5 allows a trailing comma `[1,]`
+            (ParserState::ExpectArrayValue, TokenType::RightBracket) if self.json5() => {
+                self.depth -= 1;
+                self.state_stack.pop();
+                Ok(Some(ParserEvent::EndArray))
+            }
+            // Check for invalid trailing comma `[1,,2]`
+            (ParserState::ExpectArrayValue, TokenType::RightBracket) => {
+                Err(self.error_from_token(ErrorKind::TrailingCommaInArray, token))
+            }
+            (ParserState::ExpectArrayValue, _) => {
+                Err(self.error_from_token(ErrorKind::ExpectedValue, token))
+            }
 
-            // Handle the result of the `match` expression
-            match result {
-                Ok(Some(event)) => {
-                    // We have an event to emit. Return it.
-                    return Some(Ok(event));
-                }
-                Ok(None) => {
-                    // This was a non-event token (like `,` or `:`).
-                    // We loop again to get the *next* token.
-                    current_token = match self.tokenizer.next() {
-                        Some(Ok(token)) => Some(token),
-                        Some(Err(e)) => return Some(Err(e)),
-                        None => None,
-                    };
-                    continue;
+            // --- Inside Array: after a value, expecting ',' or ']' ---
+            (ParserState::ExpectArrayCommaOrEnd, TokenType::Comma) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectArrayValue;
+                Ok(None) // Comma is consumed, state changes, but no event emitted.
+            }
+            (ParserState::ExpectArrayCommaOrEnd, TokenType::RightBracket) => {
+                self.depth -= 1;
+                self.state_stack.pop();
+                Ok(Some(ParserEvent::EndArray))
+            }
+            (ParserState::ExpectArrayCommaOrEnd, _) => {
+                Err(self.error_from_token(ErrorKind::ExpectedCommaOrArrayEnd, token))
+            }
+
+            // --- Inside Object: expecting first key or '}' (empty object) ---
+            (
+                ParserState::ExpectObjectFirstKeyOrEnd,
+                TokenType::String(_) | TokenType::WtfString(_),
+            ) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectColon;
+                Ok(Some(string_event(&token.kind, true)))
+            }
+            (ParserState::ExpectObjectFirstKeyOrEnd, TokenType::Identifier(name)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectColon;
+                Ok(Some(ParserEvent::Key(Cow::Borrowed(name))))
+            }
+            (ParserState::ExpectObjectFirstKeyOrEnd, TokenType::RightBrace) => {
+                self.depth -= 1;
+                self.state_stack.pop();
+                Ok(Some(ParserEvent::EndObject))
+            }
+            (ParserState::ExpectObjectFirstKeyOrEnd, _) => {
+                Err(self.error_from_token(ErrorKind::ExpectedKeyOrObjectEnd, token))
+            }
+
+            // --- Inside Object: after comma, expecting key (no '}' allowed) ---
+            (ParserState::ExpectObjectKey, TokenType::String(_) | TokenType::WtfString(_)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectColon;
+                Ok(Some(string_event(&token.kind, true)))
+            }
+            (ParserState::ExpectObjectKey, TokenType::Identifier(name)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectColon;
+                Ok(Some(ParserEvent::Key(Cow::Borrowed(name))))
+            }
+            // JSON5 allows a trailing comma `{"key":1,}`
+            (ParserState::ExpectObjectKey, TokenType::RightBrace) if self.json5() => {
+                self.depth -= 1;
+                self.state_stack.pop();
+                Ok(Some(ParserEvent::EndObject))
+            }
+            // Check for invalid trailing comma `{"key":1,}`
+            (ParserState::ExpectObjectKey, TokenType::RightBrace) => {
+                Err(self.error_from_token(ErrorKind::TrailingCommaInObject, token))
+            }
+            (ParserState::ExpectObjectKey, _) => {
+                Err(self.error_from_token(ErrorKind::ExpectedKey, token))
+            }
+
+            // --- Inside Object: after key, expecting ':' ---
+            (ParserState::ExpectObjectColon, TokenType::Colon) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectValue;
+                Ok(None) // Colon is consumed, state changes, no event emitted.
+            }
+            (ParserState::ExpectObjectColon, _) => {
+                Err(self.error_from_token(ErrorKind::ExpectedColon, token))
+            }
+
+            // --- Inside Object: after ':', expecting value ---
+            (ParserState::ExpectObjectValue, TokenType::LeftBracket) => {
+                if self.depth >= self.options.max_depth {
+                    return Err(self.error_from_token(ErrorKind::DepthLimitExceeded, token));
                 }
-                Err(e) => {
-                    // A parsing error occurred.
-                    return Some(Err(e));
+                self.depth += 1;
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
+                self.state_stack
+                    .push(ParserState::ExpectArrayFirstValueOrEnd);
+                Ok(Some(ParserEvent::StartArray))
+            }
+            (ParserState::ExpectObjectValue, TokenType::LeftBrace) => {
+                if self.depth >= self.options.max_depth {
+                    return Err(self.error_from_token(ErrorKind::DepthLimitExceeded, token));
                 }
+                self.depth += 1;
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
+                self.state_stack
+                    .push(ParserState::ExpectObjectFirstKeyOrEnd);
+                Ok(Some(ParserEvent::StartObject))
+            }
+            (ParserState::ExpectObjectValue, TokenType::String(_) | TokenType::WtfString(_)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
+                Ok(Some(string_event(&token.kind, false)))
+            }
+            (ParserState::ExpectObjectValue, TokenType::Number(n)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
+                Ok(Some(ParserEvent::Number(n.clone())))
             }
+            (ParserState::ExpectObjectValue, TokenType::Boolean(b)) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
+                Ok(Some(ParserEvent::Boolean(*b)))
+            }
+            (ParserState::ExpectObjectValue, TokenType::Null) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectCommaOrEnd;
+                Ok(Some(ParserEvent::Null))
+            }
+            (ParserState::ExpectObjectValue, _) => {
+                Err(self.error_from_token(ErrorKind::ExpectedValue, token))
+            }
+
+            // --- Inside Object: after value, expecting ',' or '}' ---
+            (ParserState::ExpectObjectCommaOrEnd, TokenType::Comma) => {
+                *self.state_stack.last_mut().unwrap() = ParserState::ExpectObjectKey;
+                Ok(None) // Comma consumed, state changes, no event.
+            }
+            (ParserState::ExpectObjectCommaOrEnd, TokenType::RightBrace) => {
+                self.depth -= 1;
+                self.state_stack.pop();
+                Ok(Some(ParserEvent::EndObject))
+            }
+            (ParserState::ExpectObjectCommaOrEnd, _) => {
+                Err(self.error_from_token(ErrorKind::ExpectedCommaOrObjectEnd, token))
+            }
+        }
+    }
+
+    /// Checks that the input may legally end in the current state.
+    ///
+    /// A clean end is either a finished document or a completely empty
+    /// input; anything else means a structure was left open, and the
+    /// error points at the innermost unclosed bracket.
+    pub(crate) fn end_of_input(&self) -> Result<(), ParseError> {
+        let state = match self.state_stack.last() {
+            None => return Ok(()), // Clean end
+            Some(state) => state,
+        };
+
+        // We expected a root value but got a clean EOF. Valid for empty input.
+        if *state == ParserState::ExpectValue && self.state_stack.len() == 1 {
+            return Ok(());
         }
+
+        // Return a more specific error based on the parser's state
+        let kind = match state {
+            ParserState::ExpectObjectCommaOrEnd
+            | ParserState::ExpectObjectFirstKeyOrEnd
+            | ParserState::ExpectObjectKey
+            | ParserState::ExpectObjectColon
+            | ParserState::ExpectObjectValue => ErrorKind::UnclosedObject,
+            ParserState::ExpectArrayCommaOrEnd
+            | ParserState::ExpectArrayFirstValueOrEnd
+            | ParserState::ExpectArrayValue => ErrorKind::UnclosedArray,
+            _ => ErrorKind::UnexpectedEndOfInput,
+        };
+
+        Err(match self.frames.last() {
+            Some(frame) => ParseError::new(kind, frame.line, frame.column, frame.offset),
+            None => ParseError::new(kind, 1, 1, 0),
+        })
+    }
+}
+
+/// The main implementation of the parser's `Iterator` trait.
+/// The grammar itself lives in `StateMachine`.
+impl<'a> Iterator for StreamingParser<'a> {
+    type Item = Result<ParserEvent<'a>, ParseError>;
+
+    /// Consumes the next token and advances the parser's state.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_spanned()
+            .map(|result| result.map(|spanned| spanned.event))
     }
 }
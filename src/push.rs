@@ -0,0 +1,566 @@
+//! Contains the incremental, chunk-fed `PushParser`.
+//!
+//! The `StreamingParser` needs the whole document as one `&str`. The
+//! `PushParser` instead accepts input in arbitrary chunks (e.g. straight
+//! from a socket or a pipe) and resumes its state across chunk
+//! boundaries, even when a string, escape, number or literal is split
+//! between two chunks.
+
+use crate::error::{ErrorKind, ParseError};
+use crate::options::{Dialect, LoneSurrogatePolicy, ParserOptions};
+use crate::parser::{ParserEvent, StateMachine};
+use crate::tokenizer::Tokenizer;
+use memchr::{memchr, memmem};
+
+/// An incremental JSON parser that is fed input in chunks.
+///
+/// Feed bytes with `feed`, pull events with `next_event` until it returns
+/// `None` (meaning "need more input"), and call `finish` once the input is
+/// exhausted. It yields exactly the same `ParserEvent`s as the slice-based
+/// `StreamingParser`, but every event owns its data, since the parser's
+/// internal buffer is reused between chunks.
+///
+/// Only the bytes of the token currently being lexed are buffered, so
+/// memory use is proportional to the largest single token rather than to
+/// the size of the document.
+///
+/// # Examples
+/// ```
+/// use rill_json::{JsonNumber, ParserEvent, PushParser};
+///
+/// let mut parser = PushParser::default();
+/// let mut events = Vec::new();
+///
+/// // The number `123` is split across two chunks.
+/// for chunk in [&b"[12"[..], &b"3, \"hi\"]"[..]] {
+///     parser.feed(chunk).unwrap();
+///     while let Some(event) = parser.next_event() {
+///         events.push(event.unwrap());
+///     }
+/// }
+/// parser.finish();
+/// while let Some(event) = parser.next_event() {
+///     events.push(event.unwrap());
+/// }
+///
+/// assert_eq!(
+///     events,
+///     vec![
+///         ParserEvent::StartArray,
+///         ParserEvent::Number(JsonNumber::I64(123)),
+///         ParserEvent::String("hi".into()),
+///         ParserEvent::EndArray,
+///     ]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct PushParser {
+    /// Input that has been fed but not yet turned into tokens.
+    buffer: Vec<u8>,
+    /// The number of bytes at the front of `buffer` already consumed.
+    cursor: usize,
+    /// The byte offset (within the whole input) of the first unconsumed byte.
+    offset: usize,
+    /// The line (1-indexed) of the first unconsumed byte.
+    line: usize,
+    /// The column (1-indexed) of the first unconsumed byte.
+    column: usize,
+    /// How far `scan` got into the partial token at `cursor` without
+    /// finding its end, so that the next call does not rescan it.
+    scanned: usize,
+    /// The grammar state machine, shared with `StreamingParser`.
+    machine: StateMachine,
+    /// The total number of bytes fed so far.
+    fed: usize,
+    /// The maximum total number of bytes that may be fed.
+    max_input_size: usize,
+    /// The accepted JSON dialect.
+    dialect: Dialect,
+    /// How lone surrogates in `\u` escapes are decoded.
+    lone_surrogates: LoneSurrogatePolicy,
+    /// Whether numbers are kept as their source text.
+    lossless_numbers: bool,
+    /// Set by `finish`: no more input will arrive.
+    finished: bool,
+    /// Set once the document is complete or an error was reported.
+    done: bool,
+}
+
+impl PushParser {
+    /// Creates a new `PushParser` with the given maximum nesting depth.
+    ///
+    /// The total input size is not limited; see `with_options`.
+    pub fn new(max_depth: usize) -> Self {
+        Self::with_options(
+            &ParserOptions::new()
+                .max_depth(max_depth)
+                .max_input_size(usize::MAX),
+        )
+    }
+
+    /// Creates a new `PushParser` that enforces the given limits.
+    ///
+    /// The input size limit applies to the total number of bytes fed.
+    pub fn with_options(options: &ParserOptions) -> Self {
+        PushParser {
+            buffer: Vec::new(),
+            cursor: 0,
+            offset: 0,
+            line: 1,
+            column: 1,
+            scanned: 0,
+            machine: StateMachine::new(options),
+            fed: 0,
+            max_input_size: options.max_input_size,
+            dialect: options.dialect,
+            lone_surrogates: options.lone_surrogates,
+            lossless_numbers: options.lossless_numbers,
+            finished: false,
+            done: false,
+        }
+    }
+
+    /// Appends a chunk of input.
+    ///
+    /// Chunks may be split anywhere, including in the middle of a
+    /// multi-byte UTF-8 sequence.
+    ///
+    /// # Errors
+    /// Fails with `ErrorKind::SizeLimitExceeded`, without buffering the
+    /// chunk, if it would take the total input past the maximum size. The
+    /// parser is then done: `next_event` returns `None`.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), ParseError> {
+        let fed = self.fed.saturating_add(chunk.len());
+        if fed > self.max_input_size {
+            self.done = true;
+            return Err(ParseError::new(
+                ErrorKind::SizeLimitExceeded,
+                self.line,
+                self.column,
+                self.offset,
+            ));
+        }
+        self.fed = fed;
+        self.buffer.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    /// Signals that no more input will be fed.
+    ///
+    /// After this, `next_event` lexes any trailing token and reports
+    /// unclosed structures as errors.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Returns `true` once the document has been fully parsed, or an
+    /// error has been reported.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Returns the next event, if enough input has been fed to produce one.
+    ///
+    /// `None` means that more input is needed (before `finish`), or that
+    /// parsing is over (after `finish`, or once an error has been returned).
+    pub fn next_event(&mut self) -> Option<Result<ParserEvent<'static>, ParseError>> {
+        if self.done {
+            return None;
+        }
+
+        // Loop handles "non-event" tokens (like `,` or `:`).
+        loop {
+            if !self.finished {
+                let pending = &self.buffer[self.cursor..];
+                match scan(pending, self.dialect == Dialect::Json5, &mut self.scanned) {
+                    Scan::Token => {}
+                    Scan::Skip(len) => {
+                        self.skip(len);
+                        continue;
+                    }
+                    Scan::Incomplete => {
+                        self.compact();
+                        return None;
+                    }
+                }
+            }
+
+            let pending = &self.buffer[self.cursor..];
+            let mut tokenizer = Tokenizer::resume(pending, self.offset, self.line, self.column)
+                .with_dialect(self.dialect)
+                .with_lone_surrogates(self.lone_surrogates)
+                .with_lossless_numbers(self.lossless_numbers);
+            let token = tokenizer.next();
+            let (consumed, line, column) = tokenizer.position();
+            self.cursor += consumed;
+            self.offset += consumed;
+            self.line = line;
+            self.column = column;
+            self.scanned = 0;
+
+            let result = match token {
+                Some(Ok(token)) => match self.machine.handle_token(&token) {
+                    Ok(Some(event)) => Ok(event.into_owned()),
+                    Ok(None) => continue,
+                    Err(e) => Err(e),
+                },
+                Some(Err(e)) => Err(self.machine.add_context(e)),
+                // Only reachable after `finish`: the input is exhausted.
+                None => match self.machine.end_of_input() {
+                    Ok(()) => {
+                        self.done = true;
+                        return None;
+                    }
+                    Err(e) => Err(e),
+                },
+            };
+
+            if result.is_err() {
+                self.done = true;
+            }
+            return Some(result);
+        }
+    }
+
+    /// Returns the line, column and byte offset of the first unconsumed byte.
+    pub(crate) fn location(&self) -> (usize, usize, usize) {
+        (self.line, self.column, self.offset)
+    }
+
+    /// Consumes `len` bytes of whitespace and complete comments.
+    fn skip(&mut self, len: usize) {
+        let whitespace = &self.buffer[self.cursor..self.cursor + len];
+        let mut tokenizer = Tokenizer::resume(whitespace, self.offset, self.line, self.column)
+            .with_dialect(self.dialect);
+        tokenizer.peek_byte();
+        let (consumed, line, column) = tokenizer.position();
+        debug_assert_eq!(consumed, len);
+        self.cursor += len;
+        self.offset += len;
+        self.line = line;
+        self.column = column;
+        self.scanned = 0;
+    }
+
+    /// Drops the consumed prefix of the buffer, so that only the bytes of
+    /// a partial token are kept between chunks.
+    fn compact(&mut self) {
+        if self.cursor > 0 {
+            self.buffer.drain(..self.cursor);
+            self.cursor = 0;
+        }
+    }
+}
+
+impl Default for PushParser {
+    /// Creates a `PushParser` with the default maximum nesting depth.
+    fn default() -> Self {
+        PushParser::new(crate::DEFAULT_MAX_DEPTH)
+    }
+}
+
+/// What `scan` found at the front of the unconsumed input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scan {
+    /// This many bytes of whitespace or complete comments.
+    Skip(usize),
+    /// A token that can be lexed without seeing any more input.
+    Token,
+    /// The start of a token or comment whose end has not arrived yet.
+    Incomplete,
+}
+
+/// Classifies the front of `bytes`, the unconsumed input.
+///
+/// Strings are complete once their closing quote has arrived. Numbers,
+/// literals and (in JSON5) identifiers are only complete once a byte that
+/// cannot continue them has arrived, since `12` may still turn into `123`
+/// (and `tru` into `true`). In JSON5, comments count as incomplete until
+/// they are closed (by a newline for `//`), and any non-ASCII byte is
+/// treated as part of an identifier, so a token followed only by
+/// non-ASCII whitespace waits for more input.
+///
+/// `scanned` is where the previous call stopped looking for the end of the
+/// same partial token; it is updated when `Incomplete` is returned, so
+/// that feeding a long token in many chunks takes linear time overall.
+fn scan(bytes: &[u8], json5: bool, scanned: &mut usize) -> Scan {
+    let is_space = |b: &u8| match b {
+        b' ' | b'\n' | b'\r' | b'\t' => true,
+        b'\x0B' | b'\x0C' => json5,
+        _ => false,
+    };
+    let first = match bytes.first() {
+        Some(first) => *first,
+        None => return Scan::Incomplete,
+    };
+    if is_space(&first) {
+        return Scan::Skip(bytes.iter().take_while(|b| is_space(b)).count());
+    }
+
+    let from = *scanned;
+    let end = match first {
+        b'/' if json5 => match bytes.get(1) {
+            Some(b'/') => {
+                let from = from.max(2);
+                memchr(b'\n', &bytes[from..]).map(|end| Scan::Skip(from + end + 1))
+            }
+            Some(b'*') => {
+                // A `*` at the end of a chunk may start the closing `*/`.
+                let from = from.saturating_sub(1).max(2);
+                memmem::find(&bytes[from..], b"*/").map(|end| Scan::Skip(from + end + 2))
+            }
+            Some(_) => Some(Scan::Token),
+            // A lone `/` may still turn into a comment.
+            None => None,
+        },
+        b'"' => scan_string(bytes, b'"', scanned),
+        b'\'' if json5 => scan_string(bytes, b'\'', scanned),
+        b'-' | b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' if !json5 => bytes[from..]
+            .iter()
+            .any(|b| !(b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.')))
+            .then_some(Scan::Token),
+        b'[' | b']' | b'{' | b'}' | b':' | b',' if json5 => Some(Scan::Token),
+        _ if json5 => bytes[from..]
+            .iter()
+            .any(|&b| {
+                !(b.is_ascii_alphanumeric()
+                    || b >= 0x80
+                    || matches!(b, b'-' | b'+' | b'.' | b'_' | b'$'))
+            })
+            .then_some(Scan::Token),
+        // Structural and invalid bytes are single-byte "tokens".
+        _ => Some(Scan::Token),
+    };
+    end.unwrap_or_else(|| {
+        *scanned = (*scanned).max(bytes.len());
+        Scan::Incomplete
+    })
+}
+
+/// Looks for the closing `quote` of the string at the front of `bytes`,
+/// resuming at `scanned`.
+fn scan_string(bytes: &[u8], quote: u8, scanned: &mut usize) -> Option<Scan> {
+    let mut i = (*scanned).max(1);
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2, // Skip the escaped byte, whatever it is.
+            b if b == quote => return Some(Scan::Token),
+            _ => i += 1,
+        }
+    }
+    // `i` may point past an escaped byte that has not arrived yet.
+    *scanned = i;
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_streaming, JsonNumber};
+
+    // Feeds `input` in chunks of `chunk_size` bytes and collects every result.
+    fn push_events(
+        input: &str,
+        chunk_size: usize,
+    ) -> Result<Vec<ParserEvent<'static>>, ParseError> {
+        let mut parser = PushParser::default();
+        let mut events = Vec::new();
+        for chunk in input.as_bytes().chunks(chunk_size) {
+            parser.feed(chunk)?;
+            while let Some(event) = parser.next_event() {
+                events.push(event?);
+            }
+        }
+        parser.finish();
+        while let Some(event) = parser.next_event() {
+            events.push(event?);
+        }
+        Ok(events)
+    }
+
+    fn slice_events(input: &str) -> Result<Vec<ParserEvent<'static>>, ParseError> {
+        parse_streaming(input)?
+            .map(|event| event.map(ParserEvent::into_owned))
+            .collect()
+    }
+
+    #[test]
+    fn test_push_matches_slice_parser_at_every_chunk_size() {
+        let inputs = [
+            r#"{ "key": [1, null, true, "hello"] }"#,
+            r#"[{"a": -12.5e3, "b": [false, {"c": {}}]}, "esc\n\"aped\u1234"]"#,
+            r#"  "a top-level string"  "#,
+            "9223372036854775808",
+            "  123  ",
+            "null",
+            "",
+        ];
+        for input in inputs {
+            let expected = slice_events(input).unwrap();
+            for chunk_size in 1..=input.len().max(1) {
+                assert_eq!(
+                    push_events(input, chunk_size).unwrap(),
+                    expected,
+                    "input {:?}, chunk size {}",
+                    input,
+                    chunk_size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_json5_at_every_chunk_size() {
+        let options = ParserOptions::new().dialect(Dialect::Json5);
+        let input =
+            "// head\n{a: 'x\\'y', /* c */ \"b\": [0x1F, .5, +1, -Infinity,], c\u{e9}: true,}//";
+        let expected: Vec<_> = crate::parse_streaming_with(input, &options)
+            .unwrap()
+            .map(|event| event.unwrap().into_owned())
+            .collect();
+        for chunk_size in 1..=input.len() {
+            let mut parser = PushParser::with_options(&options);
+            let mut events = Vec::new();
+            for chunk in input.as_bytes().chunks(chunk_size) {
+                parser.feed(chunk).unwrap();
+                while let Some(event) = parser.next_event() {
+                    events.push(event.unwrap());
+                }
+            }
+            parser.finish();
+            while let Some(event) = parser.next_event() {
+                events.push(event.unwrap());
+            }
+            assert_eq!(events, expected, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn test_push_tokens_split_across_chunks() {
+        // Split mid-number: `12` must not be reported before `34` arrives.
+        let mut parser = PushParser::default();
+        parser.feed(b"[12").unwrap();
+        assert_eq!(
+            parser.next_event().unwrap().unwrap(),
+            ParserEvent::StartArray
+        );
+        assert!(parser.next_event().is_none());
+        parser.feed(b"34]").unwrap();
+        assert_eq!(
+            parser.next_event().unwrap().unwrap(),
+            ParserEvent::Number(JsonNumber::I64(1234))
+        );
+        assert_eq!(parser.next_event().unwrap().unwrap(), ParserEvent::EndArray);
+        parser.finish();
+        assert!(parser.next_event().is_none());
+        assert!(parser.is_done());
+
+        // Split mid-UTF-8 sequence.
+        let mut parser = PushParser::default();
+        let input = "\"caf\u{e9}\"".as_bytes();
+        parser.feed(&input[..5]).unwrap(); // Up to the first byte of `é`
+        assert!(parser.next_event().is_none());
+        parser.feed(&input[5..]).unwrap();
+        assert_eq!(
+            parser.next_event().unwrap().unwrap(),
+            ParserEvent::String("caf\u{e9}".into())
+        );
+
+        // Split mid-escape.
+        let mut parser = PushParser::default();
+        parser.feed(br#"["a\"#).unwrap();
+        assert_eq!(
+            parser.next_event().unwrap().unwrap(),
+            ParserEvent::StartArray
+        );
+        assert!(parser.next_event().is_none());
+        parser.feed(br#""b\u00"#).unwrap();
+        assert!(parser.next_event().is_none());
+        parser.feed(br#"41"]"#).unwrap();
+        assert_eq!(
+            parser.next_event().unwrap().unwrap(),
+            ParserEvent::String("a\"bA".into())
+        );
+
+        // Split mid-literal.
+        let mut parser = PushParser::default();
+        parser.feed(b"tr").unwrap();
+        assert!(parser.next_event().is_none());
+        parser.feed(b"ue").unwrap();
+        parser.finish();
+        assert_eq!(
+            parser.next_event().unwrap().unwrap(),
+            ParserEvent::Boolean(true)
+        );
+        assert!(parser.next_event().is_none());
+    }
+
+    #[test]
+    fn test_push_buffers_only_the_partial_token() {
+        // Whitespace between tokens is dropped as it arrives.
+        let mut parser = PushParser::default();
+        for _ in 0..100 {
+            parser.feed(&[b' '; 99]).unwrap();
+            parser.feed(b"\n").unwrap();
+            assert!(parser.next_event().is_none());
+            assert!(parser.buffer.is_empty());
+        }
+        parser.feed(b" 1 ").unwrap();
+        assert_eq!(
+            parser.next_event().unwrap().unwrap(),
+            ParserEvent::Number(JsonNumber::I64(1))
+        );
+        assert_eq!(parser.location(), (101, 3, 10_002));
+
+        // A partial token is scanned once, not again with every chunk.
+        let mut parser = PushParser::default();
+        parser.feed(b"\"").unwrap();
+        for _ in 0..1000 {
+            parser.feed(b"\\\"").unwrap();
+            assert!(parser.next_event().is_none());
+            assert_eq!(parser.scanned, parser.buffer.len());
+        }
+        parser.feed(b"\"").unwrap();
+        assert_eq!(
+            parser.next_event().unwrap().unwrap(),
+            ParserEvent::String("\"".repeat(1000).into())
+        );
+
+        // The end of a JSON5 comment may be split after its `*`.
+        let options = ParserOptions::new().dialect(Dialect::Json5);
+        let mut parser = PushParser::with_options(&options);
+        parser.feed(b"/* x *").unwrap();
+        assert!(parser.next_event().is_none());
+        parser.feed(b"*/ 2 ").unwrap();
+        assert_eq!(
+            parser.next_event().unwrap().unwrap(),
+            ParserEvent::Number(JsonNumber::I64(2))
+        );
+    }
+
+    #[test]
+    fn test_push_errors() {
+        let err = push_events("[1, 2", 2).unwrap_err();
+        assert_eq!(err.message, "Unclosed array");
+
+        let err = push_events("[1 true]", 3).unwrap_err();
+        assert_eq!(err.message, "Expected ',' or ']'");
+        assert_eq!(err.line, 1);
+        assert_eq!(err.column, 4);
+
+        let err = push_events("{\"a\": 1}\n [2]", 1).unwrap_err();
+        assert_eq!(err.message, "Unexpected trailing token");
+        assert_eq!(err.line, 2);
+        assert_eq!(err.column, 2);
+
+        let err = push_events("\"unterminated", 4).unwrap_err();
+        assert_eq!(err.message, "Unterminated string");
+
+        // The parser stops after the first error.
+        let mut parser = PushParser::default();
+        parser.feed(b"[?, 1]").unwrap();
+        assert!(parser.next_event().unwrap().is_ok());
+        assert!(parser.next_event().unwrap().is_err());
+        assert!(parser.next_event().is_none());
+    }
+}
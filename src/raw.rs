@@ -0,0 +1,545 @@
+//! Contains `RawValue`, a JSON value kept as its unparsed source text, and
+//! `RawNumber`, its counterpart for a single number.
+//!
+//! Raw values let a sub-document pass through untouched: it is sliced out
+//! of the input by `StreamingParser::next_raw_value`, and written back out
+//! verbatim when embedded in a `JsonValue` with `JsonValue::Raw`.
+//! Raw numbers are produced in lossless number mode (see
+//! `ParserOptions::lossless_numbers`) and embedded with `JsonNumber::Raw`.
+
+use crate::error::{ErrorKind, ParseError};
+use crate::options::ParserOptions;
+use crate::parser::StreamingParser;
+use crate::tokenizer::is_json_number;
+use std::borrow::Cow;
+use std::fmt;
+
+/// The exact source text of a single JSON value.
+///
+/// The text is never re-formatted: whitespace, key order and escapes are
+/// kept exactly as they were in the input.
+///
+/// # Examples
+/// ```
+/// use rill_json::{JsonValue, RawValue};
+///
+/// let raw = RawValue::new(r#"{"b": 1,  "a": [true]}"#).unwrap();
+/// let value = JsonValue::Array(vec![JsonValue::Raw(raw.into_owned()), JsonValue::Null]);
+/// assert_eq!(value.stringify().unwrap(), r#"[{"b": 1,  "a": [true]},null]"#);
+///
+/// assert!(RawValue::new("[1, 2").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawValue<'a> {
+    /// The source text, without surrounding whitespace.
+    json: Cow<'a, str>,
+}
+
+impl<'a> RawValue<'a> {
+    /// Checks that `json` is a single, complete JSON value and wraps it.
+    ///
+    /// Leading and trailing whitespace is dropped.
+    pub fn new(json: &'a str) -> Result<Self, ParseError> {
+        let json = json.trim_matches(is_json_whitespace);
+        validate(json)?;
+        Ok(RawValue {
+            json: Cow::Borrowed(json),
+        })
+    }
+
+    /// Wraps text that the parser has already scanned as a single value.
+    pub(crate) fn from_source(json: &'a str) -> Self {
+        RawValue {
+            json: Cow::Borrowed(json),
+        }
+    }
+
+    /// Returns the source text of the value.
+    pub fn get(&self) -> &str {
+        &self.json
+    }
+
+    /// Converts the value into one that owns its text.
+    ///
+    /// This is needed to embed it in a `JsonValue`.
+    pub fn into_owned(self) -> RawValue<'static> {
+        RawValue {
+            json: Cow::Owned(self.json.into_owned()),
+        }
+    }
+}
+
+impl RawValue<'static> {
+    /// Checks that `json` is a single, complete JSON value and takes
+    /// ownership of it.
+    ///
+    /// Leading and trailing whitespace is dropped.
+    pub fn from_string(json: String) -> Result<Self, ParseError> {
+        let trimmed = json.trim_matches(is_json_whitespace);
+        validate(trimmed)?;
+        let json = if trimmed.len() == json.len() {
+            json
+        } else {
+            trimmed.to_string()
+        };
+        Ok(RawValue {
+            json: Cow::Owned(json),
+        })
+    }
+}
+
+impl fmt::Display for RawValue<'_> {
+    /// Writes the source text unchanged.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.json)
+    }
+}
+
+/// The exact source text of a JSON number, such as `-0`, `1.10` or
+/// `123456789012345678901234567890`.
+///
+/// Unlike `f64`, a raw number never loses precision or formatting: it is
+/// written back out byte for byte. The `to_*` conversions are checked, and
+/// return `None` when the number cannot be represented exactly.
+///
+/// # Examples
+/// ```
+/// use rill_json::RawNumber;
+///
+/// let price = RawNumber::new("19.90").unwrap();
+/// assert_eq!(price.get(), "19.90");
+/// assert_eq!(price.to_i64(), None);
+/// assert_eq!(price.to_f64(), Some(19.9));
+///
+/// let big = RawNumber::new("1.5e20").unwrap();
+/// assert_eq!(big.to_u64(), None);
+/// assert_eq!(big.to_u128(), Some(150_000_000_000_000_000_000));
+///
+/// assert!(RawNumber::new("01").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawNumber {
+    /// The source text, which follows the RFC 8259 number grammar.
+    text: String,
+}
+
+impl RawNumber {
+    /// Checks that `text` is a JSON number and wraps it.
+    ///
+    /// Only the strict RFC 8259 grammar is accepted: no surrounding
+    /// whitespace, leading `+`, leading zeros or hexadecimal.
+    pub fn new(text: &str) -> Result<Self, ParseError> {
+        if !is_json_number(text) {
+            return Err(ParseError::new(
+                ErrorKind::InvalidNumber(text.to_string()),
+                1,
+                1,
+                0,
+            ));
+        }
+        Ok(Self::from_source(text))
+    }
+
+    /// Wraps text that the tokenizer has already scanned as a number.
+    pub(crate) fn from_source(text: &str) -> Self {
+        RawNumber {
+            text: text.to_string(),
+        }
+    }
+
+    /// Returns the source text of the number.
+    pub fn get(&self) -> &str {
+        &self.text
+    }
+
+    /// Converts the number to an `i64`, if it is an integer in range.
+    ///
+    /// Fractions and exponents are allowed as long as the value is a whole
+    /// number, so `1.5e1` converts to `15`.
+    pub fn to_i64(&self) -> Option<i64> {
+        self.to_i128().and_then(|n| i64::try_from(n).ok())
+    }
+
+    /// Converts the number to a `u64`, if it is an integer in range.
+    pub fn to_u64(&self) -> Option<u64> {
+        self.to_u128().and_then(|n| u64::try_from(n).ok())
+    }
+
+    /// Converts the number to an `i128`, if it is an integer in range.
+    pub fn to_i128(&self) -> Option<i128> {
+        let (negative, magnitude) = self.integer()?;
+        if negative {
+            // `i128::MIN` has no positive counterpart.
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    /// Converts the number to a `u128`, if it is a non-negative integer
+    /// in range. `-0` converts to `0`.
+    pub fn to_u128(&self) -> Option<u128> {
+        match self.integer()? {
+            (true, magnitude) if magnitude != 0 => None,
+            (_, magnitude) => Some(magnitude),
+        }
+    }
+
+    /// Converts the number to the nearest `f64`.
+    ///
+    /// Returns `None` if the number is too large for an `f64`. Most
+    /// decimals, like `0.1`, have no exact `f64` representation and are
+    /// rounded.
+    pub fn to_f64(&self) -> Option<f64> {
+        // The JSON grammar is a subset of what `f64` parses.
+        self.text.parse::<f64>().ok().filter(|n| n.is_finite())
+    }
+
+    /// Returns `true` if `text`, a number in the JSON grammar or in Rust's
+    /// `f64` display form, has exactly this number's value. Only the
+    /// spelling may differ, as in `1.50` and `15e-1`.
+    #[cfg(feature = "serde_json")]
+    pub(crate) fn same_value(&self, text: &str) -> bool {
+        decompose(&self.text) == decompose(text)
+    }
+
+    /// Returns the sign and magnitude of the number, if it is an integer
+    /// whose magnitude fits in a `u128`.
+    fn integer(&self) -> Option<(bool, u128)> {
+        let (negative, significant, scale) = decompose(&self.text);
+        if significant.is_empty() {
+            return Some((negative, 0));
+        }
+        if scale < 0 {
+            return None; // A non-zero fractional part remains.
+        }
+        let mut magnitude: u128 = significant.parse().ok()?;
+        for _ in 0..scale {
+            magnitude = magnitude.checked_mul(10)?;
+        }
+        Some((negative, magnitude))
+    }
+}
+
+/// Splits a number into its sign, its significant digits and the power of
+/// ten that scales them, so that the value is `digits * 10^scale`. Leading
+/// and trailing zeros are dropped, so equal values decompose equally; zero
+/// has no digits.
+fn decompose(text: &str) -> (bool, String, i64) {
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, text),
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], &unsigned[i + 1..]),
+        None => (unsigned, "0"),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    // Trailing zeros only move the scale.
+    let digits = format!("{}{}", int, frac);
+    let digits = digits.trim_start_matches('0');
+    let significant = digits.trim_end_matches('0');
+    if significant.is_empty() {
+        return (negative, String::new(), 0);
+    }
+    let exponent: i64 = exponent.parse().unwrap_or(
+        // Too many digits: the sign alone decides between huge and tiny.
+        if exponent.starts_with('-') {
+            i64::MIN
+        } else {
+            i64::MAX
+        },
+    );
+    let scale = exponent
+        .saturating_sub(frac.len() as i64)
+        .saturating_add((digits.len() - significant.len()) as i64);
+    (negative, significant.to_string(), scale)
+}
+
+impl fmt::Display for RawNumber {
+    /// Writes the source text unchanged.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Returns `true` for the four whitespace characters allowed by RFC 8259.
+fn is_json_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\n' | '\r' | '\t')
+}
+
+/// Fully parses `json`, which must hold exactly one value.
+fn validate(json: &str) -> Result<(), ParseError> {
+    if json.is_empty() {
+        return Err(ParseError::new(ErrorKind::EmptyInput, 1, 1, 0));
+    }
+    // The caller already holds the whole text, so only the size limit is
+    // lifted; nesting stays limited, since the receiver may parse the
+    // fragment recursively.
+    let options = ParserOptions::new().max_input_size(usize::MAX);
+    for event in StreamingParser::with_options(json, &options) {
+        event?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_streaming, JsonNumber, JsonValue, ParserEvent};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_next_raw_value_slices_source() {
+        let input = "[ {\"a\" : [1,2]} ,\"x\\ty\", -1.5e3,\n  null ]";
+        let mut parser = parse_streaming(input).unwrap();
+        assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::StartArray);
+
+        let mut raws = Vec::new();
+        while let Some(raw) = parser.next_raw_value().unwrap() {
+            raws.push(raw);
+        }
+        let texts: Vec<&str> = raws.iter().map(RawValue::get).collect();
+        assert_eq!(texts, ["{\"a\" : [1,2]}", "\"x\\ty\"", "-1.5e3", "null"]);
+        assert!(matches!(raws[0].json, Cow::Borrowed(_)));
+
+        assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::EndArray);
+        assert!(parser.next_raw_value().unwrap().is_none());
+        assert!(parser.next().is_none());
+
+        // Malformed input inside the value is still reported.
+        let mut parser = parse_streaming(r#"{"a": [1, "b}"#).unwrap();
+        parser.next().unwrap().unwrap();
+        parser.next().unwrap().unwrap();
+        let err = parser.next_raw_value().unwrap_err();
+        assert_eq!(err.message, "Unterminated string");
+
+        // So are misplaced or missing separators, and lone surrogates.
+        let cases = [
+            ("[1 2]", ErrorKind::ExpectedCommaOrArrayEnd),
+            (r#"{"x" "y"}"#, ErrorKind::ExpectedColon),
+            ("[,,,]", ErrorKind::ExpectedValueOrArrayEnd),
+            ("{1:2}", ErrorKind::ExpectedKeyOrObjectEnd),
+            (r#"["\uD800"]"#, ErrorKind::LoneSurrogate),
+        ];
+        for (value, kind) in cases {
+            let input = format!("[{}]", value);
+            let mut parser = parse_streaming(&input).unwrap();
+            parser.next().unwrap().unwrap();
+            let err = parser.next_raw_value().unwrap_err();
+            assert_eq!(err.kind, kind, "input {:?}", input);
+        }
+
+        // The parser's limits apply inside the value too.
+        let cases = [
+            (
+                ParserOptions::new().max_string_length(3),
+                r#"["abcd"]"#,
+                ErrorKind::StringTooLong,
+            ),
+            (
+                ParserOptions::new().max_key_length(3),
+                r#"{"abcd": 1}"#,
+                ErrorKind::KeyTooLong,
+            ),
+            (
+                ParserOptions::new().max_array_members(2),
+                "[1, 2, 3]",
+                ErrorKind::TooManyArrayMembers,
+            ),
+            (
+                ParserOptions::new().max_object_members(1),
+                r#"{"x": 1, "y": 2}"#,
+                ErrorKind::TooManyObjectMembers,
+            ),
+            (
+                ParserOptions::new().max_events(4),
+                "[1, 2, 3]",
+                ErrorKind::TooManyEvents,
+            ),
+        ];
+        for (options, value, kind) in cases {
+            let input = format!("[{}]", value);
+            let mut parser = StreamingParser::with_options(&input, &options);
+            parser.next().unwrap().unwrap();
+            let err = parser.next_raw_value().unwrap_err();
+            assert_eq!(err.kind, kind, "input {:?}", input);
+        }
+
+        // JSON5 text cannot be passed through as JSON.
+        let options = ParserOptions::new().dialect(crate::Dialect::Json5);
+        let mut parser = StreamingParser::with_options("[ {a: 'b'}]", &options);
+        parser.next().unwrap().unwrap();
+        let err = parser.next_raw_value().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::RawValueInJson5);
+        assert_eq!(err.offset, 2);
+        assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::StartObject);
+    }
+
+    #[test]
+    fn test_raw_value_validation() {
+        assert_eq!(RawValue::new("  [1, {}]\n").unwrap().get(), "[1, {}]");
+        assert_eq!(
+            RawValue::from_string(" \"s\" ".to_string()).unwrap().get(),
+            "\"s\""
+        );
+        for invalid in ["", "   ", "[1,]", "1 2", "{\"a\"}", "tru"] {
+            assert!(RawValue::new(invalid).is_err(), "input {:?}", invalid);
+        }
+        assert_eq!(RawValue::new("").unwrap_err().kind, ErrorKind::EmptyInput);
+    }
+
+    #[test]
+    fn test_raw_value_in_json_value() {
+        let mut map = BTreeMap::new();
+        map.insert(
+            "payload".to_string(),
+            JsonValue::Raw(
+                RawValue::new("{\"z\": 1,  \"a\": [ ]}")
+                    .unwrap()
+                    .into_owned(),
+            ),
+        );
+        map.insert(
+            "id".to_string(),
+            JsonValue::Raw(RawValue::new("7").unwrap().into_owned()),
+        );
+        let value = JsonValue::Object(map);
+
+        assert_eq!(
+            value.stringify().unwrap(),
+            r#"{"id":7,"payload":{"z": 1,  "a": [ ]}}"#
+        );
+        assert_eq!(
+            value.stringify_pretty().unwrap(),
+            "{\n  \"id\": 7,\n  \"payload\": {\"z\": 1,  \"a\": [ ]}\n}"
+        );
+    }
+
+    #[test]
+    fn test_raw_number_conversions() {
+        let n = |text| RawNumber::new(text).unwrap();
+
+        assert_eq!(n("-0").to_i64(), Some(0));
+        assert_eq!(n("-0").to_u64(), Some(0));
+        assert!(n("-0").to_f64().unwrap().is_sign_negative());
+        assert_eq!(n("-9223372036854775808").to_i64(), Some(i64::MIN));
+        assert_eq!(n("-9223372036854775809").to_i64(), None);
+        assert_eq!(
+            n("-9223372036854775809").to_i128(),
+            Some(-9223372036854775809)
+        );
+        assert_eq!(n("18446744073709551615").to_u64(), Some(u64::MAX));
+        assert_eq!(n("18446744073709551616").to_u64(), None);
+        assert_eq!(n("-1").to_u64(), None);
+        assert_eq!(
+            n("340282366920938463463374607431768211455").to_u128(),
+            Some(u128::MAX)
+        );
+        assert_eq!(n("340282366920938463463374607431768211456").to_u128(), None);
+        assert_eq!(
+            n("-170141183460469231731687303715884105728").to_i128(),
+            Some(i128::MIN)
+        );
+        assert_eq!(n("170141183460469231731687303715884105728").to_i128(), None);
+
+        // Whole numbers written with a fraction or exponent still convert.
+        assert_eq!(n("1.50e1").to_i64(), Some(15));
+        assert_eq!(n("1500e-2").to_i64(), Some(15));
+        assert_eq!(n("100.000").to_u64(), Some(100));
+        assert_eq!(n("0.0e99999999999999999999").to_i64(), Some(0));
+        assert_eq!(n("1.5").to_i64(), None);
+        assert_eq!(n("1e-99999999999999999999").to_i64(), None);
+        assert_eq!(n("1e99999999999999999999").to_i64(), None);
+
+        assert_eq!(n("0.1").to_f64(), Some(0.1));
+        assert_eq!(n("1e400").to_f64(), None);
+
+        for invalid in ["", " 1", "+1", "01", "1.", ".5", "0x10", "1e", "NaN", "1 "] {
+            assert!(RawNumber::new(invalid).is_err(), "input {:?}", invalid);
+        }
+
+        // `JsonNumber` offers the same conversions for every variant.
+        assert_eq!(JsonNumber::F64(3.0).to_i64(), Some(3));
+        assert_eq!(JsonNumber::F64(3.5).to_i64(), None);
+        assert_eq!(JsonNumber::F64(-1.0).to_u64(), None);
+        assert_eq!(JsonNumber::I64(-7).to_i128(), Some(-7));
+        assert_eq!(JsonNumber::U64(u64::MAX).to_i64(), None);
+        assert_eq!(
+            JsonNumber::U64(u64::MAX).to_u128(),
+            Some(u128::from(u64::MAX))
+        );
+        assert_eq!(JsonNumber::F64(f64::NAN).to_f64(), None);
+        assert_eq!(JsonNumber::Raw(n("2e3")).to_u64(), Some(2000));
+    }
+
+    #[test]
+    fn test_lossless_numbers() {
+        let options = ParserOptions::new().lossless_numbers(true);
+        let input = "[0.1, -0, 1.10, 1E+2, 123456789012345678901234567890, -1e-400]";
+
+        let value = JsonValue::parse_with(input, &options).unwrap();
+        assert_eq!(
+            value.stringify().unwrap(),
+            "[0.1,-0,1.10,1E+2,123456789012345678901234567890,-1e-400]"
+        );
+        assert_eq!(
+            value.stringify_pretty().unwrap(),
+            "[\n  0.1,\n  -0,\n  1.10,\n  1E+2,\n  123456789012345678901234567890,\n  -1e-400\n]"
+        );
+
+        // The push parser keeps numbers split across chunks intact.
+        let mut push = crate::PushParser::with_options(&options);
+        for chunk in input.as_bytes().chunks(2) {
+            push.feed(chunk).unwrap();
+        }
+        push.finish();
+        let numbers: Vec<_> = std::iter::from_fn(|| push.next_event())
+            .filter_map(|event| match event.unwrap() {
+                ParserEvent::Number(JsonNumber::Raw(raw)) => Some(raw.get().to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            numbers,
+            [
+                "0.1",
+                "-0",
+                "1.10",
+                "1E+2",
+                "123456789012345678901234567890",
+                "-1e-400"
+            ]
+        );
+
+        // Without the option, large integers are still rejected.
+        assert_eq!(
+            JsonValue::parse("123456789012345678901234567890")
+                .unwrap_err()
+                .kind,
+            ErrorKind::IntegerOutOfRange("123456789012345678901234567890".to_string())
+        );
+
+        // JSON5-only numbers are kept as the JSON text of the same value.
+        // `Infinity` and `NaN` have none, and are converted.
+        let options = options.dialect(crate::Dialect::Json5);
+        let input = "[0x10, -0XfF, 0xFFFFFFFFFFFFFFFFFF, +1, .5, 5., -.5E+1, 2.50, Infinity]";
+        let value = JsonValue::parse_with(input, &options).unwrap();
+        let raw = |text| JsonValue::Number(JsonNumber::Raw(RawNumber::new(text).unwrap()));
+        assert_eq!(
+            value,
+            JsonValue::Array(vec![
+                raw("16"),
+                raw("-255"),
+                raw("4722366482869645213695"),
+                raw("1"),
+                raw("0.5"),
+                raw("5.0"),
+                raw("-0.5E+1"),
+                raw("2.50"),
+                JsonValue::Number(JsonNumber::F64(f64::INFINITY)),
+            ])
+        );
+    }
+}
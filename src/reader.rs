@@ -0,0 +1,272 @@
+//! Contains the `ReaderParser`, which parses directly from `std::io::Read`.
+//!
+//! This is a thin layer over the `PushParser`: it refills a fixed-size
+//! chunk buffer from the reader whenever the parser needs more input.
+
+use crate::error::{ErrorKind, ParseError};
+use crate::options::ParserOptions;
+use crate::parser::ParserEvent;
+use crate::push::PushParser;
+use memchr::{memchr, memchr_iter, memrchr};
+use std::io::{self, BufRead, Read};
+
+/// The number of bytes requested from the reader on each refill.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// A streaming JSON parser that pulls its input from a `std::io::Read`.
+///
+/// This struct is an `Iterator` that yields `Result<ParserEvent, ParseError>`.
+/// It is created by the `parse_reader` function.
+///
+/// Unlike `parse_streaming`, the document never has to fit in memory:
+/// memory use is proportional to the nesting depth and to the largest
+/// single token, not to the size of the input. So `parse_reader` applies
+/// no input size limit by default, while `parse_reader_with` applies
+/// `max_input_size` to the total number of bytes read. Events own their
+/// data, since the read buffer is reused.
+pub struct ReaderParser<R> {
+    /// The source of the JSON text.
+    reader: R,
+    /// The incremental parser that does the actual work.
+    parser: PushParser,
+    /// The reusable buffer that chunks are read into.
+    chunk: Vec<u8>,
+    /// Set after an I/O error, which ends the iteration.
+    failed: bool,
+}
+
+impl<R: Read> ReaderParser<R> {
+    /// Creates a new `ReaderParser` for a given reader.
+    ///
+    /// This is called by the `parse_reader` function in `lib.rs`.
+    /// The input size is not limited.
+    pub fn new(reader: R, max_depth: usize) -> Self {
+        Self::with_options(
+            reader,
+            &ParserOptions::new()
+                .max_depth(max_depth)
+                .max_input_size(usize::MAX),
+        )
+    }
+
+    /// Creates a new `ReaderParser` that enforces the given limits.
+    ///
+    /// The input size limit applies to the total number of bytes read.
+    pub fn with_options(reader: R, options: &ParserOptions) -> Self {
+        ReaderParser {
+            reader,
+            parser: PushParser::with_options(options),
+            chunk: vec![0; CHUNK_SIZE],
+            failed: false,
+        }
+    }
+
+    /// Consumes the parser, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Turns an I/O error into a `ParseError` at the current location.
+    fn io_error(&self, error: io::Error) -> ParseError {
+        let (line, column, offset) = self.parser.location();
+        ParseError::new(ErrorKind::Io(error.to_string()), line, column, offset)
+    }
+}
+
+impl<R: Read> Iterator for ReaderParser<R> {
+    type Item = Result<ParserEvent<'static>, ParseError>;
+
+    /// Returns the next event, reading more input as needed.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            if let Some(event) = self.parser.next_event() {
+                return Some(event);
+            }
+            if self.parser.is_done() {
+                return None;
+            }
+
+            // The parser needs more input.
+            match self.reader.read(&mut self.chunk) {
+                Ok(0) => self.parser.finish(),
+                Ok(n) => {
+                    // Past the size limit, the parser is done.
+                    if let Err(e) = self.parser.feed(&self.chunk[..n]) {
+                        return Some(Err(e));
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(self.io_error(e)));
+                }
+            }
+        }
+    }
+}
+
+/// The outcome of `read_delimited`.
+pub(crate) struct Delimited {
+    /// The number of bytes consumed, including the delimiter. Zero means
+    /// the end of input.
+    pub(crate) consumed: usize,
+    /// The number of `\n` bytes consumed.
+    pub(crate) newlines: usize,
+    /// The number of `char`s consumed after the last `\n` (or in total,
+    /// if there was none).
+    pub(crate) tail_chars: usize,
+    /// Set if the record was longer than the limit and was cut short.
+    pub(crate) truncated: bool,
+}
+
+/// Reads one record, up to and including the next `delimiter` byte (or
+/// the end of input), and appends it to `record` without the delimiter.
+///
+/// At most `limit` bytes are kept; the rest of an overlong record is read
+/// and dropped, so memory use stays bounded. Used by the record-oriented
+/// readers (NDJSON and JSON text sequences).
+pub(crate) fn read_delimited<R: BufRead>(
+    reader: &mut R,
+    delimiter: u8,
+    record: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<Delimited> {
+    let mut read = Delimited {
+        consumed: 0,
+        newlines: 0,
+        tail_chars: 0,
+        truncated: false,
+    };
+    let mut kept = 0;
+
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            return Ok(read); // End of input
+        }
+
+        let (content, consumed, done) = match memchr(delimiter, available) {
+            Some(end) => (&available[..end], end + 1, true),
+            None => (available, available.len(), false),
+        };
+        let room = limit.saturating_sub(kept);
+        if content.len() > room {
+            read.truncated = true;
+        }
+        record.extend_from_slice(&content[..content.len().min(room)]);
+        kept += content.len().min(room);
+        let consumed_bytes = &available[..consumed];
+        let tail = match memrchr(b'\n', consumed_bytes) {
+            Some(newline) => {
+                read.newlines += memchr_iter(b'\n', consumed_bytes).count();
+                read.tail_chars = 0;
+                &consumed_bytes[newline + 1..]
+            }
+            None => consumed_bytes,
+        };
+        // Columns count `char`s: UTF-8 continuation bytes don't start a new one.
+        read.tail_chars += tail.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+        read.consumed += consumed;
+
+        reader.consume(consumed);
+        if done {
+            return Ok(read);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_reader, parse_streaming, JsonNumber};
+
+    /// A reader that hands out at most `step` bytes per call.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    /// A reader that fails after its data runs out.
+    struct Broken<'a>(&'a [u8]);
+
+    impl Read for Broken<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::other("connection reset"));
+            }
+            let n = buf.len().min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_reader_matches_slice_parser() {
+        let input =
+            r#"{"users": [{"id": 1, "name": "Ada"}, {"id": 2, "tags": [true, null, 1.5]}]}"#;
+        let expected: Vec<_> = parse_streaming(input)
+            .unwrap()
+            .map(|event| event.unwrap().into_owned())
+            .collect();
+
+        for step in [1, 3, 7, input.len()] {
+            let reader = Trickle {
+                data: input.as_bytes(),
+                step,
+            };
+            let events: Vec<_> = parse_reader(reader).map(Result::unwrap).collect();
+            assert_eq!(events, expected);
+        }
+    }
+
+    #[test]
+    fn test_reader_large_input() {
+        // Many more bytes than a single chunk, to exercise buffer refills.
+        let input = format!("[{}0]", "1234567, ".repeat(10_000));
+        let mut count = 0;
+        for event in parse_reader(input.as_bytes()) {
+            match event.unwrap() {
+                ParserEvent::Number(JsonNumber::I64(_)) => count += 1,
+                ParserEvent::StartArray | ParserEvent::EndArray => {}
+                other => panic!("Unexpected event {:?}", other),
+            }
+        }
+        assert_eq!(count, 10_001);
+    }
+
+    #[test]
+    fn test_reader_errors() {
+        let err = parse_reader(&b"[1, 2"[..])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(err.message, "Unclosed array");
+
+        let mut parser = parse_reader(Broken(b"[1, "));
+        assert_eq!(parser.next().unwrap().unwrap(), ParserEvent::StartArray);
+        assert_eq!(
+            parser.next().unwrap().unwrap(),
+            ParserEvent::Number(JsonNumber::I64(1))
+        );
+        let err = parser.next().unwrap().unwrap_err();
+        assert_eq!(err.message, "I/O error: connection reset");
+        assert!(parser.next().is_none());
+    }
+}
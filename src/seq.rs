@@ -0,0 +1,358 @@
+//! Contains the `JsonSeqReader` and `JsonSeqWriter` for JSON text
+//! sequences (RFC 7464, `application/json-seq`).
+//!
+//! A JSON text sequence prefixes every JSON text with the ASCII record
+//! separator (RS, 0x1E) and ends it with a newline. Since RS can never
+//! appear inside JSON, a reader can always resynchronize at the next RS
+//! after a damaged record.
+
+use crate::error::{ErrorKind, ParseError};
+use crate::options::ParserOptions;
+use crate::reader::read_delimited;
+use crate::value::JsonValue;
+use std::io::{self, BufReader, Read, Write};
+
+/// The record separator that starts every JSON text in a sequence.
+const RS: u8 = 0x1E;
+
+/// A JSON text sequence parser that pulls its input from a `std::io::Read`.
+///
+/// This struct is an `Iterator` that yields one `Result` per record. It is
+/// created by the `parse_json_seq` function. Following the recovery rules
+/// of RFC 7464, a malformed or truncated record is reported and skipped,
+/// and parsing resumes at the next RS; empty records are ignored. Only I/O
+/// errors end the iteration.
+///
+/// A top-level number, `true`, `false` or `null` that is not followed by
+/// whitespace may have been cut short (`12` could be the start of `123`),
+/// so it is rejected with `ErrorKind::PossiblyTruncated`.
+///
+/// # Examples
+/// ```
+/// use rill_json::{parse_json_seq, ErrorKind, JsonNumber, JsonValue};
+///
+/// let input = b"\x1e{\"a\": 1}\n\x1e[tru\x1e42\n\x1e42";
+/// let results: Vec<_> = parse_json_seq(&input[..]).collect();
+///
+/// assert!(matches!(results[0], Ok(JsonValue::Object(_))));
+/// assert!(results[1].is_err()); // Truncated by the next RS
+/// assert_eq!(results[2], Ok(JsonValue::Number(JsonNumber::I64(42))));
+/// // No whitespace after the last number: it may have been truncated.
+/// assert_eq!(results[3].as_ref().unwrap_err().kind, ErrorKind::PossiblyTruncated);
+/// ```
+pub struct JsonSeqReader<R> {
+    /// The buffered source of the input.
+    reader: BufReader<R>,
+    /// The bytes of the record being read.
+    record: Vec<u8>,
+    /// The byte offset of the next unread byte.
+    offset: usize,
+    /// The line (1-indexed) of the next unread byte.
+    line: usize,
+    /// The column (1-indexed) of the next unread byte.
+    column: usize,
+    /// Set once the bytes before the first RS have been read.
+    started: bool,
+    /// The limits applied to each record.
+    options: ParserOptions,
+    /// Set after an I/O error, which ends the iteration.
+    failed: bool,
+}
+
+impl<R: Read> JsonSeqReader<R> {
+    /// Creates a new `JsonSeqReader` that applies `options` to each record.
+    ///
+    /// This is called by the `parse_json_seq` and `parse_json_seq_with`
+    /// functions in `lib.rs`. `max_input_size` limits the size of each
+    /// record; the rest of an overlong record is skipped without being
+    /// buffered.
+    pub fn new(reader: R, options: &ParserOptions) -> Self {
+        JsonSeqReader {
+            reader: BufReader::new(reader),
+            record: Vec::new(),
+            offset: 0,
+            line: 1,
+            column: 1,
+            started: false,
+            options: options.clone(),
+            failed: false,
+        }
+    }
+
+    /// Consumes the reader, returning the underlying reader.
+    ///
+    /// Any input that was buffered but not yet parsed is lost.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Parses the record in `self.record`, which starts at the given
+    /// location.
+    fn parse_record(
+        &self,
+        truncated: bool,
+        (line, column, offset): (usize, usize, usize),
+    ) -> Result<JsonValue, ParseError> {
+        if truncated {
+            return Err(ParseError::new(
+                ErrorKind::SizeLimitExceeded,
+                line,
+                column,
+                offset,
+            ));
+        }
+
+        let text = std::str::from_utf8(&self.record).map_err(|e| {
+            let (lines, columns) = locate(&self.record[..e.valid_up_to()]);
+            ParseError::new(ErrorKind::InvalidUtf8, lines, columns, e.valid_up_to()).shift(
+                line - 1,
+                column - 1,
+                offset,
+            )
+        })?;
+        let value = JsonValue::parse_with(text, &self.options)
+            .map_err(|e| e.shift(line - 1, column - 1, offset))?;
+
+        // RFC 7464, section 2.4: a top-level number or literal must be
+        // followed by whitespace, or it may have been truncated.
+        let scalar = matches!(
+            value,
+            JsonValue::Number(_) | JsonValue::Boolean(_) | JsonValue::Null
+        );
+        if scalar && !matches!(text.as_bytes().last(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            let start = text.len() - text.trim_start().len();
+            let (lines, columns) = locate(&self.record[..start]);
+            return Err(
+                ParseError::new(ErrorKind::PossiblyTruncated, lines, columns, start).shift(
+                    line - 1,
+                    column - 1,
+                    offset,
+                ),
+            );
+        }
+        Ok(value)
+    }
+}
+
+impl<R: Read> Iterator for JsonSeqReader<R> {
+    type Item = Result<JsonValue, ParseError>;
+
+    /// Reads and parses the next non-empty record.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            let start = (self.line, self.column, self.offset);
+            self.record.clear();
+            let read = match read_delimited(
+                &mut self.reader,
+                RS,
+                &mut self.record,
+                self.options.max_input_size,
+            ) {
+                Ok(read) => read,
+                Err(e) => {
+                    self.failed = true;
+                    let (line, column, offset) = start;
+                    return Some(Err(ParseError::new(
+                        ErrorKind::Io(e.to_string()),
+                        line,
+                        column,
+                        offset,
+                    )));
+                }
+            };
+            if read.consumed == 0 {
+                return None;
+            }
+
+            self.offset += read.consumed;
+            if read.newlines > 0 {
+                self.line += read.newlines;
+                self.column = read.tail_chars + 1;
+            } else {
+                self.column += read.tail_chars;
+            }
+
+            // Bytes before the first RS are not part of any record.
+            let preamble = !self.started;
+            self.started = true;
+
+            let blank = self
+                .record
+                .iter()
+                .all(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r'));
+            if blank && !read.truncated {
+                continue; // Empty records are ignored.
+            }
+            if preamble {
+                let (line, column, offset) = start;
+                return Some(Err(ParseError::new(
+                    ErrorKind::MissingRecordSeparator,
+                    line,
+                    column,
+                    offset,
+                )));
+            }
+
+            return Some(self.parse_record(read.truncated, start));
+        }
+    }
+}
+
+/// Returns the 1-indexed line and column just past `bytes`.
+fn locate(bytes: &[u8]) -> (usize, usize) {
+    let line = 1 + bytes.iter().filter(|&&b| b == b'\n').count();
+    let tail = match bytes.iter().rposition(|&b| b == b'\n') {
+        Some(newline) => &bytes[newline + 1..],
+        None => bytes,
+    };
+    let column = 1 + tail.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+    (line, column)
+}
+
+/// A writer for JSON text sequences.
+///
+/// Each value is written as RS, its compact JSON text, and a newline, as
+/// RFC 7464 requires. The trailing newline also protects top-level
+/// numbers against being mistaken for truncated ones.
+///
+/// # Examples
+/// ```
+/// use rill_json::{JsonSeqWriter, JsonValue};
+///
+/// let mut writer = JsonSeqWriter::new(Vec::new());
+/// writer.write(&JsonValue::Boolean(true)).unwrap();
+/// writer.write(&JsonValue::Array(vec![JsonValue::Null])).unwrap();
+/// assert_eq!(writer.into_inner(), b"\x1etrue\n\x1e[null]\n");
+/// ```
+pub struct JsonSeqWriter<W> {
+    /// The destination of the sequence.
+    writer: W,
+}
+
+impl<W: Write> JsonSeqWriter<W> {
+    /// Creates a new `JsonSeqWriter` that writes to `writer`.
+    ///
+    /// Each record is written with a single `write_all` call; wrap the
+    /// writer in a `std::io::BufWriter` if that is too many syscalls.
+    pub fn new(writer: W) -> Self {
+        JsonSeqWriter { writer }
+    }
+
+    /// Writes one value as a record.
+    ///
+    /// # Errors
+    /// Returns an `InvalidData` error if the value contains `f64::NAN` or
+    /// `f64::INFINITY`, and any error from the underlying writer.
+    pub fn write(&mut self, value: &JsonValue) -> io::Result<()> {
+        let text = value.stringify().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "NaN and infinite numbers cannot be written as JSON",
+            )
+        })?;
+        let mut record = Vec::with_capacity(text.len() + 2);
+        record.push(RS);
+        record.extend_from_slice(text.as_bytes());
+        record.push(b'\n');
+        self.writer.write_all(&record)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Consumes the writer, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_json_seq, parse_json_seq_with, JsonNumber};
+
+    #[test]
+    fn test_json_seq_round_trip() {
+        let values = vec![
+            JsonValue::Number(JsonNumber::I64(-7)),
+            JsonValue::String("tab\there".to_string()),
+            JsonValue::Array(vec![JsonValue::Boolean(false), JsonValue::Null]),
+            JsonValue::Null,
+        ];
+        let mut writer = JsonSeqWriter::new(Vec::new());
+        for value in &values {
+            writer.write(value).unwrap();
+        }
+        let bytes = writer.into_inner();
+
+        let parsed: Vec<_> = parse_json_seq(&bytes[..])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(parsed, values);
+
+        let mut writer = JsonSeqWriter::new(Vec::new());
+        let err = writer
+            .write(&JsonValue::Number(JsonNumber::F64(f64::NAN)))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(writer.into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_json_seq_recovers_from_bad_records() {
+        let input = b"\x1e\x1e \n\x1e{\"a\":\n [1, \x1e\"ok\"\n\x1e123\x1etrue \x1e{\"b\": nul}\n";
+        let results: Vec<_> = parse_json_seq(&input[..]).collect();
+        assert_eq!(results.len(), 5);
+
+        // A record truncated by the next RS.
+        let err = results[0].as_ref().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnclosedArray);
+        assert_eq!((err.line, err.column, err.offset), (3, 2, 12));
+
+        assert_eq!(results[1], Ok(JsonValue::String("ok".to_string())));
+
+        // A number directly followed by RS may have lost digits.
+        let err = results[2].as_ref().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::PossiblyTruncated);
+        assert_eq!((err.line, err.column, err.offset), (4, 2, 23));
+
+        assert_eq!(results[3], Ok(JsonValue::Boolean(true)));
+
+        let err = results[4].as_ref().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ExpectedLiteral("null"));
+        assert_eq!((err.line, err.column), (4, 18));
+    }
+
+    #[test]
+    fn test_json_seq_rejects_missing_separator_and_oversized_records() {
+        let mut reader = parse_json_seq(&b"[1]\n\x1e[2]\n"[..]);
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::MissingRecordSeparator);
+        assert_eq!(err.offset, 0);
+        assert_eq!(
+            reader.next().unwrap().unwrap(),
+            JsonValue::Array(vec![JsonValue::Number(JsonNumber::I64(2))])
+        );
+        assert!(reader.next().is_none());
+
+        let options = ParserOptions::new().max_input_size(4);
+        let input = b"\x1e[1, 2, 3]\n\x1e[4]\n";
+        let results: Vec<_> = parse_json_seq_with(&input[..], &options).collect();
+        assert_eq!(
+            results[0].as_ref().unwrap_err().kind,
+            ErrorKind::SizeLimitExceeded
+        );
+        assert_eq!(
+            results[1],
+            Ok(JsonValue::Array(vec![JsonValue::Number(JsonNumber::I64(
+                4
+            ))]))
+        );
+    }
+}
@@ -0,0 +1,1239 @@
+//! Contains the serde `Serializer`, which writes any type that implements
+//! `serde::Serialize` as JSON text.
+//!
+//! The output is exactly what `JsonValue::stringify` and
+//! `JsonValue::stringify_pretty` produce for the same data: the same string
+//! escaping, the same float formatting and the same two-space indentation.
+//!
+//! This module is only available with the `serde` feature.
+
+use crate::{
+    float, JsonNumber, JsonValue, LoneSurrogatePolicy, ParserOptions, RawNumber, RawValue,
+    Wtf8String,
+};
+use serde::ser::{self, Serialize};
+use std::cell::Cell;
+use std::fmt::{self, Write};
+use std::io;
+
+/// The name of the newtype struct through which `serialize_verbatim` hands
+/// JSON text to this module's `Serializer`.
+const VERBATIM: &str = "$rill_json::private::Verbatim";
+
+thread_local! {
+    /// The text being serialized by `serialize_verbatim`.
+    static VERBATIM_TEXT: Cell<Option<String>> = const { Cell::new(None) };
+}
+
+/// Serializes `value` as a compact JSON string.
+///
+/// # Errors
+/// Returns a `SerializeError` if `value` contains a NaN or infinite float,
+/// a map key that is not a string or integer, or if its `Serialize`
+/// implementation fails.
+///
+/// # Examples
+/// ```
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// enum Shape {
+///     Circle { radius: f64 },
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Drawing {
+///     name: &'static str,
+///     shapes: Vec<Shape>,
+/// }
+///
+/// let drawing = Drawing { name: "dot", shapes: vec![Shape::Circle { radius: 1.0 }] };
+/// assert_eq!(
+///     rill_json::to_string(&drawing).unwrap(),
+///     r#"{"name":"dot","shapes":[{"Circle":{"radius":1.0}}]}"#
+/// );
+/// assert!(rill_json::to_string(&f64::NAN).is_err());
+/// ```
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerializeError> {
+    let mut output = String::new();
+    value.serialize(&mut Serializer::new(&mut output))?;
+    Ok(output)
+}
+
+/// Serializes `value` as an indented JSON string, laid out like
+/// `JsonValue::stringify_pretty`.
+///
+/// # Errors
+/// See `to_string`.
+///
+/// # Examples
+/// ```
+/// let pretty = rill_json::to_string_pretty(&vec![(1, "a")]).unwrap();
+/// assert_eq!(pretty, "[\n  [\n    1,\n    \"a\"\n  ]\n]");
+/// ```
+pub fn to_string_pretty<T: Serialize + ?Sized>(value: &T) -> Result<String, SerializeError> {
+    let mut output = String::new();
+    value.serialize(&mut Serializer::pretty(&mut output))?;
+    Ok(output)
+}
+
+/// Serializes `value` as compact JSON into an `io::Write`.
+///
+/// The writer is not buffered; wrap it in an `io::BufWriter` when writing
+/// to a file or socket.
+///
+/// # Errors
+/// See `to_string`. Errors from the writer are returned as
+/// `SerializeError::Io`.
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+) -> Result<(), SerializeError> {
+    let mut adapter = IoAdapter::new(writer);
+    let result = value.serialize(&mut Serializer::new(&mut adapter));
+    adapter.finish(result)
+}
+
+/// Serializes `value` as indented JSON into an `io::Write`.
+///
+/// # Errors
+/// See `to_writer`.
+pub fn to_writer_pretty<W: io::Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+) -> Result<(), SerializeError> {
+    let mut adapter = IoAdapter::new(writer);
+    let result = value.serialize(&mut Serializer::pretty(&mut adapter));
+    adapter.finish(result)
+}
+
+/// An error raised while serializing a value with serde, or while
+/// converting a `JsonValue` into a `serde_json::Value`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SerializeError {
+    /// A NaN or infinite float, which JSON cannot represent.
+    NonFiniteFloat,
+    /// A map key that is not a string, a `char`, an integer or a unit
+    /// enum variant.
+    KeyMustBeString,
+    /// The underlying writer failed.
+    Io(io::Error),
+    /// An error reported by a `Serialize` implementation.
+    Custom(String),
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeError::NonFiniteFloat => {
+                f.write_str("NaN and infinite floats cannot be represented in JSON")
+            }
+            SerializeError::KeyMustBeString => f.write_str("Map key must be a string"),
+            SerializeError::Io(e) => write!(f, "I/O error: {}", e),
+            SerializeError::Custom(description) => f.write_str(description),
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SerializeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl ser::Error for SerializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerializeError::Custom(msg.to_string())
+    }
+}
+
+impl From<fmt::Error> for SerializeError {
+    /// Only an `IoAdapter` can fail to write; `to_writer` replaces this
+    /// placeholder with the underlying `io::Error`.
+    fn from(_: fmt::Error) -> Self {
+        SerializeError::Io(io::Error::other("formatter error"))
+    }
+}
+
+/// Lets the `fmt::Write`-based serializer write to an `io::Write`, keeping
+/// the real `io::Error` that a `fmt::Error` cannot carry.
+struct IoAdapter<W> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> IoAdapter<W> {
+    fn new(writer: W) -> Self {
+        IoAdapter {
+            writer,
+            error: None,
+        }
+    }
+
+    /// Swaps the placeholder error for the writer's own.
+    fn finish(self, result: Result<(), SerializeError>) -> Result<(), SerializeError> {
+        match (result, self.error) {
+            (Err(SerializeError::Io(_)), Some(error)) => Err(SerializeError::Io(error)),
+            (result, _) => result,
+        }
+    }
+}
+
+impl<W: io::Write> Write for IoAdapter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// A serde `Serializer` that writes JSON text into a `fmt::Write`.
+///
+/// Most code should call `to_string` or `to_writer` instead.
+///
+/// # Examples
+/// ```
+/// use rill_json::ser::Serializer;
+/// use serde::Serialize;
+///
+/// let mut output = String::from("data = ");
+/// vec![Some(1), None].serialize(&mut Serializer::new(&mut output)).unwrap();
+/// assert_eq!(output, "data = [1,null]");
+/// ```
+pub struct Serializer<W> {
+    /// Where the JSON text goes.
+    writer: W,
+    /// Whether to indent the output like `stringify_pretty`.
+    pretty: bool,
+    /// The number of open arrays and objects.
+    depth: usize,
+}
+
+impl<W: Write> Serializer<W> {
+    /// Creates a serializer that writes compact JSON.
+    pub fn new(writer: W) -> Self {
+        Serializer {
+            writer,
+            pretty: false,
+            depth: 0,
+        }
+    }
+
+    /// Creates a serializer that writes indented JSON.
+    pub fn pretty(writer: W) -> Self {
+        Serializer {
+            writer,
+            pretty: true,
+            depth: 0,
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Opens an array or object.
+    fn begin(&mut self, open: char) -> Result<(), SerializeError> {
+        self.depth += 1;
+        Ok(self.writer.write_char(open)?)
+    }
+
+    /// Writes what precedes an array element or object member: a comma
+    /// unless it is the first, then a newline and indentation if pretty.
+    fn begin_member(&mut self, first: &mut bool) -> Result<(), SerializeError> {
+        if !*first {
+            self.writer.write_char(',')?;
+        }
+        *first = false;
+        self.newline(self.depth)
+    }
+
+    /// Writes the separator between an object key and its value.
+    fn colon(&mut self) -> Result<(), SerializeError> {
+        Ok(self
+            .writer
+            .write_str(if self.pretty { ": " } else { ":" })?)
+    }
+
+    /// Closes an array or object. Empty ones stay on a single line, as
+    /// `[]` or `{}`.
+    fn end(&mut self, close: char, empty: bool) -> Result<(), SerializeError> {
+        self.depth -= 1;
+        if !empty {
+            self.newline(self.depth)?;
+        }
+        Ok(self.writer.write_char(close)?)
+    }
+
+    /// Starts a new, indented line if pretty-printing.
+    fn newline(&mut self, depth: usize) -> Result<(), SerializeError> {
+        if self.pretty {
+            self.writer.write_char('\n')?;
+            for _ in 0..depth {
+                self.writer.write_str(JsonValue::INDENT)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes an escaped JSON string.
+    fn string(&mut self, s: &str) -> Result<(), SerializeError> {
+        Ok(JsonValue::write_string(s, &mut self.writer)?)
+    }
+
+    /// Opens the `{"Variant":` wrapper of a non-unit enum variant.
+    fn begin_variant(&mut self, variant: &str) -> Result<(), SerializeError> {
+        self.begin('{')?;
+        self.newline(self.depth)?;
+        self.string(variant)?;
+        self.colon()
+    }
+
+    /// Writes a number's `Display` form.
+    fn display<T: fmt::Display>(&mut self, n: T) -> Result<(), SerializeError> {
+        Ok(write!(self.writer, "{}", n)?)
+    }
+}
+
+impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerializeError> {
+        Ok(self.writer.write_str(if v { "true" } else { "false" })?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), SerializeError> {
+        self.display(v)
+    }
+
+    /// NaN and infinities are rejected, as by `stringify`.
+    fn serialize_f32(self, v: f32) -> Result<(), SerializeError> {
+        if !v.is_finite() {
+            return Err(SerializeError::NonFiniteFloat);
+        }
+        Ok(float::write_f32(&mut self.writer, v)?)
+    }
+
+    /// NaN and infinities are rejected, as by `stringify`.
+    fn serialize_f64(self, v: f64) -> Result<(), SerializeError> {
+        if !v.is_finite() {
+            return Err(SerializeError::NonFiniteFloat);
+        }
+        Ok(float::write_f64(&mut self.writer, v)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerializeError> {
+        self.string(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SerializeError> {
+        self.string(v)
+    }
+
+    /// Bytes are written as an array of numbers.
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SerializeError> {
+        let mut seq = ser::Serializer::serialize_seq(self, Some(v.len()))?;
+        for byte in v {
+            ser::SerializeSeq::serialize_element(&mut seq, byte)?;
+        }
+        ser::SerializeSeq::end(seq)
+    }
+
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        Ok(self.writer.write_str("null")?)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerializeError> {
+        self.serialize_unit()
+    }
+
+    /// Unit variants are written as their name: `"Red"`.
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerializeError> {
+        self.string(variant)
+    }
+
+    /// The private newtype of `serialize_verbatim` is written as the JSON
+    /// text it carries. Any other newtype is written as its content.
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        if name == VERBATIM {
+            if let Some(text) = VERBATIM_TEXT.take() {
+                return Ok(self.writer.write_str(&text)?);
+            }
+        }
+        value.serialize(self)
+    }
+
+    /// Newtype variants are written as `{"Variant": value}`.
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.begin_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.end('}', false)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, W>, SerializeError> {
+        self.begin('[')?;
+        Ok(Compound::new(self, false))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, W>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    /// Tuple variants are written as `{"Variant": [...]}`.
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>, SerializeError> {
+        self.begin_variant(variant)?;
+        self.begin('[')?;
+        Ok(Compound::new(self, true))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, W>, SerializeError> {
+        self.begin('{')?;
+        Ok(Compound::new(self, false))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>, SerializeError> {
+        self.serialize_map(Some(len))
+    }
+
+    /// Struct variants are written as `{"Variant": {...}}`.
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>, SerializeError> {
+        self.begin_variant(variant)?;
+        self.begin('{')?;
+        Ok(Compound::new(self, true))
+    }
+}
+
+/// Numbers keep their type: `I64` is serialized as an `i64`, `U64` as a
+/// `u64` and `F64` as an `f64`. This crate's serializer writes a `Raw`
+/// number's text verbatim, as `JsonValue::stringify` does; other
+/// serializers get the narrowest of `i64`, `u64`, `i128`, `u128` and `f64`
+/// that holds it.
+impl Serialize for JsonNumber {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonNumber::I64(n) => serializer.serialize_i64(*n),
+            JsonNumber::U64(n) => serializer.serialize_u64(*n),
+            JsonNumber::F64(n) => serializer.serialize_f64(*n),
+            JsonNumber::Raw(raw) => {
+                serialize_verbatim(serializer, raw.get().to_string(), &NarrowestNumber(raw))
+            }
+        }
+    }
+}
+
+/// The fallback of a raw number for other serializers.
+struct NarrowestNumber<'a>(&'a RawNumber);
+
+impl Serialize for NarrowestNumber<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_raw_number(self.0, serializer)
+    }
+}
+
+/// The fallback of a raw value for other serializers: the value, parsed.
+struct ParsedValue<'a>(&'a RawValue<'static>);
+
+impl Serialize for ParsedValue<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Raw values are checked against the full grammar when they are
+        // created, so only the nesting depth limit or a lone surrogate in
+        // a key can reject them here.
+        let options = ParserOptions::new()
+            .max_input_size(usize::MAX)
+            .lossless_numbers(true)
+            .lone_surrogates(LoneSurrogatePolicy::Preserve);
+        JsonValue::parse_with(self.0.get(), &options)
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// Serializes JSON `text` that this module's `Serializer` writes verbatim,
+/// while any other serializer gets `fallback` instead.
+///
+/// A `Serialize` impl cannot tell serializers apart, so `text` is left in
+/// a thread-local and a newtype struct with a private name tells our
+/// `Serializer` to take it. Other serializers see an ordinary newtype and
+/// serialize its content, the fallback.
+fn serialize_verbatim<S: ser::Serializer, T: Serialize + ?Sized>(
+    serializer: S,
+    text: String,
+    fallback: &T,
+) -> Result<S::Ok, S::Error> {
+    // Restoring the previous text keeps nested calls, made while another
+    // serializer serializes a fallback, from interfering.
+    let previous = VERBATIM_TEXT.replace(Some(text));
+    let result = serializer.serialize_newtype_struct(VERBATIM, fallback);
+    VERBATIM_TEXT.set(previous);
+    result
+}
+
+/// Serializes a raw number as the narrowest type that holds it.
+fn serialize_raw_number<S: ser::Serializer>(
+    raw: &RawNumber,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if !raw.get().contains(['.', 'e', 'E']) {
+        if let Some(n) = raw.to_i64() {
+            return serializer.serialize_i64(n);
+        } else if let Some(n) = raw.to_u64() {
+            return serializer.serialize_u64(n);
+        } else if let Some(n) = raw.to_i128() {
+            return serializer.serialize_i128(n);
+        } else if let Some(n) = raw.to_u128() {
+            return serializer.serialize_u128(n);
+        }
+    }
+    let n = raw
+        .get()
+        .parse()
+        .expect("Internal error: invalid raw number");
+    serializer.serialize_f64(n)
+}
+
+/// This crate's serializer writes the string back out with each lone
+/// surrogate escaped as `\uXXXX`, as `JsonValue::stringify` does. Other
+/// serializers, which take Rust strings, get the string with each lone
+/// surrogate replaced by U+FFFD.
+impl Serialize for Wtf8String {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut text = String::with_capacity(self.len() + 2);
+        self.write_json(&mut text).map_err(ser::Error::custom)?;
+        serialize_verbatim(serializer, text, &self.to_string_lossy())
+    }
+}
+
+/// Objects are serialized as maps and arrays as sequences. This crate's
+/// serializer writes a `Raw` value verbatim, as `JsonValue::stringify`
+/// does; other serializers get it parsed, like the equivalent `JsonValue`.
+impl Serialize for JsonValue {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonValue::Null => serializer.serialize_unit(),
+            JsonValue::Boolean(b) => serializer.serialize_bool(*b),
+            JsonValue::Number(n) => n.serialize(serializer),
+            JsonValue::String(s) => serializer.serialize_str(s),
+            JsonValue::WtfString(s) => s.serialize(serializer),
+            JsonValue::Array(a) => serializer.collect_seq(a),
+            JsonValue::Object(o) => serializer.collect_map(o),
+            JsonValue::Raw(raw) => {
+                serialize_verbatim(serializer, raw.get().to_string(), &ParsedValue(raw))
+            }
+        }
+    }
+}
+
+/// Serializes the members of an array or object.
+pub struct Compound<'a, W> {
+    ser: &'a mut Serializer<W>,
+    /// Whether no member has been written yet.
+    first: bool,
+    /// Whether the container is wrapped in `{"Variant": ...}`.
+    variant: bool,
+}
+
+impl<'a, W: Write> Compound<'a, W> {
+    fn new(ser: &'a mut Serializer<W>, variant: bool) -> Self {
+        Compound {
+            ser,
+            first: true,
+            variant,
+        }
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.ser.begin_member(&mut self.first)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), SerializeError> {
+        self.ser.begin_member(&mut self.first)?;
+        self.ser.string(key)?;
+        self.ser.colon()?;
+        value.serialize(&mut *self.ser)
+    }
+
+    /// Closes the container, and the variant wrapper around it.
+    fn finish(self, close: char) -> Result<(), SerializeError> {
+        self.ser.end(close, self.first)?;
+        if self.variant {
+            self.ser.end('}', false)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish(']')
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish(']')
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish(']')
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish(']')
+    }
+}
+
+impl<W: Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.ser.begin_member(&mut self.first)?;
+        key.serialize(KeySerializer {
+            ser: &mut *self.ser,
+        })
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.ser.colon()?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish('}')
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish('}')
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.finish('}')
+    }
+}
+
+/// Serializes an object key. JSON keys are strings, so integer keys are
+/// quoted (`{"1": ...}`) and every other non-string key is rejected.
+struct KeySerializer<'a, W> {
+    ser: &'a mut Serializer<W>,
+}
+
+impl<W: Write> KeySerializer<'_, W> {
+    fn quoted<T: fmt::Display>(self, n: T) -> Result<(), SerializeError> {
+        Ok(write!(self.ser.writer, "\"{}\"", n)?)
+    }
+}
+
+impl<W: Write> ser::Serializer for KeySerializer<'_, W> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = ser::Impossible<(), SerializeError>;
+    type SerializeTuple = ser::Impossible<(), SerializeError>;
+    type SerializeTupleStruct = ser::Impossible<(), SerializeError>;
+    type SerializeTupleVariant = ser::Impossible<(), SerializeError>;
+    type SerializeMap = ser::Impossible<(), SerializeError>;
+    type SerializeStruct = ser::Impossible<(), SerializeError>;
+    type SerializeStructVariant = ser::Impossible<(), SerializeError>;
+
+    fn serialize_str(self, v: &str) -> Result<(), SerializeError> {
+        self.ser.string(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerializeError> {
+        self.ser.string(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), SerializeError> {
+        self.quoted(v)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerializeError> {
+        self.ser.string(variant)
+    }
+
+    /// Verbatim JSON text is a valid key only if it is a string.
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        if name == VERBATIM {
+            match VERBATIM_TEXT.take() {
+                Some(text) if text.starts_with('"') => {
+                    return Ok(self.ser.writer.write_str(&text)?);
+                }
+                _ => {}
+            }
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<(), SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<(), SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<(), SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<(), SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        Err(SerializeError::KeyMustBeString)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonNumber;
+    use serde::Serialize;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Serialize)]
+    struct Unit;
+
+    #[derive(Serialize)]
+    struct Meters(f32);
+
+    #[derive(Serialize)]
+    struct Pair(u8, &'static str);
+
+    #[derive(Serialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Point(i32, i32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize)]
+    struct Everything {
+        flag: bool,
+        small: i8,
+        big: u128,
+        ratio: f64,
+        letter: char,
+        text: String,
+        missing: Option<u8>,
+        unit: (),
+        unit_struct: Unit,
+        newtype: Meters,
+        pair: Pair,
+        bytes: &'static [u8],
+        shapes: Vec<Shape>,
+        empty: Vec<u8>,
+        keys: BTreeMap<i32, char>,
+    }
+
+    fn everything() -> Everything {
+        Everything {
+            flag: true,
+            small: -8,
+            big: u128::MAX,
+            ratio: 2.0,
+            letter: '"',
+            text: "tab\there".to_string(),
+            missing: None,
+            unit: (),
+            unit_struct: Unit,
+            newtype: Meters(1.1),
+            pair: Pair(1, "a"),
+            bytes: b"hi",
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(0.5),
+                Shape::Point(1, -2),
+                Shape::Rect { w: 3, h: 4 },
+            ],
+            empty: vec![],
+            keys: BTreeMap::from([(-1, 'x'), (2, 'y')]),
+        }
+    }
+
+    #[test]
+    fn test_serialize_data_model() {
+        assert_eq!(
+            to_string(&everything()).unwrap(),
+            concat!(
+                r#"{"flag":true,"small":-8,"big":340282366920938463463374607431768211455,"#,
+                r#""ratio":2.0,"letter":"\"","text":"tab\there","missing":null,"unit":null,"#,
+                r#""unit_struct":null,"newtype":1.1,"pair":[1,"a"],"bytes":[104,105],"#,
+                r#""shapes":["Empty",{"Circle":0.5},{"Point":[1,-2]},{"Rect":{"w":3,"h":4}}],"#,
+                r#""empty":[],"keys":{"-1":"x","2":"y"}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_serialize_matches_stringify() {
+        let input = r#"{"a": [1, -2.5, "\u0001/", {}, [], null], "b": {"c": {"d": [true]}}}"#;
+        let value = JsonValue::parse(input).unwrap();
+        let native: serde_json::Value = serde_json::from_str(input).unwrap();
+        assert_eq!(to_string(&native).unwrap(), value.stringify().unwrap());
+        assert_eq!(
+            to_string_pretty(&native).unwrap(),
+            value.stringify_pretty().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_serialize_pretty_variants() {
+        let shapes = vec![Shape::Point(1, 2), Shape::Rect { w: 3, h: 4 }];
+        assert_eq!(
+            to_string_pretty(&shapes).unwrap(),
+            "[\n  {\n    \"Point\": [\n      1,\n      2\n    ]\n  },\n  {\n    \"Rect\": {\n      \"w\": 3,\n      \"h\": 4\n    }\n  }\n]"
+        );
+        assert_eq!(
+            to_string_pretty(&Shape::Circle(1.0)).unwrap(),
+            "{\n  \"Circle\": 1.0\n}"
+        );
+    }
+
+    #[test]
+    fn test_serialize_errors() {
+        assert!(matches!(
+            to_string(&vec![1.0, f64::INFINITY]),
+            Err(SerializeError::NonFiniteFloat)
+        ));
+        assert!(matches!(
+            to_string(&f32::NAN),
+            Err(SerializeError::NonFiniteFloat)
+        ));
+        assert!(JsonValue::Number(JsonNumber::F64(f64::NAN))
+            .stringify()
+            .is_err());
+
+        let map = HashMap::from([(vec![1], 2)]);
+        let err = to_string(&map).unwrap_err();
+        assert!(matches!(err, SerializeError::KeyMustBeString));
+        assert_eq!(err.to_string(), "Map key must be a string");
+    }
+
+    #[test]
+    fn test_serialize_json_value() {
+        let input = r#"{"i":-1,"u":18446744073709551615,"f":1.0,"s":"x","a":[null,true]}"#;
+        let value = JsonValue::parse(input).unwrap();
+        assert_eq!(to_string(&value).unwrap(), value.stringify().unwrap());
+
+        // Numbers keep their type through other serializers too.
+        let native = serde_json::to_value(&value).unwrap();
+        assert!(native["i"].is_i64());
+        assert!(native["u"].is_u64());
+        assert!(native["f"].is_f64());
+
+        let options = ParserOptions::new().lossless_numbers(true);
+        let raw =
+            JsonValue::parse_with("[1, -1, 1.50, 1e2, 99999999999999999999]", &options).unwrap();
+        assert_eq!(
+            serde_json::to_string(&raw).unwrap(),
+            "[1,-1,1.5,100.0,99999999999999999999]"
+        );
+
+        let mut parser = crate::parse_streaming(r#"{"raw": {"b": [1, 2.5]}}"#).unwrap();
+        parser.next();
+        parser.next();
+        let raw = JsonValue::Raw(parser.next_raw_value().unwrap().unwrap().into_owned());
+        assert_eq!(serde_json::to_string(&raw).unwrap(), r#"{"b":[1,2.5]}"#);
+    }
+
+    #[test]
+    fn test_serialize_raw_verbatim() {
+        // Raw numbers and values are written exactly as `stringify` writes
+        // them: verbatim.
+        let options = ParserOptions::new().lossless_numbers(true);
+        let input =
+            "[0.10000000000000000000001, 1e400, -0, 1.50, 340282366920938463463374607431768211456]";
+        let value = JsonValue::parse_with(input, &options).unwrap();
+        let text = to_string(&value).unwrap();
+        assert_eq!(
+            text,
+            "[0.10000000000000000000001,1e400,-0,1.50,340282366920938463463374607431768211456]"
+        );
+        assert_eq!(text, value.stringify().unwrap());
+        assert_eq!(
+            to_string_pretty(&value).unwrap(),
+            value.stringify_pretty().unwrap()
+        );
+
+        let mut parser = crate::parse_streaming(r#"{"raw": {"b": [1, 2.5]}}"#).unwrap();
+        parser.next();
+        parser.next();
+        let raw = JsonValue::Raw(parser.next_raw_value().unwrap().unwrap().into_owned());
+        let object = JsonValue::Object(BTreeMap::from([("r".to_string(), raw.clone())]));
+        assert_eq!(to_string(&raw).unwrap(), r#"{"b": [1, 2.5]}"#);
+        assert_eq!(to_string(&object).unwrap(), object.stringify().unwrap());
+        assert_eq!(
+            to_string_pretty(&object).unwrap(),
+            object.stringify_pretty().unwrap()
+        );
+
+        // Inside other types, and as map keys, where only integers fit.
+        #[derive(Serialize)]
+        struct Wrapper {
+            n: JsonNumber,
+            v: Vec<JsonValue>,
+        }
+        let JsonValue::Array(numbers) = value else {
+            unreachable!()
+        };
+        let JsonValue::Number(first) = numbers[0].clone() else {
+            unreachable!()
+        };
+        let wrapper = Wrapper {
+            n: first.clone(),
+            v: vec![raw, numbers[1].clone()],
+        };
+        assert_eq!(
+            to_string(&wrapper).unwrap(),
+            r#"{"n":0.10000000000000000000001,"v":[{"b": [1, 2.5]},1e400]}"#
+        );
+        struct Keyed(JsonNumber);
+        impl Serialize for Keyed {
+            fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map([(&self.0, 1)])
+            }
+        }
+        let big = JsonNumber::Raw(RawNumber::new("123456789012345678901234567890").unwrap());
+        assert_eq!(
+            to_string(&Keyed(big)).unwrap(),
+            r#"{"123456789012345678901234567890":1}"#
+        );
+        assert!(matches!(
+            to_string(&Keyed(first)),
+            Err(SerializeError::KeyMustBeString)
+        ));
+
+        // Other serializers get the narrowest type.
+        assert_eq!(
+            serde_json::to_string(&numbers[2..]).unwrap(),
+            "[0,1.5,3.402823669209385e38]"
+        );
+    }
+
+    #[test]
+    fn test_serialize_lone_surrogates() {
+        let s = crate::Wtf8String::from_utf16(&[0x22, 0xD800, 0x61]);
+        assert_eq!(to_string(&s).unwrap(), r#""\"\ud800a""#);
+        let value = JsonValue::Array(vec![JsonValue::WtfString(s.clone())]);
+        assert_eq!(to_string(&value).unwrap(), value.stringify().unwrap());
+        assert_eq!(
+            to_string_pretty(&value).unwrap(),
+            value.stringify_pretty().unwrap()
+        );
+
+        // Keys are written the same way.
+        let map = BTreeMap::from([(s.clone(), vec![s.clone()])]);
+        assert_eq!(to_string(&map).unwrap(), r#"{"\"\ud800a":["\"\ud800a"]}"#);
+
+        // Other serializers see the string with U+FFFD in place of the
+        // surrogate.
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            "[\"\\\"\u{FFFD}a\"]"
+        );
+        let options = ParserOptions::new().lone_surrogates(LoneSurrogatePolicy::Preserve);
+        let parsed = JsonValue::parse_with(&to_string(&value).unwrap(), &options).unwrap();
+        assert_eq!(parsed, value);
+    }
+
+    #[test]
+    fn test_serialize_to_writer() {
+        let mut output = Vec::new();
+        to_writer(&mut output, &everything().shapes).unwrap();
+        assert_eq!(
+            output,
+            br#"["Empty",{"Circle":0.5},{"Point":[1,-2]},{"Rect":{"w":3,"h":4}}]"#
+        );
+
+        let mut output = Vec::new();
+        to_writer_pretty(&mut output, &[1]).unwrap();
+        assert_eq!(output, b"[\n  1\n]");
+
+        // The writer's own error is reported.
+        let mut full = [0u8; 4];
+        let err = to_writer(&mut full[..], &"too long").unwrap_err();
+        let SerializeError::Io(e) = err else {
+            panic!("expected an I/O error, got {:?}", err);
+        };
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+    }
+}
@@ -5,6 +5,7 @@
 //! This module is part of the library's internal API.
 
 use crate::value::JsonNumber;
+use crate::wtf8::Wtf8String;
 use std::borrow::Cow;
 
 /// The specific type of a `Token`.
@@ -26,24 +27,36 @@ pub enum TokenType<'a> {
     Comma,
     /// A string, e.g., `"hello"`
     String(Cow<'a, str>),
+    /// A string holding a lone surrogate, e.g., `"\uD800"`, kept by
+    /// `LoneSurrogatePolicy::Preserve`.
+    WtfString(Wtf8String),
     /// A number, e.g., `123.4`
     Number(JsonNumber),
     /// A boolean, `true` or `false`
     Boolean(bool),
     /// The `null` literal
     Null,
+    /// An unquoted identifier, e.g., `port` in `{port: 80}` (JSON5 only).
+    ///
+    /// The parser decides whether it is a key or a literal such as
+    /// `true` or `Infinity`.
+    Identifier(&'a str),
 }
 
 /// A single token produced by the `Tokenizer`.
 ///
-/// It contains the `TokenType` and its location (line and column)
-/// in the source string, which is crucial for error reporting.
+/// It contains the `TokenType` and its location (line, column and byte
+/// offset) in the source string, which is crucial for error reporting.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token<'a> {
     /// The type of the token.
     pub(crate) kind: TokenType<'a>,
     /// The 1-indexed line number where the token starts.
     pub(crate) line: usize,
-    /// The 1-indexed column number where the token starts.
+    /// The 1-indexed column number (in `char`s) where the token starts.
     pub(crate) column: usize,
+    /// The 0-indexed byte offset where the token starts.
+    pub(crate) offset: usize,
+    /// The 0-indexed byte offset just past the end of the token.
+    pub(crate) end: usize,
 }
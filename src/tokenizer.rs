@@ -4,10 +4,14 @@
 //! the raw input `&str` (as `&[u8]`) and produces a stream of `Token`s.
 //! It is *not* part of the public API.
 
-use crate::error::ParseError;
+use crate::error::{ErrorKind, ParseError};
+use crate::float;
+use crate::options::{Dialect, LoneSurrogatePolicy};
+use crate::raw::RawNumber;
 use crate::token::{Token, TokenType};
 use crate::value::JsonNumber;
-use memchr::memchr;
+use crate::wtf8::Wtf8String;
+use memchr::{memchr, memchr2, memmem};
 use std::borrow::Cow;
 use std::str;
 
@@ -63,6 +67,7 @@ static BYTE_PROPERTIES: [u8; 256] = {
 };
 
 const IS_WHITESPACE: u8 = 1;
+
 /// The internal tokenizer (lexer).
 ///
 /// It operates on raw bytes (`&[u8]`) for performance, using a lookup
@@ -73,10 +78,18 @@ pub(crate) struct Tokenizer<'a> {
     bytes: &'a [u8],
     /// The current position (index) in the `bytes` slice.
     cursor: usize,
+    /// The byte offset of `bytes[0]` within the whole input.
+    base_offset: usize,
     /// The current line number (1-indexed) for error reporting.
     line: usize,
-    /// The current column number (1-indexed) for error reporting.
+    /// The current column number (1-indexed, in `char`s) for error reporting.
     column: usize,
+    /// `true` when lexing JSON5 rather than strict JSON.
+    json5: bool,
+    /// How lone surrogates in `\u` escapes are decoded.
+    lone_surrogates: LoneSurrogatePolicy,
+    /// `true` when numbers are kept as `JsonNumber::Raw`.
+    lossless_numbers: bool,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -85,18 +98,86 @@ impl<'a> Tokenizer<'a> {
         Tokenizer {
             bytes: input.as_bytes(),
             cursor: 0,
+            base_offset: 0,
             line: 1,
             column: 1,
+            json5: false,
+            lone_surrogates: LoneSurrogatePolicy::Error,
+            lossless_numbers: false,
         }
     }
 
-    /// Creates a `ParseError` with the current line and column.
-    fn error(&self, message: String) -> ParseError {
-        ParseError {
-            message,
-            line: self.line,
-            column: self.column,
+    /// Creates a `Tokenizer` over raw bytes that continues counting from a
+    /// known offset, line and column. Used by the incremental parsers,
+    /// which lex a moving window of their internal buffer.
+    pub(crate) fn resume(bytes: &'a [u8], offset: usize, line: usize, column: usize) -> Self {
+        Tokenizer {
+            bytes,
+            cursor: 0,
+            base_offset: offset,
+            line,
+            column,
+            json5: false,
+            lone_surrogates: LoneSurrogatePolicy::Error,
+            lossless_numbers: false,
+        }
+    }
+
+    /// Sets the dialect to lex.
+    pub(crate) fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.json5 = dialect == Dialect::Json5;
+        self
+    }
+
+    /// Sets how lone surrogates in `\u` escapes are decoded.
+    pub(crate) fn with_lone_surrogates(mut self, policy: LoneSurrogatePolicy) -> Self {
+        self.lone_surrogates = policy;
+        self
+    }
+
+    /// Sets whether numbers are kept as their source text.
+    pub(crate) fn with_lossless_numbers(mut self, lossless: bool) -> Self {
+        self.lossless_numbers = lossless;
+        self
+    }
+
+    /// Returns the number of bytes consumed so far, and the current line and column.
+    pub(crate) fn position(&self) -> (usize, usize, usize) {
+        (self.cursor, self.line, self.column)
+    }
+
+    /// Returns the line, column and byte offset of the cursor.
+    pub(crate) fn location(&self) -> (usize, usize, usize) {
+        (self.line, self.column, self.base_offset + self.cursor)
+    }
+
+    /// Skips whitespace and returns the next byte without consuming it.
+    pub(crate) fn peek_byte(&mut self) -> Option<u8> {
+        if self.json5 {
+            // An unterminated comment is left in place, to be reported by `next`.
+            let _ = self.skip_json5_whitespace();
+        } else {
+            self.skip_whitespace();
         }
+        self.bytes.get(self.cursor).copied()
+    }
+
+    /// Returns `true` if `byte` can start a value in the current dialect.
+    pub(crate) fn is_value_start(&self, byte: u8) -> bool {
+        if self.json5 {
+            // Anything else is either a value or an error for the lexer to report.
+            !matches!(byte, b']' | b'}' | b':' | b',')
+        } else {
+            matches!(
+                byte,
+                b'"' | b'[' | b'{' | b'-' | b'0'..=b'9' | b't' | b'f' | b'n'
+            )
+        }
+    }
+
+    /// Creates a `ParseError` at the current position.
+    fn error(&self, kind: ErrorKind) -> ParseError {
+        ParseError::new(kind, self.line, self.column, self.base_offset + self.cursor)
     }
 
     /// The performance-critical whitespace skipping function.
@@ -128,7 +209,8 @@ impl<'a> Tokenizer<'a> {
         if byte == b'\n' {
             self.line += 1;
             self.column = 1;
-        } else {
+        } else if byte & 0xC0 != 0x80 {
+            // Columns count `char`s: UTF-8 continuation bytes don't start a new one.
             self.column += 1;
         }
         self.cursor += 1;
@@ -152,6 +234,76 @@ impl<'a> Tokenizer<'a> {
         self.bytes.get(range)
     }
 
+    /// Decodes a `\uXXXX` escape into `s`. The cursor is just past the `u`.
+    ///
+    /// A high surrogate followed by an escaped low surrogate is combined
+    /// into one character; any other surrogate is handled according to
+    /// `self.lone_surrogates`, with preserved ones recorded in `preserved`.
+    fn lex_unicode_escape(
+        &mut self,
+        s: &mut String,
+        preserved: &mut Vec<(usize, u16)>,
+    ) -> Result<(), ParseError> {
+        let code = match self.hex_at(self.cursor) {
+            Some(code) => code,
+            None if self.cursor + 4 > self.bytes.len() => {
+                return Err(self.error(ErrorKind::IncompleteUnicodeEscape))
+            }
+            None => return Err(self.error(ErrorKind::InvalidUnicodeEscape)),
+        };
+        self.advance_by(4); // Advance past the 4 hex digits
+
+        let code = match code {
+            0xD800..=0xDBFF => {
+                // Only consume the next escape if it completes the pair.
+                let low = match self.get_slice(self.cursor..self.cursor + 2) {
+                    Some(b"\\u") => self.hex_at(self.cursor + 2),
+                    _ => None,
+                };
+                match low {
+                    Some(low @ 0xDC00..=0xDFFF) => {
+                        self.advance_by(6);
+                        0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)
+                    }
+                    _ => return self.lone_surrogate(s, code, preserved),
+                }
+            }
+            0xDC00..=0xDFFF => return self.lone_surrogate(s, code, preserved),
+            _ => code,
+        };
+        let c = std::char::from_u32(code).ok_or_else(|| self.error(ErrorKind::InvalidCodePoint))?;
+        s.push(c);
+        Ok(())
+    }
+
+    /// Applies the lone surrogate policy to the escape of `code` that ends
+    /// at the cursor.
+    ///
+    /// A `str` cannot hold a surrogate, so a preserved one is pushed as
+    /// U+FFFD, and its byte index in `s` recorded in `preserved` for
+    /// `lex_string` to put it back.
+    fn lone_surrogate(
+        &self,
+        s: &mut String,
+        code: u32,
+        preserved: &mut Vec<(usize, u16)>,
+    ) -> Result<(), ParseError> {
+        match self.lone_surrogates {
+            LoneSurrogatePolicy::Error => return Err(self.error(ErrorKind::LoneSurrogate)),
+            LoneSurrogatePolicy::Replace => {}
+            LoneSurrogatePolicy::Preserve => preserved.push((s.len(), code as u16)),
+        }
+        s.push(char::REPLACEMENT_CHARACTER);
+        Ok(())
+    }
+
+    /// Returns the value of the four hex digits at `pos`, if there are four.
+    fn hex_at(&self, pos: usize) -> Option<u32> {
+        let hex = self.get_slice(pos..pos + 4)?;
+        hex.iter()
+            .try_fold(0, |code, &b| Some(code * 16 + (b as char).to_digit(16)?))
+    }
+
     /// Parses a JSON literal (`true`, `false`, `null`).
     fn lex_literal(
         &mut self,
@@ -164,14 +316,18 @@ impl<'a> Tokenizer<'a> {
             Ok(kind)
         } else {
             // This unwrap is safe because "true", "false", and "null" are valid UTF-8
-            Err(self.error(format!("Expected '{}'", str::from_utf8(expected).unwrap())))
+            Err(self.error(ErrorKind::ExpectedLiteral(
+                str::from_utf8(expected).unwrap(),
+            )))
         }
     }
 
     /// Parses a JSON string, handling escapes.
     /// Uses `memchr` for "safe SIMD" acceleration.
-    fn lex_string(&mut self) -> Result<TokenType<'a>, ParseError> {
-        self.advance_byte(); // Consume opening '"'
+    ///
+    /// `quote` is the opening quote: always `"` in JSON, `"` or `'` in JSON5.
+    fn lex_string(&mut self, quote: u8) -> Result<TokenType<'a>, ParseError> {
+        self.advance_byte(); // Consume opening quote
 
         let string_start_cursor = self.cursor;
         let mut s: String; // Will hold our final string if it has escapes.
@@ -182,7 +338,7 @@ impl<'a> Tokenizer<'a> {
         let mut total_offset = 0; // Offset from self.cursor
 
         let quote_index = loop {
-            match memchr(b'"', current_slice) {
+            match memchr(quote, current_slice) {
                 Some(i) => {
                     // We found a quote. Check if it's escaped.
                     // Count the number of preceding backslashes.
@@ -208,7 +364,7 @@ impl<'a> Tokenizer<'a> {
                         current_slice = &current_slice[i + 1..];
                     }
                 }
-                None => return Err(self.error("Unterminated string".to_string())),
+                None => return Err(self.error(ErrorKind::UnterminatedString)),
             }
         };
 
@@ -218,79 +374,58 @@ impl<'a> Tokenizer<'a> {
         // 3. Scan *that slice* for an escape (`\`).
         if memchr(b'\\', content_slice).is_some() {
             // --- "Cold" path (contains escapes) ---
-            // We must build the string byte-by-byte.
+            // We must build the string, one run of unescaped text at a time.
             s = String::with_capacity(content_slice.len());
+            let mut preserved = Vec::new();
 
             // Re-scan from the start, but this time building the string.
             self.cursor = string_start_cursor;
+            let end = string_start_cursor + quote_index;
 
-            while self.cursor < string_start_cursor + quote_index {
-                let byte = self.advance_byte();
-                // Check for unescaped control characters.
-                if byte < 0x20 {
-                    return Err(self.error("Unescaped control character in string".to_string()));
+            loop {
+                // Copy the text up to the next escape (or the closing quote).
+                let run_end =
+                    memchr(b'\\', &self.bytes[self.cursor..end]).map_or(end, |i| self.cursor + i);
+                s.push_str(self.lex_string_run(run_end)?);
+                if self.cursor >= end {
+                    break;
                 }
 
-                if byte == b'\\' {
-                    // Handle escape sequence
-                    let escaped_char = match self.advance_byte() {
-                        b'"' | b'\\' | b'/' => self.bytes[self.cursor - 1],
-                        b'b' => b'\x08', // Backspace
-                        b'f' => b'\x0C', // Form feed
-                        b'n' => b'\n',   // Newline
-                        b'r' => b'\r',   // Carriage return
-                        b't' => b'\t',   // Tab
-                        b'u' => {
-                            // Handle \uXXXX Unicode escape
-                            let s_slice =
-                                self.get_slice(self.cursor..self.cursor + 4)
-                                    .ok_or_else(|| {
-                                        self.error("Incomplete Unicode escape".to_string())
-                                    })?;
-
-                            let hex_str = str::from_utf8(s_slice).map_err(|_| {
-                                self.error("Non-UTF8 in Unicode escape".to_string())
-                            })?;
-
-                            let code = u32::from_str_radix(hex_str, 16).map_err(|_| {
-                                self.error("Non-hex char in Unicode escape".to_string())
-                            })?;
-
-                            self.advance_by(4); // Advance past the 4 hex digits
-
-                            let c = std::char::from_u32(code).ok_or_else(|| {
-                                self.error("Invalid Unicode code point".to_string())
-                            })?;
-                            s.push(c);
-                            continue; // Skip the char push at the end
-                        }
-                        _ => return Err(self.error("Invalid escape sequence".to_string())),
-                    };
-                    s.push(escaped_char as char);
-                } else {
-                    // Not an escape, just a regular char
-                    s.push(byte as char);
-                }
+                self.advance_byte(); // Consume the backslash
+                let escaped_char = match self.advance_byte() {
+                    b'"' => '"',
+                    b'\\' => '\\',
+                    b'/' => '/',
+                    b'b' => '\x08', // Backspace
+                    b'f' => '\x0C', // Form feed
+                    b'n' => '\n',   // Newline
+                    b'r' => '\r',   // Carriage return
+                    b't' => '\t',   // Tab
+                    b'u' => {
+                        // Handle \uXXXX Unicode escape
+                        self.lex_unicode_escape(&mut s, &mut preserved)?;
+                        continue;
+                    }
+                    _ if self.json5 => match self.lex_json5_escape()? {
+                        Some(c) => c,
+                        None => continue, // A line continuation adds nothing
+                    },
+                    _ => return Err(self.error(ErrorKind::InvalidEscape)),
+                };
+                s.push(escaped_char);
             }
             // After the loop, we're at the closing quote.
             self.advance_byte(); // Consume the quote
+            if !preserved.is_empty() {
+                return Ok(TokenType::WtfString(Wtf8String::from_replaced(
+                    &s, &preserved,
+                )));
+            }
             Ok(TokenType::String(Cow::Owned(s))) // Return Cow::Owned for the escaped string.
         } else {
             // --- "Hot" path (no escapes) ---
             // This is the fastest path.
-            // We *must* still check for unescaped control chars
-            // and update our line/column counters.
-            for _ in 0..content_slice.len() {
-                let byte = self.advance_byte(); // Consume *first*
-                if byte < 0x20 {
-                    // This reports the error at the correct line/col.
-                    return Err(self.error("Unescaped control character in string".to_string()));
-                }
-            }
-
-            // Now we know the slice is valid.
-            let s_str = str::from_utf8(content_slice)
-                .map_err(|_| self.error("Invalid UTF-8 in string".to_string()))?;
+            let s_str = self.lex_string_run(self.cursor + quote_index)?;
 
             // We're at the closing quote. Consume it.
             self.advance_byte();
@@ -298,89 +433,557 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    /// Parses a JSON number, handling i64, u64, and f64.
-    fn lex_number(&mut self) -> Result<TokenType<'a>, ParseError> {
+    /// Consumes unescaped string content up to `end`, which must not span
+    /// a backslash or the closing quote, and returns it as a `str`.
+    ///
+    /// We *must* still check for unescaped control chars and update our
+    /// line/column counters.
+    fn lex_string_run(&mut self, end: usize) -> Result<&'a str, ParseError> {
+        let bytes = self.bytes;
         let start = self.cursor;
+        while self.cursor < end {
+            let byte = self.advance_byte(); // Consume *first*
+            if byte < 0x20 {
+                // This reports the error at the correct line/col.
+                return Err(self.error(ErrorKind::ControlCharacterInString));
+            }
+        }
+        // A run is split at ASCII bytes only, so it is valid UTF-8 on its own
+        // if the input is.
+        str::from_utf8(&bytes[start..end]).map_err(|_| self.error(ErrorKind::InvalidUtf8))
+    }
 
-        // Greedily consume all valid number characters.
-        while let Some(&byte) = self.bytes.get(self.cursor) {
-            match byte {
-                // Use the LUT for the most common case
-                b if BYTE_PROPERTIES[b as usize] == D => {
+    /// Gets the next token of a value being skipped, together with the
+    /// length its string would have once decoded (0 for other tokens).
+    ///
+    /// Strings are not unescaped and numbers are not converted: both come
+    /// back as empty placeholders. The input is still checked exactly as
+    /// by `next`: strings must be terminated with valid escapes and no
+    /// control characters, numbers must follow the JSON grammar, and
+    /// literals must be spelled correctly. Where the tokens may go is left
+    /// to the parser's grammar.
+    pub(crate) fn next_skipped(&mut self) -> Option<Result<(Token<'a>, usize), ParseError>> {
+        if self.json5 {
+            // JSON5 has too many token forms to scan without lexing them.
+            return self.next().map(|token| {
+                token.map(|token| {
+                    let len = match &token.kind {
+                        TokenType::String(s) => s.len(),
+                        TokenType::WtfString(s) => s.len(),
+                        _ => 0,
+                    };
+                    (token, len)
+                })
+            });
+        }
+
+        self.skip_whitespace();
+        let byte = *self.bytes.get(self.cursor)?;
+        let (line, column, offset) = self.location();
+        let mut len = 0;
+        let kind = match byte {
+            b'"' => self.skip_string().map(|decoded| {
+                len = decoded;
+                TokenType::String(Cow::Borrowed(""))
+            }),
+            b'-' | b'0'..=b'9' => self
+                .scan_number()
+                .map(|_| TokenType::Number(JsonNumber::I64(0))),
+            // Structurals and literals are cheap to lex as usual.
+            _ => return self.next().map(|token| token.map(|token| (token, 0))),
+        };
+        Some(kind.map(|kind| {
+            let token = Token {
+                kind,
+                line,
+                column,
+                offset,
+                end: self.base_offset + self.cursor,
+            };
+            (token, len)
+        }))
+    }
+
+    /// Skips over a string without decoding it, returning the length the
+    /// decoded string would have. Used by `next_skipped`.
+    fn skip_string(&mut self) -> Result<usize, ParseError> {
+        let start = (self.cursor, self.line, self.column);
+        self.advance_byte(); // Consume opening '"'
+        let mut len = 0;
+
+        loop {
+            let rest = &self.bytes[self.cursor..];
+            let special = memchr2(b'"', b'\\', rest)
+                .ok_or_else(|| self.error(ErrorKind::UnterminatedString))?;
+
+            // Plain bytes: only control characters are invalid.
+            for _ in 0..special {
+                if self.advance_byte() < 0x20 {
+                    return Err(self.error(ErrorKind::ControlCharacterInString));
+                }
+            }
+            len += special;
+
+            if self.advance_byte() == b'"' {
+                return Ok(len);
+            }
+
+            // A backslash: check the escape without decoding it.
+            match self.bytes.get(self.cursor) {
+                Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {
                     self.advance_byte();
+                    len += 1;
                 }
-                // Handle other valid number parts
-                b'.' | b'e' | b'E' | b'+' => {
+                Some(b'u') => {
                     self.advance_byte();
+                    if self.get_slice(self.cursor..self.cursor + 4).is_none() {
+                        return Err(self.error(ErrorKind::IncompleteUnicodeEscape));
+                    }
+                    let code = self
+                        .hex_at(self.cursor)
+                        .ok_or_else(|| self.error(ErrorKind::InvalidUnicodeEscape))?;
+                    match char::from_u32(code) {
+                        Some(c) => len += c.len_utf8(),
+                        None => {
+                            // A surrogate: whether it is paired, and how a
+                            // lone one decodes, depends on what follows, so
+                            // decode the string exactly as parsing would.
+                            (self.cursor, self.line, self.column) = start;
+                            return match self.lex_string(b'"')? {
+                                TokenType::String(s) => Ok(s.len()),
+                                TokenType::WtfString(s) => Ok(s.len()),
+                                _ => unreachable!(), // lex_string only returns strings
+                            };
+                        }
+                    }
+                    self.advance_by(4);
+                }
+                Some(_) => {
+                    self.advance_byte();
+                    return Err(self.error(ErrorKind::InvalidEscape));
                 }
-                _ => break, // Not a number char, stop
+                None => return Err(self.error(ErrorKind::UnterminatedString)),
             }
         }
+    }
 
-        let end = self.cursor;
-        if start == end {
-            // This should be unreachable if called from `next`
-            return Err(self.error("Expected a number".to_string()));
+    /// Decodes an escape that only JSON5 allows. The escaped byte has
+    /// just been consumed. Returns `None` for a line continuation.
+    fn lex_json5_escape(&mut self) -> Result<Option<char>, ParseError> {
+        match self.bytes[self.cursor - 1] {
+            b'\n' => Ok(None),
+            b'\r' => {
+                if self.bytes.get(self.cursor) == Some(&b'\n') {
+                    self.advance_byte();
+                }
+                Ok(None)
+            }
+            // U+2028 and U+2029 are line terminators too.
+            0xE2 if matches!(
+                self.get_slice(self.cursor..self.cursor + 2),
+                Some([0x80, 0xA8 | 0xA9])
+            ) =>
+            {
+                self.advance_by(2);
+                Ok(None)
+            }
+            b'v' => Ok(Some('\x0B')),
+            b'0' if !self.bytes.get(self.cursor).is_some_and(u8::is_ascii_digit) => Ok(Some('\0')),
+            b'x' => {
+                let hex = self
+                    .get_slice(self.cursor..self.cursor + 2)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                    .ok_or_else(|| self.error(ErrorKind::InvalidEscape))?;
+                // This is safe: the slice only contains ASCII hex digits.
+                let code = u8::from_str_radix(str::from_utf8(hex).unwrap(), 16).unwrap();
+                self.advance_by(2);
+                Ok(Some(char::from(code)))
+            }
+            b'0'..=b'9' => Err(self.error(ErrorKind::InvalidEscape)),
+            // Any other character stands for itself, e.g. `\'` or `\é`.
+            byte if byte.is_ascii() => Ok(Some(char::from(byte))),
+            byte => {
+                let len = match byte {
+                    0xF0..=0xFF => 4,
+                    0xE0..=0xEF => 3,
+                    _ => 2,
+                };
+                let c = self
+                    .get_slice(self.cursor - 1..self.cursor - 1 + len)
+                    .and_then(|bytes| str::from_utf8(bytes).ok())
+                    .and_then(|s| s.chars().next())
+                    .ok_or_else(|| self.error(ErrorKind::InvalidUtf8))?;
+                self.advance_by(len - 1);
+                Ok(Some(c))
+            }
         }
+    }
 
-        // This is 100% safe. The slice only contains ASCII number chars,
-        // so this will never fail, but we use the safe version to
-        // uphold the #![forbid(unsafe_code)] guarantee.
-        let num_str = str::from_utf8(&self.bytes[start..end])
-            .expect("Internal error: non-UTF8 in number slice");
-
-        // --- RFC 8259 Validation ---
-        if num_str.starts_with('0') && num_str.len() > 1 {
-            if let Some(second_char) = num_str.chars().nth(1) {
-                if second_char.is_ascii_digit() {
-                    return Err(self.error("Invalid number: leading zeros not allowed".to_string()));
+    /// Skips whitespace and comments, as allowed by JSON5.
+    ///
+    /// An unterminated `/*` comment is reported at its start, and the
+    /// cursor is left there.
+    fn skip_json5_whitespace(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_whitespace();
+            match self.bytes.get(self.cursor) {
+                Some(b'/') => match self.bytes.get(self.cursor + 1) {
+                    Some(b'/') => {
+                        let rest = &self.bytes[self.cursor..];
+                        let len = memchr(b'\n', rest).unwrap_or(rest.len());
+                        self.advance_by(len);
+                    }
+                    Some(b'*') => match memmem::find(&self.bytes[self.cursor + 2..], b"*/") {
+                        Some(len) => self.advance_by(len + 4),
+                        None => return Err(self.error(ErrorKind::UnterminatedComment)),
+                    },
+                    _ => return Ok(()),
+                },
+                Some(b'\x0B' | b'\x0C') => {
+                    self.advance_byte();
                 }
+                Some(&byte) if byte >= 0x80 => match self.peek_char() {
+                    Some(c) if is_json5_space(c) => self.advance_by(c.len_utf8()),
+                    _ => return Ok(()),
+                },
+                _ => return Ok(()),
             }
         }
-        if num_str.ends_with('.') {
-            return Err(self.error("Invalid number: cannot end with a decimal point".to_string()));
+    }
+
+    /// Decodes the `char` at the cursor, if the input holds a complete one.
+    fn peek_char(&self) -> Option<char> {
+        let end = (self.cursor + 4).min(self.bytes.len());
+        let bytes = &self.bytes[self.cursor..end];
+        let valid = match str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(e) => str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
+        };
+        valid.chars().next()
+    }
+
+    /// Parses an unquoted JSON5 identifier, such as a key or `Infinity`.
+    fn lex_identifier(&mut self) -> Result<TokenType<'a>, ParseError> {
+        let start = self.cursor;
+        match self.peek_char() {
+            Some(c) if is_identifier_start(c) => {}
+            Some(c) => return Err(self.error(ErrorKind::UnexpectedCharacter(c))),
+            None => return Err(self.error(ErrorKind::InvalidUtf8)),
         }
-        if let Some(e_pos) = num_str.find(['e', 'E']) {
-            if e_pos > 0 && num_str.chars().nth(e_pos - 1) == Some('.') {
-                return Err(self.error(format!("Invalid number '{}'", num_str)));
+        while let Some(c) = self.peek_char() {
+            if !is_identifier_part(c) {
+                break;
             }
+            self.advance_by(c.len_utf8());
         }
-        if let Some(last_char) = num_str.chars().last() {
-            if (last_char == 'e' || last_char == 'E' || last_char == '+' || last_char == '-')
-                && num_str.len() > start - self.cursor
+        // This is safe: the slice is made of whole, decoded chars.
+        let name = str::from_utf8(&self.bytes[start..self.cursor]).unwrap();
+        Ok(TokenType::Identifier(name))
+    }
+
+    /// Parses a JSON5 number: a JSON number, optionally with a `+` sign,
+    /// a leading or trailing decimal point, in hexadecimal, or one of
+    /// `Infinity` and `NaN`.
+    fn lex_json5_number(&mut self) -> Result<TokenType<'a>, ParseError> {
+        let start = self.cursor;
+        let negative = self.bytes[self.cursor] == b'-';
+        if matches!(self.bytes[self.cursor], b'-' | b'+') {
+            self.advance_byte();
+        }
+
+        for (name, value) in [(&b"Infinity"[..], f64::INFINITY), (&b"NaN"[..], f64::NAN)] {
+            if self.get_slice(self.cursor..self.cursor + name.len()) == Some(name) {
+                self.advance_by(name.len());
+                let value = if negative { -value } else { value };
+                return Ok(TokenType::Number(JsonNumber::F64(value)));
+            }
+        }
+
+        if self.bytes.get(self.cursor) == Some(&b'0')
+            && matches!(self.bytes.get(self.cursor + 1), Some(b'x' | b'X'))
+        {
+            self.advance_by(2);
+            let digits_start = self.cursor;
+            while self
+                .bytes
+                .get(self.cursor)
+                .is_some_and(u8::is_ascii_hexdigit)
             {
-                return Err(self.error(format!("Invalid number '{}'", num_str)));
+                self.advance_byte();
+            }
+            // This is safe: the slices only contain ASCII.
+            let text = str::from_utf8(&self.bytes[start..self.cursor]).unwrap();
+            let digits = str::from_utf8(&self.bytes[digits_start..self.cursor]).unwrap();
+            if digits.is_empty() {
+                return Err(self.error(ErrorKind::InvalidNumber(text.to_string())));
+            }
+            let out_of_range = || self.error(ErrorKind::IntegerOutOfRange(text.to_string()));
+            let magnitude = u128::from_str_radix(digits, 16).map_err(|_| out_of_range())?;
+            if self.lossless_numbers {
+                // Hexadecimal is not JSON; keep the value as decimal text.
+                let sign = if negative { "-" } else { "" };
+                let decimal = format!("{}{}", sign, magnitude);
+                return Ok(TokenType::Number(JsonNumber::Raw(RawNumber::from_source(
+                    &decimal,
+                ))));
             }
+            let magnitude = u64::try_from(magnitude).map_err(|_| out_of_range())?;
+            let number = if !negative {
+                match i64::try_from(magnitude) {
+                    Ok(n) => JsonNumber::I64(n),
+                    Err(_) => JsonNumber::U64(magnitude),
+                }
+            } else {
+                match 0i64.checked_sub_unsigned(magnitude) {
+                    Some(n) => JsonNumber::I64(n),
+                    None => return Err(out_of_range()),
+                }
+            };
+            return Ok(TokenType::Number(number));
         }
-        // --- End validation ---
 
-        // Try parsing as integer first, then fall back to float.
-        if num_str.contains(['.', 'e', 'E']) {
-            // It's a float
-            match num_str.parse::<f64>() {
-                Ok(num) => Ok(TokenType::Number(JsonNumber::F64(num))),
-                Err(_) => Err(self.error(format!("Invalid number '{}'", num_str))),
+        while let Some(&byte) = self.bytes.get(self.cursor) {
+            if !matches!(byte, b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-') {
+                break;
             }
-        } else {
-            // Try i64, then u64
-            match num_str.parse::<i64>() {
-                Ok(num) => Ok(TokenType::Number(JsonNumber::I64(num))),
-                Err(_) => match num_str.parse::<u64>() {
-                    Ok(num) => Ok(TokenType::Number(JsonNumber::U64(num))),
-                    Err(_) => Err(self.error(format!("Invalid integer '{}'", num_str))),
-                },
+            self.advance_byte();
+        }
+        // This is safe: the slice only contains ASCII number chars.
+        let text = str::from_utf8(&self.bytes[start..self.cursor]).unwrap();
+        let unsigned = text.trim_start_matches(['-', '+']);
+        if !is_json5_decimal(unsigned) || unsigned.len() + 1 < text.len() {
+            return Err(self.error(ErrorKind::InvalidNumber(text.to_string())));
+        }
+        if unsigned.starts_with('0') && unsigned.as_bytes().get(1).is_some_and(u8::is_ascii_digit) {
+            return Err(self.error(ErrorKind::LeadingZero));
+        }
+
+        // Spelled as JSON, the number converts exactly like one.
+        let json = json5_decimal_to_json(negative, unsigned);
+        let decimal = Tokenizer::new(&json)
+            .scan_number()
+            .expect("Internal error: a JSON5 decimal is a JSON number once respelled");
+        self.convert_number(decimal, &json, text)
+            .map(TokenType::Number)
+    }
+
+    /// Parses a JSON number, handling i64, u64, and f64.
+    fn lex_number(&mut self) -> Result<TokenType<'a>, ParseError> {
+        let start = self.cursor;
+        let decimal = self.scan_number()?;
+        let text = self.number_text(start);
+        self.convert_number(decimal, text, text)
+            .map(TokenType::Number)
+    }
+
+    /// Converts the number `scan_number` read from `json` into a
+    /// `JsonNumber`. `source` is the number as written, for errors.
+    fn convert_number(
+        &self,
+        decimal: Decimal,
+        json: &str,
+        source: &str,
+    ) -> Result<JsonNumber, ParseError> {
+        if self.lossless_numbers {
+            return Ok(JsonNumber::Raw(RawNumber::from_source(json)));
+        }
+        let out_of_range = || self.error(ErrorKind::IntegerOutOfRange(source.to_string()));
+
+        if decimal.integer && decimal.exponent == 0 && !decimal.truncated {
+            // The digits fit in a `u64`; build the integer directly.
+            return Ok(if !decimal.negative {
+                match i64::try_from(decimal.mantissa) {
+                    Ok(num) => JsonNumber::I64(num),
+                    Err(_) => JsonNumber::U64(decimal.mantissa),
+                }
+            } else {
+                match 0i64.checked_sub_unsigned(decimal.mantissa) {
+                    Some(num) => JsonNumber::I64(num),
+                    None => return Err(out_of_range()),
+                }
+            });
+        }
+
+        if decimal.integer {
+            // More than 19 significant digits: only a `u64` can still hold it.
+            return json
+                .parse::<u64>()
+                .map(JsonNumber::U64)
+                .map_err(|_| out_of_range());
+        }
+
+        // It's a float. The rare numbers the fast conversion cannot decide
+        // take the exact slow path over the whole digit string.
+        let num = float::to_f64(
+            decimal.negative,
+            decimal.mantissa,
+            decimal.exponent,
+            decimal.truncated,
+        )
+        .unwrap_or_else(|| float::slow_path(json));
+        Ok(JsonNumber::F64(num))
+    }
+
+    /// Returns the source text of the number that started at `start`.
+    fn number_text(&self, start: usize) -> &'a str {
+        // This is 100% safe. `scan_number` only accepts ASCII number chars,
+        // so this will never fail, but we use the safe version to
+        // uphold the #![forbid(unsafe_code)] guarantee.
+        str::from_utf8(&self.bytes[start..self.cursor])
+            .expect("Internal error: non-UTF8 in number slice")
+    }
+
+    /// Consumes a number that follows the RFC 8259 grammar,
+    /// `[ "-" ] int [ frac ] [ exp ]`, one byte at a time.
+    ///
+    /// The digits are accumulated into a `Decimal` on the way, so that
+    /// converting the number does not need to scan it again. A malformed
+    /// number is reported at the offending byte, or at the end of input,
+    /// with the cursor left there.
+    fn scan_number(&mut self) -> Result<Decimal, ParseError> {
+        use NumberState::*;
+
+        let mut decimal = Decimal::default();
+        if self.bytes.get(self.cursor) == Some(&b'-') {
+            decimal.negative = true;
+            self.advance_byte();
+        }
+        let mut significant = 0;
+        let mut explicit_exponent = 0i64;
+        let mut negative_exponent = false;
+        let mut state = Start;
+        loop {
+            let byte = self.bytes.get(self.cursor).copied();
+            state = match (state, byte) {
+                (Start, Some(b'0')) => Zero,
+                (Start, Some(b'1'..=b'9')) => Int,
+                (Start, _) => return Err(self.error(ErrorKind::ExpectedIntegerDigit)),
+                (Zero, Some(b'0'..=b'9')) => return Err(self.error(ErrorKind::LeadingZero)),
+                (Int, Some(b'0'..=b'9')) => Int,
+                (Zero | Int, Some(b'.')) => Dot,
+                (Dot | Frac, Some(b'0'..=b'9')) => Frac,
+                (Dot, _) => return Err(self.error(ErrorKind::TrailingDecimalPoint)),
+                (Zero | Int | Frac, Some(b'e' | b'E')) => Exp,
+                (Exp, Some(b'+' | b'-')) => ExpSign,
+                (Exp | ExpSign | ExpDigits, Some(b'0'..=b'9')) => ExpDigits,
+                (Exp | ExpSign, _) => return Err(self.error(ErrorKind::ExpectedExponentDigit)),
+                // The number is complete. Another number character means
+                // it was malformed, as in `1.2.3` or `1-2`.
+                (_, Some(b @ (b'.' | b'e' | b'E' | b'+' | b'-'))) => {
+                    return Err(self.error(ErrorKind::UnexpectedCharacterInNumber(b as char)))
+                }
+                (Zero | Int, _) => {
+                    decimal.integer = true;
+                    return Ok(decimal);
+                }
+                (Frac | ExpDigits, _) => {
+                    let explicit_exponent = if negative_exponent {
+                        -explicit_exponent
+                    } else {
+                        explicit_exponent
+                    };
+                    decimal.exponent = decimal.exponent.saturating_add(explicit_exponent);
+                    return Ok(decimal);
+                }
+            };
+
+            if let Some(b @ b'0'..=b'9') = byte {
+                let digit = u64::from(b - b'0');
+                match state {
+                    Zero | Int | Frac => {
+                        let fraction = state == Frac;
+                        if decimal.mantissa == 0 && digit == 0 {
+                            // A leading zero is not significant.
+                            if fraction {
+                                decimal.exponent -= 1;
+                            }
+                        } else if significant < MAX_SIGNIFICANT_DIGITS {
+                            decimal.mantissa = decimal.mantissa * 10 + digit;
+                            significant += 1;
+                            if fraction {
+                                decimal.exponent -= 1;
+                            }
+                        } else {
+                            // The digit no longer fits; remember its weight.
+                            decimal.truncated |= digit != 0;
+                            if !fraction {
+                                decimal.exponent += 1;
+                            }
+                        }
+                    }
+                    // Anything this large overflows or underflows anyway.
+                    _ => explicit_exponent = (explicit_exponent * 10 + digit as i64).min(1 << 32),
+                }
+            } else if byte == Some(b'-') && state == ExpSign {
+                negative_exponent = true;
             }
+            self.advance_byte();
         }
     }
 }
 
+/// The most significant digits a `u64` can always hold.
+const MAX_SIGNIFICANT_DIGITS: u32 = 19;
+
+/// A number split into the parts `scan_number` found: `mantissa` times
+/// ten to the power of `exponent`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Decimal {
+    /// Whether the number starts with `-`.
+    negative: bool,
+    /// The first 19 significant digits.
+    mantissa: u64,
+    /// The power of ten that scales `mantissa`.
+    exponent: i64,
+    /// Whether non-zero digits after the first 19 were dropped.
+    truncated: bool,
+    /// Whether the number has no fraction or exponent.
+    integer: bool,
+}
+
+/// Returns `true` if `text` is exactly one number in the RFC 8259 grammar.
+pub(crate) fn is_json_number(text: &str) -> bool {
+    let mut tokenizer = Tokenizer::new(text);
+    matches!(text.as_bytes().first(), Some(b'-' | b'0'..=b'9'))
+        && tokenizer.scan_number().is_ok()
+        && tokenizer.cursor == text.len()
+}
+
+/// The states of `Tokenizer::scan_number`, named after the part of the
+/// number that was just read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberState {
+    /// The optional `-`; the integer part must follow.
+    Start,
+    /// A leading `0`, which must be the whole integer part.
+    Zero,
+    /// A digit of the integer part.
+    Int,
+    /// The decimal point; a digit must follow.
+    Dot,
+    /// A digit of the fraction.
+    Frac,
+    /// The `e` or `E`; a sign or digit must follow.
+    Exp,
+    /// The exponent's sign; a digit must follow.
+    ExpSign,
+    /// A digit of the exponent.
+    ExpDigits,
+}
+
 impl<'a> Iterator for Tokenizer<'a> {
     type Item = Result<Token<'a>, ParseError>;
 
     /// Gets the next `Token` from the input stream.
     fn next(&mut self) -> Option<Self::Item> {
         // 1. Skip all insignificant whitespace. This is the first hot path.
-        self.skip_whitespace();
+        if self.json5 {
+            if let Err(e) = self.skip_json5_whitespace() {
+                return Some(Err(e));
+            }
+        } else {
+            self.skip_whitespace();
+        }
 
         // 2. Peek at the next byte to see what to do.
         let byte = match self.bytes.get(self.cursor) {
@@ -389,10 +992,18 @@ impl<'a> Iterator for Tokenizer<'a> {
         };
 
         // 3. Record position *after* skipping whitespace.
-        let (start_line, start_column) = (self.line, self.column);
+        let (start_line, start_column, start_offset) =
+            (self.line, self.column, self.base_offset + self.cursor);
 
         // 4. Use our blazing-fast LUT to decide which lexer to call.
         let token_kind_result = match BYTE_PROPERTIES[byte as usize] {
+            // JSON5 adds strings, numbers and identifiers that start with
+            // bytes JSON rejects, and turns `true` & co. into identifiers.
+            _ if self.json5 && BYTE_PROPERTIES[byte as usize] != S => match byte {
+                b'"' | b'\'' => self.lex_string(byte),
+                b'-' | b'+' | b'.' | b'0'..=b'9' => self.lex_json5_number(),
+                _ => self.lex_identifier(),
+            },
             S => {
                 // Structural character (e.g., `{`, `[`, `,`)
                 self.advance_byte();
@@ -415,27 +1026,107 @@ impl<'a> Iterator for Tokenizer<'a> {
                     _ => unreachable!(), // LUT guarantees this
                 }
             }
-            D => self.lex_number(), // Digit or '-' (start of a number)
-            Q => self.lex_string(), // Quote (start of a string)
+            D => self.lex_number(),     // Digit or '-' (start of a number)
+            Q => self.lex_string(b'"'), // Quote (start of a string)
             _ => {
                 // All other bytes (0 or 1) are invalid in this context.
                 // Whitespace (1) should have been skipped.
                 // 0 means an invalid character.
-                Err(self.error(format!("Unexpected character '{}'", byte as char)))
+                Err(self.error(ErrorKind::UnexpectedCharacter(byte as char)))
             }
         };
 
         // 5. Wrap the result in a `Token` struct.
+        let end = self.base_offset + self.cursor;
         let token_result = token_kind_result.map(|kind| Token {
             kind,
             line: start_line,
             column: start_column,
+            offset: start_offset,
+            end,
         });
 
         Some(token_result)
     }
 }
 
+/// Returns `true` for the whitespace JSON5 allows beyond JSON's four
+/// (`\v` and `\f` are handled by the caller).
+fn is_json5_space(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00A0}' | '\u{1680}' | '\u{2000}'
+            ..='\u{200A}'
+                | '\u{2028}'
+                | '\u{2029}'
+                | '\u{202F}'
+                | '\u{205F}'
+                | '\u{3000}'
+                | '\u{FEFF}'
+    )
+}
+
+/// Returns `true` if `c` can start a JSON5 identifier.
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$' || c == '_'
+}
+
+/// Returns `true` if `c` can continue a JSON5 identifier.
+fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_alphanumeric() || c == '\u{200C}' || c == '\u{200D}'
+}
+
+/// Checks the shape of an unsigned JSON5 decimal number: digits with an
+/// optional decimal point (on either side of at least one digit) and an
+/// optional exponent.
+fn is_json5_decimal(text: &str) -> bool {
+    let (mantissa, exponent) = split_exponent(text);
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let mantissa_ok = all_digits(int) && all_digits(frac) && !(int.is_empty() && frac.is_empty());
+    let exponent_ok = match exponent {
+        Some(exp) => {
+            let digits = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+            !digits.is_empty() && all_digits(digits)
+        }
+        None => true,
+    };
+    mantissa_ok && exponent_ok
+}
+
+/// Respells an unsigned JSON5 decimal, checked by `is_json5_decimal`, as
+/// the JSON number of the same value: `.5` becomes `0.5` and `5.` becomes
+/// `5.0`. A number that already is JSON is returned unchanged.
+fn json5_decimal_to_json(negative: bool, unsigned: &str) -> String {
+    let (mantissa, exponent) = split_exponent(unsigned);
+    let mut json = String::with_capacity(unsigned.len() + 2);
+    if negative {
+        json.push('-');
+    }
+    match mantissa.split_once('.') {
+        Some((int, frac)) => {
+            json.push_str(if int.is_empty() { "0" } else { int });
+            json.push('.');
+            json.push_str(if frac.is_empty() { "0" } else { frac });
+        }
+        None => json.push_str(mantissa),
+    }
+    if let Some(exponent) = exponent {
+        // Keep the `e` or `E`, so that JSON text is left unchanged.
+        json.push_str(&unsigned[mantissa.len()..mantissa.len() + 1]);
+        json.push_str(exponent);
+    }
+    json
+}
+
+/// Splits a number into its mantissa and the digits after `e` or `E`.
+fn split_exponent(text: &str) -> (&str, Option<&str>) {
+    match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], Some(&text[i + 1..])),
+        None => (text, None),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -446,6 +1137,14 @@ mod tests {
         tokenizer.map(|res| res.map(|token| token.kind)).collect()
     }
 
+    /// Skips every token of `input`, returning the decoded string lengths.
+    fn skip_tokens(input: &str) -> Result<Vec<usize>, ParseError> {
+        let mut tokenizer = Tokenizer::new(input);
+        std::iter::from_fn(|| tokenizer.next_skipped())
+            .map(|res| res.map(|(_, len)| len))
+            .collect()
+    }
+
     #[test]
     fn test_tokenizer_structurals() {
         let input = "{}[]:,";
@@ -485,6 +1184,67 @@ mod tests {
         assert_eq!(collect_token_types(input).unwrap(), expected);
     }
 
+    #[test]
+    fn test_tokenizer_numbers_match_std() {
+        let floats = [
+            "0.0",
+            "-0.0",
+            "0e0",
+            "-0e-5",
+            "0.1",
+            "0.30000000000000004",
+            "1.7976931348623157e308",
+            "1.7976931348623159e308",
+            "1e309",
+            "-1e400",
+            "2.2250738585072011e-308",
+            "2.2250738585072014e-308",
+            "4.9e-324",
+            "2.4703282292062327e-324",
+            "2.4703282292062328e-324",
+            "1e-400",
+            "9007199254740993.0",
+            "9007199254740993.00000000000000000001",
+            "0.000000000000000000000000000000000000001",
+            "123456789012345678901234567890.5e-10",
+            "1e99999999999999999999",
+            "1e-99999999999999999999",
+            "100000000000000000000000e-23",
+        ];
+        for text in floats {
+            let Ok(TokenType::Number(JsonNumber::F64(num))) = Tokenizer::new(text).lex_number()
+            else {
+                panic!("{} did not lex as a float", text);
+            };
+            let expected: f64 = text.parse().unwrap();
+            assert_eq!(num.to_bits(), expected.to_bits(), "{}", text);
+        }
+
+        let integers = [
+            ("0", JsonNumber::I64(0)),
+            ("-0", JsonNumber::I64(0)),
+            ("9223372036854775807", JsonNumber::I64(i64::MAX)),
+            ("-9223372036854775808", JsonNumber::I64(i64::MIN)),
+            (
+                "10000000000000000000",
+                JsonNumber::U64(10_000_000_000_000_000_000),
+            ),
+            ("18446744073709551615", JsonNumber::U64(u64::MAX)),
+        ];
+        for (text, expected) in integers {
+            assert_eq!(
+                Tokenizer::new(text).lex_number(),
+                Ok(TokenType::Number(expected)),
+                "{}",
+                text
+            );
+        }
+        for text in ["-9223372036854775809", "18446744073709551616"] {
+            let err = Tokenizer::new(text).lex_number().unwrap_err();
+            assert_eq!(err.kind, ErrorKind::IntegerOutOfRange(text.to_string()));
+        }
+    }
+
     #[test]
     fn test_tokenizer_strings() {
         let input = r#" "hello" "a\nb" "\u1234" "\"" "#;
@@ -551,6 +1311,84 @@ mod tests {
             err.message,
             "Invalid number: cannot end with a decimal point"
         );
+
+        // Every malformed number is reported at the offending byte, or at
+        // the end of input. (input, kind, offset)
+        let cases = [
+            ("-", ErrorKind::ExpectedIntegerDigit, 1),
+            ("--1", ErrorKind::ExpectedIntegerDigit, 1),
+            ("-.5", ErrorKind::ExpectedIntegerDigit, 1),
+            ("-a", ErrorKind::ExpectedIntegerDigit, 1),
+            ("-01", ErrorKind::LeadingZero, 2),
+            ("00", ErrorKind::LeadingZero, 1),
+            ("2.e3", ErrorKind::TrailingDecimalPoint, 2),
+            ("1.]", ErrorKind::TrailingDecimalPoint, 2),
+            ("1e", ErrorKind::ExpectedExponentDigit, 2),
+            ("0E", ErrorKind::ExpectedExponentDigit, 2),
+            ("1e+", ErrorKind::ExpectedExponentDigit, 3),
+            ("1.0e-x", ErrorKind::ExpectedExponentDigit, 5),
+            ("1eE2", ErrorKind::ExpectedExponentDigit, 2),
+            ("1.2.3", ErrorKind::UnexpectedCharacterInNumber('.'), 3),
+            ("1-2", ErrorKind::UnexpectedCharacterInNumber('-'), 1),
+            ("0+1", ErrorKind::UnexpectedCharacterInNumber('+'), 1),
+            ("1e5e3", ErrorKind::UnexpectedCharacterInNumber('e'), 3),
+            ("1e5.3", ErrorKind::UnexpectedCharacterInNumber('.'), 3),
+        ];
+        for (input, kind, offset) in cases {
+            let err = collect_token_types(input).unwrap_err();
+            assert_eq!((err.kind, err.offset), (kind, offset), "input {:?}", input);
+        }
+
+        // The same grammar applies when a number is skipped.
+        for input in ["[1, -]", "[1e]", "{\"a\": 1-2}", "[0.1.2]"] {
+            assert!(skip_tokens(input).is_err(), "input {:?}", input);
+        }
+        // No combination of number characters can make the lexer panic.
+        let alphabet = b"-+.eE019x";
+        for len in 1..=4 {
+            for mut index in 0..alphabet.len().pow(len) {
+                let mut input = String::new();
+                for _ in 0..len {
+                    input.push(alphabet[index % alphabet.len()] as char);
+                    index /= alphabet.len();
+                }
+                let _ = collect_token_types(&input);
+                let _ = skip_tokens(&input);
+            }
+        }
+
+        assert!(skip_tokens("[-0.5e-3, 0, 10E+2]").is_ok());
+    }
+
+    #[test]
+    fn test_tokenizer_skipped_string_lengths() {
+        // A skipped string reports the length it would have decoded to.
+        let cases = [
+            (r#""""#, 0),
+            (r#""abc""#, 3),
+            (r#""é""#, 2),
+            (r#""a\nb\"""#, 4),
+            (r#""A\u00e9\u20ac""#, 6),
+            (r#""\ud83d\ude00!""#, 5),
+        ];
+        for (input, len) in cases {
+            let expected = match collect_token_types(input).unwrap().as_slice() {
+                [TokenType::String(s)] => s.len(),
+                other => panic!("unexpected tokens {:?}", other),
+            };
+            assert_eq!(expected, len, "input {:?}", input);
+            assert_eq!(skip_tokens(input), Ok(vec![len]), "input {:?}", input);
+        }
+
+        // Lone surrogates follow the configured policy, as when lexing.
+        let mut tokenizer =
+            Tokenizer::new(r#""\ud800x""#).with_lone_surrogates(LoneSurrogatePolicy::Replace);
+        assert_eq!(tokenizer.next_skipped().unwrap().unwrap().1, 4);
+        let mut tokenizer = Tokenizer::new(r#""\ud800\udc00\udfffx""#)
+            .with_lone_surrogates(LoneSurrogatePolicy::Preserve);
+        assert_eq!(tokenizer.next_skipped().unwrap().unwrap().1, 8);
+        let err = skip_tokens(r#""\ud800x""#).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::LoneSurrogate);
     }
 
     #[test]
@@ -563,4 +1401,294 @@ mod tests {
         let err = collect_token_types(input).unwrap_err();
         assert_eq!(err.message, "Unexpected character '&'");
     }
+
+    fn collect_json5_token_types(input: &str) -> Result<Vec<TokenType<'_>>, ParseError> {
+        let tokenizer = Tokenizer::new(input).with_dialect(Dialect::Json5);
+        tokenizer.map(|res| res.map(|token| token.kind)).collect()
+    }
+
+    #[test]
+    fn test_tokenizer_json5() {
+        let input = "// line\n{ /* block\n */ key_1: 'it\\'s \"q\"', $b: 0x1F, c: -0XfF,\u{a0}\
+                     d: .5, e: 5., f: +1, g: -Infinity, h: -NaN, i: 'a\\\nb\\x41\\v\\0', }";
+        let tokens = collect_json5_token_types(input).unwrap();
+        let expected = vec![
+            TokenType::LeftBrace,
+            TokenType::Identifier("key_1"),
+            TokenType::Colon,
+            TokenType::String(Cow::Owned("it's \"q\"".to_string())),
+            TokenType::Comma,
+            TokenType::Identifier("$b"),
+            TokenType::Colon,
+            TokenType::Number(JsonNumber::I64(31)),
+            TokenType::Comma,
+            TokenType::Identifier("c"),
+            TokenType::Colon,
+            TokenType::Number(JsonNumber::I64(-255)),
+            TokenType::Comma,
+            TokenType::Identifier("d"),
+            TokenType::Colon,
+            TokenType::Number(JsonNumber::F64(0.5)),
+            TokenType::Comma,
+            TokenType::Identifier("e"),
+            TokenType::Colon,
+            TokenType::Number(JsonNumber::F64(5.0)),
+            TokenType::Comma,
+            TokenType::Identifier("f"),
+            TokenType::Colon,
+            TokenType::Number(JsonNumber::I64(1)),
+            TokenType::Comma,
+            TokenType::Identifier("g"),
+            TokenType::Colon,
+            TokenType::Number(JsonNumber::F64(f64::NEG_INFINITY)),
+            TokenType::Comma,
+            TokenType::Identifier("h"),
+            TokenType::Colon,
+        ];
+        assert_eq!(&tokens[..expected.len()], &expected[..]);
+        assert!(matches!(
+            tokens[expected.len()],
+            TokenType::Number(JsonNumber::F64(n)) if n.is_nan()
+        ));
+        assert_eq!(
+            &tokens[expected.len() + 1..],
+            &[
+                TokenType::Comma,
+                TokenType::Identifier("i"),
+                TokenType::Colon,
+                TokenType::String(Cow::Owned("abA\u{b}\0".to_string())),
+                TokenType::Comma,
+                TokenType::RightBrace,
+            ]
+        );
+
+        // Identity escapes and `\xHH` work beyond ASCII.
+        assert_eq!(
+            collect_json5_token_types("'\\\u{e9}t\u{e9} \\xe9\\n\u{1F600}'").unwrap(),
+            [TokenType::String(Cow::Owned(
+                "\u{e9}t\u{e9} \u{e9}\n\u{1F600}".to_string()
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_tokenizer_json5_errors() {
+        let cases = [
+            ("/* open", ErrorKind::UnterminatedComment),
+            ("0x", ErrorKind::InvalidNumber("0x".to_string())),
+            ("1.2.3", ErrorKind::InvalidNumber("1.2.3".to_string())),
+            ("+-1", ErrorKind::InvalidNumber("+-1".to_string())),
+            ("007", ErrorKind::LeadingZero),
+            (
+                "0xFFFFFFFFFFFFFFFFFF",
+                ErrorKind::IntegerOutOfRange("0xFFFFFFFFFFFFFFFFFF".to_string()),
+            ),
+            (
+                "-0x8000000000000001",
+                ErrorKind::IntegerOutOfRange("-0x8000000000000001".to_string()),
+            ),
+            (
+                "+99999999999999999999",
+                ErrorKind::IntegerOutOfRange("+99999999999999999999".to_string()),
+            ),
+            ("'\\1'", ErrorKind::InvalidEscape),
+            ("'\\xZ1'", ErrorKind::InvalidEscape),
+            ("#", ErrorKind::UnexpectedCharacter('#')),
+        ];
+        for (input, kind) in cases {
+            let err = collect_json5_token_types(input).unwrap_err();
+            assert_eq!(err.kind, kind, "input {:?}", input);
+        }
+        let err = collect_json5_token_types("[0x1FFFFFFFFFFFFFFFF]").unwrap_err();
+        assert_eq!(err.message, "Integer '0x1FFFFFFFFFFFFFFFF' is out of range");
+        assert_eq!((err.column, err.offset), (21, 20));
+
+        // Strict JSON still rejects all of it.
+        assert_eq!(
+            collect_token_types("// comment").unwrap_err().kind,
+            ErrorKind::UnexpectedCharacter('/')
+        );
+        assert_eq!(
+            collect_token_types("'single'").unwrap_err().kind,
+            ErrorKind::UnexpectedCharacter('\'')
+        );
+    }
+
+    #[test]
+    fn test_tokenizer_json5_numbers() {
+        // JSON5 decimals convert exactly like the JSON number of the same
+        // value, including those that need the slow path.
+        let cases = [
+            ("+1", "1"),
+            (".5", "0.5"),
+            ("-.5", "-0.5"),
+            ("5.", "5.0"),
+            ("+5.e-1", "5.0e-1"),
+            ("+18446744073709551615", "18446744073709551615"),
+            ("-9223372036854775808", "-9223372036854775808"),
+            (".10000000000000000000001", "0.10000000000000000000001"),
+            ("+.1e-400", "0.1e-400"),
+            ("1.e400", "1.0e400"),
+            ("9007199254740993.", "9007199254740993.0"),
+            (
+                "+.2470328229206232720882538e-323",
+                "0.2470328229206232720882538e-323",
+            ),
+            (
+                "+2.4703282292062327208828439643411068618252990130716238221279284125033775363510437593264991818081799618989828234772285886546332835517796989819938739800539093906315035659515570226392290858392449105184435931802849936536152500319370457678249219365623669863658480757001585769269903706311928279558551332927834338409351978015531246597263579574622766465272827220056374006485499977096599470454020828166226237857393450736339007967761930577506740176324673600968951340535537458516661134223766678604162159680461914467291840300530057530849048765391711386591646239524912623653881879636239373280423891018672348497668235089863388587925628302755995657524455507255189313690836254779186948667994968324049705821028513185451396213837722826145437693412532098591327667236328125e-324",
+                "2.4703282292062327208828439643411068618252990130716238221279284125033775363510437593264991818081799618989828234772285886546332835517796989819938739800539093906315035659515570226392290858392449105184435931802849936536152500319370457678249219365623669863658480757001585769269903706311928279558551332927834338409351978015531246597263579574622766465272827220056374006485499977096599470454020828166226237857393450736339007967761930577506740176324673600968951340535537458516661134223766678604162159680461914467291840300530057530849048765391711386591646239524912623653881879636239373280423891018672348497668235089863388587925628302755995657524455507255189313690836254779186948667994968324049705821028513185451396213837722826145437693412532098591327667236328125e-324",
+            ),
+        ];
+        for (json5, json) in cases {
+            let expected = collect_token_types(json).unwrap();
+            assert_eq!(
+                collect_json5_token_types(json5).unwrap(),
+                expected,
+                "input {:?}",
+                json5
+            );
+        }
+
+        // Lossless mode keeps the JSON text, so it round-trips exactly.
+        let lossless = |input| {
+            let mut tokenizer = Tokenizer::new(input)
+                .with_dialect(Dialect::Json5)
+                .with_lossless_numbers(true);
+            tokenizer.next().unwrap().map(|token| token.kind)
+        };
+        let cases = [
+            ("+.10000000000000000000001", "0.10000000000000000000001"),
+            ("-5.E+400", "-5.0E+400"),
+            ("1E2", "1E2"),
+            ("0x1F", "31"),
+            ("-0x0", "-0"),
+            (
+                "0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
+                "340282366920938463463374607431768211455",
+            ),
+        ];
+        for (json5, json) in cases {
+            assert_eq!(
+                lossless(json5),
+                Ok(TokenType::Number(JsonNumber::Raw(
+                    RawNumber::new(json).unwrap()
+                ))),
+                "input {:?}",
+                json5
+            );
+        }
+        assert_eq!(
+            lossless("0x1FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF")
+                .unwrap_err()
+                .kind,
+            ErrorKind::IntegerOutOfRange("0x1FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF".to_string())
+        );
+        assert_eq!(
+            lossless("-Infinity"),
+            Ok(TokenType::Number(JsonNumber::F64(f64::NEG_INFINITY)))
+        );
+    }
+
+    fn lex_string_with(input: &str, policy: LoneSurrogatePolicy) -> Result<String, ParseError> {
+        let mut tokenizer = Tokenizer::new(input).with_lone_surrogates(policy);
+        match tokenizer.next().unwrap()?.kind {
+            TokenType::String(s) => Ok(s.into_owned()),
+            other => panic!("expected a string, got {:?}", other),
+        }
+    }
+
+    /// Lexes a string under `LoneSurrogatePolicy::Preserve`, as UTF-16.
+    fn lex_preserved(input: &str) -> Result<Vec<u16>, ParseError> {
+        let mut tokenizer =
+            Tokenizer::new(input).with_lone_surrogates(LoneSurrogatePolicy::Preserve);
+        match tokenizer.next().unwrap()?.kind {
+            TokenType::String(s) => Ok(s.encode_utf16().collect()),
+            TokenType::WtfString(s) => Ok(s.to_utf16()),
+            other => panic!("expected a string, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tokenizer_surrogate_pairs() {
+        // Pairs always decode, whatever the policy, into a plain string.
+        for policy in [
+            LoneSurrogatePolicy::Error,
+            LoneSurrogatePolicy::Replace,
+            LoneSurrogatePolicy::Preserve,
+        ] {
+            assert_eq!(
+                lex_string_with(r#""\uD83D\uDE00""#, policy).unwrap(),
+                "\u{1F600}"
+            );
+            assert_eq!(
+                lex_string_with(r#""a\ud834\udd1eb""#, policy).unwrap(),
+                "a\u{1D11E}b"
+            );
+            assert_eq!(
+                lex_string_with(r#""\uDBFF\uDFFF""#, policy).unwrap(),
+                "\u{10FFFF}"
+            );
+            assert_eq!(
+                lex_string_with(r#""\uD800\uDC00""#, policy).unwrap(),
+                "\u{10000}"
+            );
+            assert_eq!(lex_string_with(r#""\uFFFF""#, policy).unwrap(), "\u{FFFF}");
+        }
+
+        // (input, replaced, preserved as UTF-16)
+        let lone: [(&str, &str, &[u16]); 9] = [
+            (r#""\uD83D""#, "\u{FFFD}", &[0xD83D]),
+            (r#""\uD83Dx""#, "\u{FFFD}x", &[0xD83D, 0x78]),
+            (r#""\uDE00""#, "\u{FFFD}", &[0xDE00]),
+            // Reversed, and doubled high surrogates.
+            (r#""\uDE00\uD83D""#, "\u{FFFD}\u{FFFD}", &[0xDE00, 0xD83D]),
+            (
+                r#""\uD83D\uD83D\uDE00""#,
+                "\u{FFFD}\u{1F600}",
+                &[0xD83D, 0xD83D, 0xDE00],
+            ),
+            // The escape after a high surrogate is still decoded normally.
+            (r#""\ud83d\u0041""#, "\u{FFFD}A", &[0xD83D, 0x41]),
+            (r#""\uD83D\n""#, "\u{FFFD}\n", &[0xD83D, 0x0A]),
+            // A real U+FFFD stays apart from the surrogates around it.
+            (
+                r#""\uFFFD\uDC00\uFFFD""#,
+                "\u{FFFD}\u{FFFD}\u{FFFD}",
+                &[0xFFFD, 0xDC00, 0xFFFD],
+            ),
+            (
+                "\"é\\uD800€\\uDBFF\"",
+                "é\u{FFFD}€\u{FFFD}",
+                &[0xE9, 0xD800, 0x20AC, 0xDBFF],
+            ),
+        ];
+        for (input, replaced, preserved) in lone {
+            let err = lex_string_with(input, LoneSurrogatePolicy::Error).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::LoneSurrogate, "input {:?}", input);
+            assert_eq!(
+                lex_string_with(input, LoneSurrogatePolicy::Replace).unwrap(),
+                replaced,
+                "input {:?}",
+                input
+            );
+            assert_eq!(
+                lex_preserved(input).unwrap(),
+                preserved,
+                "input {:?}",
+                input
+            );
+        }
+
+        // The error points just past the unpaired escape.
+        let err = lex_string_with(r#""ab\uDC00\u0041""#, LoneSurrogatePolicy::Error).unwrap_err();
+        assert_eq!((err.column, err.offset), (10, 9));
+
+        // A broken escape after a high surrogate is still reported.
+        let err = lex_string_with(r#""\uD83D\uDEzz""#, LoneSurrogatePolicy::Replace).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidUnicodeEscape);
+        let err = lex_preserved(r#""\uD83D\uDEzz""#).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidUnicodeEscape);
+        let err = lex_string_with(r#""\u+123""#, LoneSurrogatePolicy::Error).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidUnicodeEscape);
+    }
 }
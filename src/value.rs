@@ -3,15 +3,25 @@
 //!
 //! This module also includes the "stringify" (serialization) logic
 //! for converting a `JsonValue` back into a JSON string.
-use crate::{parse_streaming, ParseError, ParserEvent, StreamingParser};
+use crate::{
+    float, parse_streaming_with, ErrorKind, ParseError, ParserEvent, ParserOptions, RawNumber,
+    RawValue, SpannedEvent, Wtf8String,
+};
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt;
 
 /// A native Rust representation of any valid JSON number.
 ///
 /// This enum is used to store numbers without precision loss,
-/// supporting `i64`, `u64`, and `f64`.
-#[derive(Debug, PartialEq, Clone, Copy)]
+/// supporting `i64`, `u64`, and `f64`. In lossless mode (see
+/// `ParserOptions::lossless_numbers`), every number is kept as its source
+/// text in a `RawNumber` instead.
+///
+/// New representations may be added, so matches need a wildcard arm; the
+/// `to_i64`-style accessors work for every variant.
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
 pub enum JsonNumber {
     /// Represents a signed 64-bit integer.
     I64(i64),
@@ -19,6 +29,58 @@ pub enum JsonNumber {
     U64(u64),
     /// Represents a 64-bit floating-point number.
     F64(f64),
+    /// A number kept as its exact source text, written out byte for byte.
+    Raw(RawNumber),
+}
+
+impl JsonNumber {
+    /// Converts the number to an `i64`, if it is a whole number in range.
+    pub fn to_i64(&self) -> Option<i64> {
+        self.to_i128().and_then(|n| i64::try_from(n).ok())
+    }
+
+    /// Converts the number to a `u64`, if it is a whole number in range.
+    pub fn to_u64(&self) -> Option<u64> {
+        self.to_u128().and_then(|n| u64::try_from(n).ok())
+    }
+
+    /// Converts the number to an `i128`, if it is a whole number in range.
+    pub fn to_i128(&self) -> Option<i128> {
+        match self {
+            JsonNumber::I64(n) => Some(i128::from(*n)),
+            JsonNumber::U64(n) => Some(i128::from(*n)),
+            // Every `f64` whole number below 2^127 converts exactly.
+            JsonNumber::F64(n) if n.fract() == 0.0 && n.abs() < 2f64.powi(127) => Some(*n as i128),
+            JsonNumber::F64(_) => None,
+            JsonNumber::Raw(raw) => raw.to_i128(),
+        }
+    }
+
+    /// Converts the number to a `u128`, if it is a non-negative whole
+    /// number in range.
+    pub fn to_u128(&self) -> Option<u128> {
+        match self {
+            JsonNumber::Raw(raw) => raw.to_u128(),
+            JsonNumber::F64(n) if n.fract() == 0.0 && *n >= 0.0 && *n < 2f64.powi(128) => {
+                Some(*n as u128)
+            }
+            _ => self.to_i128().and_then(|n| u128::try_from(n).ok()),
+        }
+    }
+
+    /// Converts the number to an `f64`, rounding if needed.
+    ///
+    /// Returns `None` for NaN and infinite values, and for raw numbers too
+    /// large for an `f64`.
+    pub fn to_f64(&self) -> Option<f64> {
+        let n = match self {
+            JsonNumber::I64(n) => *n as f64,
+            JsonNumber::U64(n) => *n as f64,
+            JsonNumber::F64(n) => *n,
+            JsonNumber::Raw(raw) => return raw.to_f64(),
+        };
+        Some(n).filter(|n| n.is_finite())
+    }
 }
 
 /// Implement Display to allow `write!(w, "{}", ...)`
@@ -27,7 +89,8 @@ impl fmt::Display for JsonNumber {
         match self {
             JsonNumber::I64(n) => write!(f, "{}", n),
             JsonNumber::U64(n) => write!(f, "{}", n),
-            JsonNumber::F64(n) => write!(f, "{}", n),
+            JsonNumber::F64(n) => float::write_f64(f, *n),
+            JsonNumber::Raw(raw) => write!(f, "{}", raw),
         }
     }
 }
@@ -36,7 +99,10 @@ impl fmt::Display for JsonNumber {
 ///
 /// This enum is used by the `stringify` functions to serialize
 /// Rust data *into* a JSON string.
+///
+/// New variants may be added, so matches need a wildcard arm.
 #[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
 pub enum JsonValue {
     /// Represents a JSON `null`.
     Null,
@@ -46,12 +112,21 @@ pub enum JsonValue {
     Number(JsonNumber),
     /// Represents a JSON string.
     String(String),
+    /// A JSON string holding a lone UTF-16 surrogate, which only
+    /// `LoneSurrogatePolicy::Preserve` produces. It is written out with
+    /// each lone surrogate escaped as `\uXXXX`.
+    WtfString(Wtf8String),
     /// Represents a JSON array (list).
     Array(Vec<JsonValue>),
     /// Represents a JSON object (map).
     /// We use BTreeMap to guarantee deterministic (alphabetical) key order
     /// during serialization.
     Object(BTreeMap<String, JsonValue>),
+    /// A JSON value kept as its source text, written out verbatim.
+    ///
+    /// See `StreamingParser::next_raw_value`. Raw values are never
+    /// re-formatted, not even by `stringify_pretty`.
+    Raw(RawValue<'static>),
 }
 
 impl JsonValue {
@@ -63,37 +138,43 @@ impl JsonValue {
     /// # Errors
     /// Returns a `ParseError` if the JSON is invalid, empty, or has trailing tokens.
     pub fn parse(input: &str) -> Result<JsonValue, ParseError> {
-        let mut parser = parse_streaming(input)?.peekable();
+        Self::parse_with(input, &ParserOptions::default())
+    }
+
+    /// Parses a JSON string into a `JsonValue`, enforcing the given `ParserOptions`.
+    ///
+    /// # Errors
+    /// Returns a `ParseError` if the JSON is invalid, empty, has trailing
+    /// tokens, or exceeds one of the configured limits.
+    pub fn parse_with(input: &str, options: &ParserOptions) -> Result<JsonValue, ParseError> {
+        let mut streaming = parse_streaming_with(input, options)?;
+        let mut parser = std::iter::from_fn(move || streaming.next_spanned()).peekable();
 
         // Check for empty input
         if parser.peek().is_none() {
-            return Err(ParseError {
-                message: "Empty input".to_string(),
-                line: 1,
-                column: 1,
-            });
+            return Err(ParseError::new(ErrorKind::EmptyInput, 1, 1, 0));
         }
 
         // Recursive helper
-        fn parse_one(
-            parser: &mut std::iter::Peekable<StreamingParser<'_>>,
+        fn parse_one<'a>(
+            parser: &mut std::iter::Peekable<
+                impl Iterator<Item = Result<SpannedEvent<'a>, ParseError>>,
+            >,
         ) -> Result<JsonValue, ParseError> {
             // Consume next event
             let event = match parser.next() {
-                Some(Ok(event)) => event,
+                Some(Ok(spanned)) => spanned.event,
                 Some(Err(e)) => return Err(e),
                 None => {
-                    return Err(ParseError {
-                        message: "Unexpected end of input".to_string(),
-                        line: 1, // This is a best-effort location
-                        column: 1,
-                    });
+                    // This is a best-effort location
+                    return Err(ParseError::new(ErrorKind::UnexpectedEndOfInput, 1, 1, 0));
                 }
             };
 
             match event {
                 // Base cases
                 ParserEvent::String(s) => Ok(JsonValue::String(s.into_owned())),
+                ParserEvent::WtfString(s) => Ok(JsonValue::WtfString(s)),
                 ParserEvent::Number(n) => Ok(JsonValue::Number(n)),
                 ParserEvent::Boolean(b) => Ok(JsonValue::Boolean(b)),
                 ParserEvent::Null => Ok(JsonValue::Null),
@@ -104,7 +185,10 @@ impl JsonValue {
                     // Loop until we see `EndArray`
                     loop {
                         match parser.peek() {
-                            Some(Ok(ParserEvent::EndArray)) => {
+                            Some(Ok(SpannedEvent {
+                                event: ParserEvent::EndArray,
+                                ..
+                            })) => {
                                 parser.next(); // Consume the EndArray
                                 break Ok(JsonValue::Array(arr));
                             }
@@ -133,14 +217,32 @@ impl JsonValue {
                     // Loop until we see `EndObject`
                     loop {
                         match parser.peek() {
-                            Some(Ok(ParserEvent::EndObject)) => {
+                            Some(Ok(SpannedEvent {
+                                event: ParserEvent::EndObject,
+                                ..
+                            })) => {
                                 parser.next(); // Consume the EndObject
                                 break Ok(JsonValue::Object(obj));
                             }
-                            Some(Ok(ParserEvent::Key(_))) => {
+                            Some(Ok(SpannedEvent {
+                                event: ParserEvent::Key(_) | ParserEvent::WtfKey(_),
+                                ..
+                            })) => {
                                 // Get the key
                                 let key = match parser.next() {
-                                    Some(Ok(ParserEvent::Key(key))) => key.into_owned(),
+                                    Some(Ok(SpannedEvent {
+                                        event: ParserEvent::Key(key),
+                                        ..
+                                    })) => key.into_owned(),
+                                    // A `String` key cannot hold a lone surrogate.
+                                    Some(Ok(SpannedEvent { span, .. })) => {
+                                        return Err(ParseError::new(
+                                            ErrorKind::LoneSurrogate,
+                                            span.line,
+                                            span.column,
+                                            span.start,
+                                        ));
+                                    }
                                     _ => unreachable!(), // We just peeked
                                 };
                                 // Get the value
@@ -171,7 +273,10 @@ impl JsonValue {
                 }
 
                 // Invalid start
-                ParserEvent::Key(_) | ParserEvent::EndArray | ParserEvent::EndObject => {
+                ParserEvent::Key(_)
+                | ParserEvent::WtfKey(_)
+                | ParserEvent::EndArray
+                | ParserEvent::EndObject => {
                     // This should be unreachable. The StreamingParser's
                     // state machine should never emit these events when
                     // `parse_one` is expecting a value. It would
@@ -191,7 +296,9 @@ impl JsonValue {
             // This branch is unreachable. The StreamingParser's `next()`
             // will return `Some(Err("Unexpected trailing token"))` if it
             // finds a token when its state stack is empty.
-            Some(Ok(event)) => unreachable!("Trailing token was not an error: {:?}", event),
+            Some(Ok(spanned)) => {
+                unreachable!("Trailing token was not an error: {:?}", spanned.event)
+            }
         }
     }
 }
@@ -220,8 +327,10 @@ impl JsonValue {
                 _ => write!(w, "{}", n), // Use JsonNumber's Display impl
             },
             JsonValue::String(s) => Self::write_string(s, w),
+            JsonValue::WtfString(s) => s.write_json(w),
             JsonValue::Array(a) => Self::write_array(a, w),
             JsonValue::Object(o) => Self::write_object(o, w),
+            JsonValue::Raw(raw) => w.write_str(raw.get()),
         }
     }
 
@@ -258,8 +367,15 @@ impl JsonValue {
 
     /// Helper to write an escaped JSON string.
     /// This handles all required JSON escape sequences (e.g., `\"`, `\\`, `\n`).
-    fn write_string<W: fmt::Write>(s: &str, w: &mut W) -> fmt::Result {
+    pub(crate) fn write_string<W: fmt::Write>(s: &str, w: &mut W) -> fmt::Result {
         w.write_char('"')?;
+        Self::write_escaped(s, w)?;
+        w.write_char('"')
+    }
+
+    /// Helper to write the escaped contents of a JSON string, without the
+    /// quotes.
+    pub(crate) fn write_escaped<W: fmt::Write>(s: &str, w: &mut W) -> fmt::Result {
         for c in s.chars() {
             match c {
                 // Standard escapes
@@ -278,12 +394,12 @@ impl JsonValue {
                 _ => w.write_char(c),
             }?;
         }
-        w.write_char('"')
+        Ok(())
     }
 
     // --- Pretty-Printing Logic ---
     /// The indentation string to use for pretty-printing (two spaces).
-    const INDENT: &'static str = "  ";
+    pub(crate) const INDENT: &'static str = "  ";
 
     /// Serializes the `JsonValue` into a human-readable,
     /// indented JSON string ("pretty-print").
@@ -313,9 +429,11 @@ impl JsonValue {
                 _ => write!(w, "{}", n), // Use JsonNumber's Display impl
             },
             JsonValue::String(s) => Self::write_string(s, w),
+            JsonValue::WtfString(s) => s.write_json(w),
             // Composites
             JsonValue::Array(a) => Self::write_array_pretty(a, w, depth),
             JsonValue::Object(o) => Self::write_object_pretty(o, w, depth),
+            JsonValue::Raw(raw) => w.write_str(raw.get()),
         }
     }
 
@@ -383,3 +501,291 @@ impl JsonValue {
         w.write_char('}') // Closing brace
     }
 }
+
+// --- Conversions from Rust values ---
+
+/// Narrows an integer the way the parser does: `I64` when it fits, then
+/// `U64`, then the exact decimal text.
+fn number_from_i128(n: i128) -> JsonNumber {
+    if let Ok(n) = i64::try_from(n) {
+        JsonNumber::I64(n)
+    } else if let Ok(n) = u64::try_from(n) {
+        JsonNumber::U64(n)
+    } else {
+        JsonNumber::Raw(RawNumber::from_source(&n.to_string()))
+    }
+}
+
+/// The unsigned counterpart of `number_from_i128`.
+fn number_from_u128(n: u128) -> JsonNumber {
+    match i128::try_from(n) {
+        Ok(n) => number_from_i128(n),
+        Err(_) => JsonNumber::Raw(RawNumber::from_source(&n.to_string())),
+    }
+}
+
+macro_rules! from_integer {
+    ($($ty:ty => $number:expr),*) => {$(
+        impl From<$ty> for JsonNumber {
+            fn from(n: $ty) -> Self {
+                $number(n)
+            }
+        }
+
+        impl From<$ty> for JsonValue {
+            fn from(n: $ty) -> Self {
+                JsonValue::Number(JsonNumber::from(n))
+            }
+        }
+    )*};
+}
+
+from_integer!(
+    i8 => |n| JsonNumber::I64(i64::from(n)),
+    i16 => |n| JsonNumber::I64(i64::from(n)),
+    i32 => |n| JsonNumber::I64(i64::from(n)),
+    i64 => JsonNumber::I64,
+    i128 => number_from_i128,
+    isize => |n| number_from_i128(n as i128),
+    u8 => |n| JsonNumber::I64(i64::from(n)),
+    u16 => |n| JsonNumber::I64(i64::from(n)),
+    u32 => |n| JsonNumber::I64(i64::from(n)),
+    u64 => |n| number_from_u128(u128::from(n)),
+    u128 => number_from_u128,
+    usize => |n| number_from_u128(n as u128)
+);
+
+impl From<f64> for JsonNumber {
+    fn from(n: f64) -> Self {
+        JsonNumber::F64(n)
+    }
+}
+
+/// Widens through the shortest decimal text, so `0.1f32` becomes `0.1` and
+/// not `0.10000000149011612`.
+impl From<f32> for JsonNumber {
+    fn from(n: f32) -> Self {
+        JsonNumber::F64(n.to_string().parse().unwrap_or(f64::from(n)))
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(n: f64) -> Self {
+        JsonValue::Number(JsonNumber::from(n))
+    }
+}
+
+impl From<f32> for JsonValue {
+    fn from(n: f32) -> Self {
+        JsonValue::Number(JsonNumber::from(n))
+    }
+}
+
+impl From<JsonNumber> for JsonValue {
+    fn from(n: JsonNumber) -> Self {
+        JsonValue::Number(n)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> Self {
+        JsonValue::Boolean(b)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(s: &str) -> Self {
+        JsonValue::String(s.to_owned())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(s: String) -> Self {
+        JsonValue::String(s)
+    }
+}
+
+impl From<Cow<'_, str>> for JsonValue {
+    fn from(s: Cow<'_, str>) -> Self {
+        JsonValue::String(s.into_owned())
+    }
+}
+
+impl From<()> for JsonValue {
+    fn from(_: ()) -> Self {
+        JsonValue::Null
+    }
+}
+
+/// `None` becomes `null`.
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(JsonValue::Null, Into::into)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(items: Vec<T>) -> Self {
+        JsonValue::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<JsonValue>> From<&[T]> for JsonValue {
+    fn from(items: &[T]) -> Self {
+        JsonValue::Array(items.iter().cloned().map(Into::into).collect())
+    }
+}
+
+impl From<BTreeMap<String, JsonValue>> for JsonValue {
+    fn from(members: BTreeMap<String, JsonValue>) -> Self {
+        JsonValue::Object(members)
+    }
+}
+
+/// Converts a `serde_json::Number`, keeping its type: integers that fit in
+/// an `i64` become `I64`, larger ones `U64`, and everything else `F64`.
+#[cfg(feature = "serde_json")]
+impl From<serde_json::Number> for JsonNumber {
+    fn from(n: serde_json::Number) -> Self {
+        if let Some(n) = n.as_i64() {
+            JsonNumber::I64(n)
+        } else if let Some(n) = n.as_u64() {
+            JsonNumber::U64(n)
+        } else if let Some(n) = n.as_f64() {
+            JsonNumber::F64(n)
+        } else {
+            // Only reachable with serde_json's `arbitrary_precision`.
+            JsonNumber::Raw(RawNumber::from_source(&n.to_string()))
+        }
+    }
+}
+
+/// Converts a `serde_json::Value` without losing any number's type.
+///
+/// # Examples
+/// ```
+/// use rill_json::{JsonNumber, JsonValue};
+///
+/// let native = serde_json::json!({"id": 18446744073709551615u64, "ratio": 1.0});
+/// let value = JsonValue::from(native.clone());
+/// assert_eq!(value.stringify().unwrap(), r#"{"id":18446744073709551615,"ratio":1.0}"#);
+///
+/// assert_eq!(serde_json::Value::try_from(value).unwrap(), native);
+/// ```
+#[cfg(feature = "serde_json")]
+impl From<serde_json::Value> for JsonValue {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => JsonValue::Null,
+            serde_json::Value::Bool(b) => JsonValue::Boolean(b),
+            serde_json::Value::Number(n) => JsonValue::Number(n.into()),
+            serde_json::Value::String(s) => JsonValue::String(s),
+            serde_json::Value::Array(a) => {
+                JsonValue::Array(a.into_iter().map(Into::into).collect())
+            }
+            serde_json::Value::Object(o) => {
+                JsonValue::Object(o.into_iter().map(|(k, v)| (k, v.into())).collect())
+            }
+        }
+    }
+}
+
+/// Converts a `JsonNumber` into a `serde_json::Number`.
+///
+/// The conversion never rounds. A raw number converts only if
+/// `serde_json` holds its exact value: an integer that fits in an `i64` or
+/// `u64`, or a decimal whose shortest `f64` form reads back as the same
+/// number. With `serde_json`'s `arbitrary_precision` feature, every raw
+/// number converts.
+///
+/// # Errors
+/// Fails with `SerializeError::NonFiniteFloat` for NaN and infinities,
+/// which `serde_json` cannot represent either, and with
+/// `SerializeError::Custom` for a raw number whose value `serde_json`
+/// would round, such as `12345678901234567890123` or `0.10000000000000000000001`.
+///
+/// # Examples
+/// ```
+/// use rill_json::{JsonNumber, RawNumber};
+///
+/// let exact = JsonNumber::Raw(RawNumber::new("1.50").unwrap());
+/// assert_eq!(serde_json::Number::try_from(exact).unwrap().to_string(), "1.5");
+///
+/// let rounded = JsonNumber::Raw(RawNumber::new("0.10000000000000000000001").unwrap());
+/// assert!(serde_json::Number::try_from(rounded).is_err());
+/// ```
+#[cfg(feature = "serde_json")]
+impl TryFrom<JsonNumber> for serde_json::Number {
+    type Error = crate::SerializeError;
+
+    fn try_from(n: JsonNumber) -> Result<Self, Self::Error> {
+        match n {
+            JsonNumber::I64(n) => Ok(n.into()),
+            JsonNumber::U64(n) => Ok(n.into()),
+            JsonNumber::F64(n) => {
+                serde_json::Number::from_f64(n).ok_or(crate::SerializeError::NonFiniteFloat)
+            }
+            JsonNumber::Raw(raw) => {
+                let n: serde_json::Number = raw
+                    .get()
+                    .parse()
+                    .map_err(|e: serde_json::Error| crate::SerializeError::Custom(e.to_string()))?;
+                if raw.same_value(&n.to_string()) {
+                    Ok(n)
+                } else {
+                    Err(crate::SerializeError::Custom(format!(
+                        "{} cannot be converted without rounding it to {}",
+                        raw, n
+                    )))
+                }
+            }
+        }
+    }
+}
+
+/// Converts a `JsonValue` into a `serde_json::Value`, keeping every
+/// number's type. The numbers in a `Raw` value are held to the same rule
+/// as `Raw` numbers: they must convert without rounding.
+///
+/// # Errors
+/// Fails where converting a number fails (see `serde_json::Number`'s
+/// `TryFrom<JsonNumber>`), or on a string or key with a lone surrogate,
+/// which a `serde_json::Value` cannot hold.
+#[cfg(feature = "serde_json")]
+impl TryFrom<JsonValue> for serde_json::Value {
+    type Error = crate::SerializeError;
+
+    fn try_from(value: JsonValue) -> Result<Self, Self::Error> {
+        Ok(match value {
+            JsonValue::Null => serde_json::Value::Null,
+            JsonValue::Boolean(b) => serde_json::Value::Bool(b),
+            JsonValue::Number(n) => serde_json::Value::Number(n.try_into()?),
+            JsonValue::String(s) => serde_json::Value::String(s),
+            JsonValue::WtfString(_) => {
+                return Err(crate::SerializeError::Custom(
+                    "A string with a lone surrogate cannot be converted".to_string(),
+                ))
+            }
+            JsonValue::Array(a) => serde_json::Value::Array(
+                a.into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<_, _>>()?,
+            ),
+            JsonValue::Object(o) => serde_json::Value::Object(
+                o.into_iter()
+                    .map(|(k, v)| Ok((k, v.try_into()?)))
+                    .collect::<Result<_, Self::Error>>()?,
+            ),
+            JsonValue::Raw(raw) => {
+                // Raw values are valid JSON, so only the nesting depth limit
+                // or a lone surrogate can fail here.
+                let options = crate::ParserOptions::new()
+                    .max_input_size(usize::MAX)
+                    .lossless_numbers(true);
+                JsonValue::parse_with(raw.get(), &options)
+                    .map_err(|e| crate::SerializeError::Custom(e.to_string()))?
+                    .try_into()?
+            }
+        })
+    }
+}
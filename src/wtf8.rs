@@ -0,0 +1,343 @@
+//! Contains `Wtf8String`, a string that can hold lone UTF-16 surrogates.
+//!
+//! JSON strings are sequences of UTF-16 code units, so `"\uD800"` is valid
+//! JSON even though it encodes no character. Under
+//! `LoneSurrogatePolicy::Preserve`, strings and keys holding such a lone
+//! surrogate are decoded into a `Wtf8String` rather than a `String`.
+
+use crate::value::JsonValue;
+use memchr::memchr;
+use std::fmt;
+use std::str;
+
+/// A string that may contain lone UTF-16 surrogates, stored as
+/// [WTF-8](https://simonsapin.github.io/wtf-8/).
+///
+/// WTF-8 extends UTF-8 to encode the surrogates U+D800 to U+DFFF as three
+/// bytes each, like any other code point. A surrogate pair is always
+/// stored as the character it encodes, so every `Wtf8String` converts to
+/// and from UTF-16 without loss, and is written back out as JSON with each
+/// lone surrogate escaped as `\uXXXX` again.
+///
+/// # Examples
+/// ```
+/// use rill_json::{JsonValue, LoneSurrogatePolicy, ParserOptions, Wtf8String};
+///
+/// let options = ParserOptions::new().lone_surrogates(LoneSurrogatePolicy::Preserve);
+/// let value = JsonValue::parse_with(r#""a\uD800b""#, &options).unwrap();
+///
+/// let expected = Wtf8String::from_utf16(&[0x61, 0xD800, 0x62]);
+/// assert_eq!(value, JsonValue::WtfString(expected.clone()));
+/// assert_eq!(expected.to_string_lossy(), "a\u{FFFD}b");
+/// assert_eq!(value.stringify().unwrap(), r#""a\ud800b""#);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Wtf8String {
+    /// The WTF-8 bytes.
+    bytes: Vec<u8>,
+}
+
+/// A run of a `Wtf8String`: either text, or a single lone surrogate.
+enum Chunk<'a> {
+    Str(&'a str),
+    Surrogate(u16),
+}
+
+impl Wtf8String {
+    /// Creates an empty string.
+    pub fn new() -> Self {
+        Wtf8String::default()
+    }
+
+    /// Decodes UTF-16, keeping any lone surrogate.
+    pub fn from_utf16(units: &[u16]) -> Self {
+        let mut s = Wtf8String::new();
+        for unit in char::decode_utf16(units.iter().copied()) {
+            match unit {
+                Ok(c) => s.push_str(c.encode_utf8(&mut [0; 4])),
+                Err(e) => s.push_surrogate(e.unpaired_surrogate()),
+            }
+        }
+        s
+    }
+
+    /// Checks that `bytes` are WTF-8: UTF-8 that may also encode lone
+    /// surrogates, though never a high surrogate followed by a low one.
+    /// Returns `None` if they are not.
+    pub fn from_wtf8(bytes: Vec<u8>) -> Option<Self> {
+        let mut rest = &bytes[..];
+        let mut after_high = false;
+        loop {
+            let end = match str::from_utf8(rest) {
+                Ok(_) => return Some(Wtf8String { bytes }),
+                Err(e) => e.valid_up_to(),
+            };
+            match rest[end..] {
+                [0xED, b1 @ 0xA0..=0xBF, 0x80..=0xBF, ..] => {
+                    let high = b1 < 0xB0;
+                    if !high && after_high && end == 0 {
+                        return None;
+                    }
+                    after_high = high;
+                    rest = &rest[end + 3..];
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Converts a string decoded with U+FFFD in place of each lone
+    /// surrogate: `surrogates` holds the byte index of each U+FFFD to
+    /// replace, in order, and the surrogate it stands for.
+    pub(crate) fn from_replaced(s: &str, surrogates: &[(usize, u16)]) -> Self {
+        let mut wtf = Wtf8String {
+            bytes: Vec::with_capacity(s.len()),
+        };
+        let mut start = 0;
+        for &(index, surrogate) in surrogates {
+            wtf.push_str(&s[start..index]);
+            wtf.push_surrogate(surrogate);
+            start = index + char::REPLACEMENT_CHARACTER.len_utf8();
+        }
+        wtf.push_str(&s[start..]);
+        wtf
+    }
+
+    /// Appends a string.
+    pub fn push_str(&mut self, s: &str) {
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    /// Appends a surrogate (or any other UTF-16 code unit). A low
+    /// surrogate that follows a high one is combined with it into the
+    /// character the pair encodes, as in UTF-16.
+    pub fn push_surrogate(&mut self, unit: u16) {
+        let unit = u32::from(unit);
+        if let (0xDC00..=0xDFFF, Some(high)) = (unit, self.last_high_surrogate()) {
+            self.bytes.truncate(self.bytes.len() - 3);
+            let c = 0x10000 + ((high - 0xD800) << 10) + (unit - 0xDC00);
+            let c = char::from_u32(c).expect("a surrogate pair encodes a char");
+            self.push_str(c.encode_utf8(&mut [0; 4]));
+        } else if let Some(c) = char::from_u32(unit) {
+            self.push_str(c.encode_utf8(&mut [0; 4]));
+        } else {
+            // The generalized UTF-8 encoding of a surrogate.
+            self.bytes.extend_from_slice(&[
+                0xED,
+                0x80 | (unit >> 6 & 0x3F) as u8,
+                0x80 | (unit & 0x3F) as u8,
+            ]);
+        }
+    }
+
+    /// Returns the high surrogate the string ends with, if any.
+    fn last_high_surrogate(&self) -> Option<u32> {
+        match self.bytes[self.bytes.len().saturating_sub(3)..] {
+            [0xED, b1 @ 0xA0..=0xAF, b2] => {
+                Some(0xD000 | u32::from(b1 & 0x3F) << 6 | u32::from(b2 & 0x3F))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the WTF-8 bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the WTF-8 bytes, consuming the string.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Returns the length in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns `true` if the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns `true` if the string holds a lone surrogate.
+    pub fn has_lone_surrogates(&self) -> bool {
+        self.chunks()
+            .any(|chunk| matches!(chunk, Chunk::Surrogate(_)))
+    }
+
+    /// Converts into a `String`, or returns the string unchanged if it
+    /// holds a lone surrogate.
+    pub fn into_string(self) -> Result<String, Self> {
+        String::from_utf8(self.bytes).map_err(|e| Wtf8String {
+            bytes: e.into_bytes(),
+        })
+    }
+
+    /// Converts into a `String`, replacing each lone surrogate with
+    /// U+FFFD REPLACEMENT CHARACTER.
+    pub fn to_string_lossy(&self) -> String {
+        let mut s = String::with_capacity(self.bytes.len());
+        for chunk in self.chunks() {
+            match chunk {
+                Chunk::Str(text) => s.push_str(text),
+                Chunk::Surrogate(_) => s.push(char::REPLACEMENT_CHARACTER),
+            }
+        }
+        s
+    }
+
+    /// Encodes the string as UTF-16, lone surrogates included.
+    pub fn to_utf16(&self) -> Vec<u16> {
+        let mut units = Vec::with_capacity(self.bytes.len());
+        for chunk in self.chunks() {
+            match chunk {
+                Chunk::Str(text) => units.extend(text.encode_utf16()),
+                Chunk::Surrogate(unit) => units.push(unit),
+            }
+        }
+        units
+    }
+
+    /// Writes the string as a quoted JSON string, escaping each lone
+    /// surrogate as `\uXXXX`.
+    pub(crate) fn write_json<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        w.write_char('"')?;
+        for chunk in self.chunks() {
+            match chunk {
+                Chunk::Str(text) => JsonValue::write_escaped(text, w)?,
+                Chunk::Surrogate(unit) => write!(w, "\\u{:04x}", unit)?,
+            }
+        }
+        w.write_char('"')
+    }
+
+    /// Splits the string into runs of text and lone surrogates.
+    fn chunks(&self) -> impl Iterator<Item = Chunk<'_>> {
+        let mut rest = &self.bytes[..];
+        std::iter::from_fn(move || {
+            let (chunk, len) = match find_surrogate(rest) {
+                _ if rest.is_empty() => return None,
+                Some(0) => {
+                    let unit = 0xD000 | u16::from(rest[1] & 0x3F) << 6 | u16::from(rest[2] & 0x3F);
+                    (Chunk::Surrogate(unit), 3)
+                }
+                end => {
+                    let end = end.unwrap_or(rest.len());
+                    let text =
+                        str::from_utf8(&rest[..end]).expect("WTF-8 between surrogates is UTF-8");
+                    (Chunk::Str(text), end)
+                }
+            };
+            rest = &rest[len..];
+            Some(chunk)
+        })
+    }
+}
+
+/// Returns the index of the first surrogate in WTF-8 `bytes`.
+fn find_surrogate(bytes: &[u8]) -> Option<usize> {
+    // 0xED is always a lead byte, and is followed by a byte of at least
+    // 0xA0 only when it encodes a surrogate.
+    let mut from = 0;
+    while let Some(i) = memchr(0xED, &bytes[from..]) {
+        if bytes[from + i + 1] >= 0xA0 {
+            return Some(from + i);
+        }
+        from += i + 1;
+    }
+    None
+}
+
+impl From<String> for Wtf8String {
+    fn from(s: String) -> Self {
+        Wtf8String {
+            bytes: s.into_bytes(),
+        }
+    }
+}
+
+impl From<&str> for Wtf8String {
+    fn from(s: &str) -> Self {
+        Wtf8String::from(s.to_string())
+    }
+}
+
+impl fmt::Display for Wtf8String {
+    /// Writes the string with each lone surrogate replaced by U+FFFD.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.chunks() {
+            match chunk {
+                Chunk::Str(text) => f.write_str(text)?,
+                Chunk::Surrogate(_) => f.write_str("\u{FFFD}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wtf8_surrogates() {
+        let units = [0x61, 0xD800, 0x62, 0xDFFF, 0xD83D, 0xDE00, 0xDBFF];
+        let s = Wtf8String::from_utf16(&units);
+        assert_eq!(
+            s.as_bytes(),
+            b"a\xED\xA0\x80b\xED\xBF\xBF\xF0\x9F\x98\x80\xED\xAF\xBF"
+        );
+        assert_eq!(s.to_utf16(), units);
+        assert_eq!(s.to_string_lossy(), "a\u{FFFD}b\u{FFFD}\u{1F600}\u{FFFD}");
+        assert_eq!(s.to_string(), s.to_string_lossy());
+        assert!(s.has_lone_surrogates());
+        assert_eq!(s.clone().into_string(), Err(s.clone()));
+
+        let mut json = String::new();
+        s.write_json(&mut json).unwrap();
+        assert_eq!(json, r#""a\ud800b\udfff😀\udbff""#);
+
+        // A low surrogate completes a high one it follows.
+        let mut s = Wtf8String::from("x");
+        s.push_surrogate(0xD83D);
+        s.push_surrogate(0xDE00);
+        assert_eq!(s.into_string(), Ok("x\u{1F600}".to_string()));
+
+        // Text holding U+ED00 to U+EDFF or U+D000 to U+D7FF is not
+        // mistaken for a surrogate.
+        let s = Wtf8String::from("\u{ED00}\u{D7FF}\u{D000}");
+        assert!(!s.has_lone_surrogates());
+        assert_eq!(s.to_utf16(), [0xED00, 0xD7FF, 0xD000]);
+    }
+
+    #[test]
+    fn test_wtf8_from_replaced() {
+        let s = "a\u{FFFD}\u{FFFD}b\u{FFFD}";
+        let wtf = Wtf8String::from_replaced(s, &[(1, 0xDC00), (8, 0xD800)]);
+        assert_eq!(wtf.to_utf16(), [0x61, 0xDC00, 0xFFFD, 0x62, 0xD800]);
+        assert_eq!(Wtf8String::from_replaced("", &[]), Wtf8String::new());
+    }
+
+    #[test]
+    fn test_wtf8_from_wtf8() {
+        let s = Wtf8String::from_utf16(&[0xDC00, 0x61, 0xD800, 0xD800, 0xE9, 0xDBFF]);
+        assert_eq!(Wtf8String::from_wtf8(s.clone().into_bytes()), Some(s));
+        assert_eq!(
+            Wtf8String::from_wtf8(b"abc".to_vec()),
+            Some(Wtf8String::from("abc"))
+        );
+
+        let invalid: [&[u8]; 5] = [
+            b"\xFF",
+            b"a\xED\xA0",
+            b"\xED\x9F",
+            b"\xC0\x80",
+            // A pair must be stored as the character it encodes.
+            b"\xED\xA0\x80\xED\xB0\x80",
+        ];
+        for bytes in invalid {
+            assert_eq!(Wtf8String::from_wtf8(bytes.to_vec()), None, "{:?}", bytes);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! A conformance suite in the style of JSONTestSuite
+//! (https://github.com/nst/JSONTestSuite), whose case names it reuses.
+//!
+//! `y_` inputs must be accepted and `n_` inputs must be rejected. `i_`
+//! inputs are left to the implementation by RFC 8259, so their expected
+//! outcome records the choice this crate makes. Every input is run through
+//! the string, reader and push parsers, which must agree, and accepted
+//! inputs must decode to the same value as `serde_json`.
+
+use rill_json::{parse_reader, JsonNumber, JsonValue, ParseError, ParserEvent, PushParser};
+use serde_json::Value as SerdeValue;
+
+/// Inputs that every JSON parser must accept.
+const ACCEPT: &[(&str, &[u8])] = &[
+    // --- Structure ---
+    ("y_array_arraysWithSpaces", b"[[]   ]"),
+    ("y_array_empty", b"[]"),
+    ("y_array_empty-string", br#"[""]"#),
+    ("y_array_false", b"[false]"),
+    ("y_array_heterogeneous", br#"[null, 1, "1", {}]"#),
+    ("y_array_null", b"[null]"),
+    ("y_array_with_1_and_newline", b"[1\n]"),
+    ("y_array_with_leading_space", b" [1]"),
+    ("y_array_with_several_null", b"[1,null,null,null,2]"),
+    ("y_array_with_trailing_space", b"[2] "),
+    ("y_object", br#"{"asd":"sdf", "dfg":"fgh"}"#),
+    ("y_object_basic", br#"{"asd":"sdf"}"#),
+    ("y_object_duplicated_key", br#"{"a":"b","a":"c"}"#),
+    ("y_object_duplicated_key_and_value", br#"{"a":"b","a":"b"}"#),
+    ("y_object_empty", b"{}"),
+    ("y_object_empty_key", br#"{"":0}"#),
+    ("y_object_escaped_null_in_key", br#"{"foo\u0000bar": 42}"#),
+    ("y_object_extreme_numbers", br#"{ "min": -1.0e+28, "max": 1.0e+28 }"#),
+    ("y_object_simple", br#"{"a":[]}"#),
+    (
+        "y_object_string_unicode",
+        br#"{"title":"\u041f\u043e\u043b\u0442\u043e\u0440\u0430 \u0417\u0435\u043c\u043b\u0435\u043a\u043e\u043f\u0430" }"#,
+    ),
+    ("y_object_with_newlines", b"{\n\"a\": \"b\"\n}"),
+    ("y_structure_lonely_false", b"false"),
+    ("y_structure_lonely_int", b"42"),
+    ("y_structure_lonely_negative_real", b"-0.1"),
+    ("y_structure_lonely_null", b"null"),
+    ("y_structure_lonely_string", br#""asd""#),
+    ("y_structure_lonely_true", b"true"),
+    ("y_structure_string_empty", br#""""#),
+    ("y_structure_trailing_newline", b"[\"a\"]\n"),
+    ("y_structure_true_in_array", b"[true]"),
+    ("y_structure_whitespace_array", b" [] "),
+    // --- Numbers ---
+    ("y_number", b"[123e65]"),
+    ("y_number_0e+1", b"[0e+1]"),
+    ("y_number_0e1", b"[0e1]"),
+    ("y_number_after_space", b"[ 4]"),
+    (
+        "y_number_double_close_to_zero",
+        b"[-0.000000000000000000000000000000000000000000000000000000000000000000000000000001]",
+    ),
+    ("y_number_int_with_exp", b"[20e1]"),
+    ("y_number_minus_zero", b"[-0]"),
+    ("y_number_negative_int", b"[-123]"),
+    ("y_number_negative_one", b"[-1]"),
+    ("y_number_real_capital_e", b"[1E22]"),
+    ("y_number_real_capital_e_neg_exp", b"[1E-2]"),
+    ("y_number_real_capital_e_pos_exp", b"[1E+2]"),
+    ("y_number_real_exponent", b"[123e45]"),
+    ("y_number_real_fraction_exponent", b"[123.456e78]"),
+    ("y_number_real_neg_exp", b"[1e-2]"),
+    ("y_number_real_pos_exponent", b"[1e+2]"),
+    ("y_number_simple_int", b"[123]"),
+    ("y_number_simple_real", b"[123.456789]"),
+    // --- Strings ---
+    ("y_string_1_2_3_bytes_UTF-8_sequences", br#"["\u0060\u012a\u12AB"]"#),
+    ("y_string_accepted_surrogate_pair", br#"["\uD801\udc37"]"#),
+    ("y_string_accepted_surrogate_pairs", br#"["\ud83d\ude39\ud83d\udc8d"]"#),
+    ("y_string_allowed_escapes", br#"["\"\\\/\b\f\n\r\t"]"#),
+    ("y_string_backslash_and_u_escaped_zero", br#"["\\u0000"]"#),
+    ("y_string_backslash_doublequotes", br#"["\""]"#),
+    ("y_string_comments", br#"["a/*b*/c/*d//e"]"#),
+    ("y_string_double_escape_a", br#"["\\a"]"#),
+    ("y_string_double_escape_n", br#"["\\n"]"#),
+    ("y_string_escaped_control_character", br#"["\u0012"]"#),
+    ("y_string_escaped_noncharacter", br#"["\uFFFF"]"#),
+    ("y_string_in_array", br#"["asd"]"#),
+    ("y_string_in_array_with_leading_space", br#"[ "asd"]"#),
+    ("y_string_last_surrogates_1_and_2", br#"["\uDBFF\uDFFF"]"#),
+    ("y_string_nbsp_uescaped", br#"["new\u00A0line"]"#),
+    ("y_string_nonCharacterInUTF-8_U+10FFFF", b"[\"\xF4\x8F\xBF\xBF\"]"),
+    ("y_string_nonCharacterInUTF-8_U+FFFF", b"[\"\xEF\xBF\xBF\"]"),
+    ("y_string_null_escape", br#"["\u0000"]"#),
+    ("y_string_one-byte-utf-8", br#"["\u002c"]"#),
+    ("y_string_pi", b"[\"\xCF\x80\"]"),
+    ("y_string_reservedCharacterInUTF-8_U+1BFFF", b"[\"\xF0\x9B\xBF\xBF\"]"),
+    ("y_string_simple_ascii", br#"["asd "]"#),
+    ("y_string_space", br#"" ""#),
+    ("y_string_surrogates_U+1D11E_MUSICAL_SYMBOL_G_CLEF", br#"["\uD834\uDd1e"]"#),
+    ("y_string_three-byte-utf-8", br#"["\u0821"]"#),
+    ("y_string_two-byte-utf-8", br#"["\u0123"]"#),
+    ("y_string_u+2028_line_sep", b"[\"\xE2\x80\xA8\"]"),
+    ("y_string_u+2029_par_sep", b"[\"\xE2\x80\xA9\"]"),
+    ("y_string_uEscape", br#"["\u0061\u30af\u30EA\u30b9"]"#),
+    ("y_string_uescaped_newline", br#"["new\u000Aline"]"#),
+    ("y_string_unescaped_char_delete", b"[\"\x7F\"]"),
+    ("y_string_unicode", br#"["\uA66D"]"#),
+    ("y_string_unicodeEscapedBackslash", br#"["\u005C"]"#),
+    ("y_string_unicode_2", b"[\"\xE2\x8D\x82\xE3\x88\xB4\xE2\x8D\x82\"]"),
+    ("y_string_unicode_escaped_double_quote", br#"["\u0022"]"#),
+    ("y_string_utf8", b"[\"\xE2\x82\xAC\xF0\x9D\x84\x9E\"]"),
+    ("y_string_with_del_character", b"[\"a\x7Fa\"]"),
+    // Not from JSONTestSuite: raw non-ASCII text next to escapes.
+    ("y_string_utf8_around_escapes", b"[\"\xC3\xA9\\n\xE2\x82\xAC\\t\xF0\x9D\x84\x9E\"]"),
+    ("y_string_utf8_after_unicode_escape", b"{\"\\u00e9\xC3\xA9\": \"\\\"\xCF\x80\\\"\"}"),
+];
+
+/// Inputs that every JSON parser must reject.
+const REJECT: &[(&str, &[u8])] = &[
+    // --- Structure ---
+    ("n_array_1_true_without_comma", b"[1 true]"),
+    ("n_array_comma_and_number", b"[,1]"),
+    ("n_array_double_comma", b"[1,,2]"),
+    ("n_array_extra_close", br#"["x"]]"#),
+    ("n_array_extra_comma", br#"["",]"#),
+    ("n_array_incomplete", br#"["x""#),
+    ("n_array_inner_array_no_comma", b"[3[4]]"),
+    ("n_array_just_comma", b"[,]"),
+    ("n_array_missing_value", br#"[   , ""]"#),
+    ("n_array_number_and_comma", b"[1,]"),
+    ("n_array_star_inside", b"[*]"),
+    ("n_array_unclosed", br#"[""#),
+    ("n_array_unclosed_with_new_lines", b"[1,\n1\n,1"),
+    ("n_incomplete_false", b"[fals]"),
+    ("n_incomplete_null", b"[nul]"),
+    ("n_incomplete_true", b"[tru]"),
+    ("n_object_bad_value", br#"["x", truth]"#),
+    ("n_object_comma_instead_of_colon", br#"{"x", null}"#),
+    ("n_object_double_colon", br#"{"x"::"b"}"#),
+    ("n_object_missing_colon", br#"{"a" b}"#),
+    ("n_object_missing_key", br#"{:"b"}"#),
+    ("n_object_missing_value", br#"{"a":"#),
+    ("n_object_no-colon", br#"{"a""#),
+    ("n_object_non_string_key", b"{1:1}"),
+    ("n_object_single_quote", b"{'a':0}"),
+    ("n_object_trailing_comma", br#"{"id":0,}"#),
+    ("n_object_trailing_comment", br#"{"a":"b"}/**/"#),
+    ("n_object_two_commas_in_a_row", br#"{"a":"b",,"c":"d"}"#),
+    ("n_object_unquoted_key", br#"{a: "b"}"#),
+    ("n_object_with_trailing_garbage", br#"{"a":"b"}#"#),
+    ("n_structure_angle_bracket_.", b"<.>"),
+    ("n_structure_array_trailing_garbage", b"[1]x"),
+    ("n_structure_array_with_extra_array_close", b"[1]]"),
+    ("n_structure_close_unopened_array", b"1]"),
+    ("n_structure_double_array", b"[][]"),
+    ("n_structure_end_array", b"]"),
+    ("n_structure_lone-invalid-utf-8", b"\xE5"),
+    ("n_structure_no_data", b""),
+    ("n_structure_null-byte-outside-string", b"[\x00]"),
+    ("n_structure_number_with_trailing_garbage", b"2@"),
+    ("n_structure_object_unclosed_no_value", br#"{"":"#),
+    ("n_structure_open_array_object", b"[{"),
+    ("n_structure_open_object_open_array", b"{["),
+    ("n_structure_trailing_#", br##"{"a":"b"}#{}"##),
+    ("n_structure_uescaped_LF_before_string", br#"[\u000A""]"#),
+    ("n_structure_unclosed_array", b"[1"),
+    ("n_structure_unclosed_object", br#"{"asd":"asd""#),
+    ("n_structure_UTF8_BOM_no_data", b"\xEF\xBB\xBF"),
+    ("n_structure_whitespace_formfeed", b"[\x0C]"),
+    // --- Numbers ---
+    ("n_number_++", b"[++1234]"),
+    ("n_number_+1", b"[+1]"),
+    ("n_number_-1.0.", b"[-1.0.]"),
+    ("n_number_-2.", b"[-2.]"),
+    ("n_number_.-1", b"[.-1]"),
+    ("n_number_.2e-3", b"[.2e-3]"),
+    ("n_number_0.1.2", b"[0.1.2]"),
+    ("n_number_2.e3", b"[2.e3]"),
+    ("n_number_Inf", b"[Inf]"),
+    ("n_number_NaN", b"[NaN]"),
+    ("n_number_hex_1_digit", b"[0x1]"),
+    ("n_number_infinity", b"[Infinity]"),
+    ("n_number_real_without_fractional_part", b"[1.]"),
+    ("n_number_with_leading_zero", b"[012]"),
+    ("n_array_just_minus", b"[-]"),
+    ("n_number_-01", b"[-01]"),
+    ("n_number_0.3e", b"[0.3e]"),
+    ("n_number_0.3e+", b"[0.3e+]"),
+    ("n_number_0.e1", b"[0.e1]"),
+    ("n_number_0_capital_E", b"[0E]"),
+    ("n_number_0_capital_E+", b"[0E+]"),
+    ("n_number_0e", b"[0e]"),
+    ("n_number_0e+", b"[0e+]"),
+    ("n_number_1.0e", b"[1.0e]"),
+    ("n_number_1.0e+", b"[1.0e+]"),
+    ("n_number_1.0e-", b"[1.0e-]"),
+    ("n_number_1_000", b"[1 000.0]"),
+    ("n_number_1eE2", b"[1eE2]"),
+    ("n_number_9.e+", b"[9.e+]"),
+    ("n_number_expression", b"[1+2]"),
+    ("n_number_invalid-negative-real", b"[-123.123foo]"),
+    ("n_number_minus_infinity", b"[-Infinity]"),
+    ("n_number_minus_sign_with_trailing_garbage", b"[-foo]"),
+    ("n_number_minus_space_1", b"[- 1]"),
+    ("n_number_neg_int_starting_with_zero", b"[-012]"),
+    ("n_number_neg_real_without_int_part", b"[-.123]"),
+    ("n_number_real_garbage_after_e", b"[1ea]"),
+    ("n_number_starting_with_dot", b"[.123]"),
+    ("n_number_with_alpha", b"[1.2a-3]"),
+    ("n_number_with_alpha_char", b"[1.8011670033376514H-308]"),
+    // --- Strings ---
+    ("n_string_1_surrogate_then_escape_u", br#"["\uD800\u"]"#),
+    ("n_string_escape_x", br#"["\x00"]"#),
+    ("n_string_escaped_backslash_bad", br#"["\\\"]"#),
+    ("n_string_escaped_ctrl_char_tab", b"[\"\\\t\"]"),
+    ("n_string_incomplete_escape", br#"["\"]"#),
+    ("n_string_incomplete_escaped_character", br#"["\u00A"]"#),
+    (
+        "n_string_incomplete_surrogate_escape_invalid",
+        br#"["\uD800\uD800\x"]"#,
+    ),
+    ("n_string_invalid-utf-8-in-escape", b"[\"\\u\xE5\"]"),
+    ("n_string_invalid_backslash_esc", br#"["\a"]"#),
+    ("n_string_invalid_unicode_escape", br#"["\uqqqq"]"#),
+    ("n_string_invalid_utf8_after_escape", b"[\"\\\xE5\"]"),
+    ("n_string_no_quotes_with_bad_escape", br#"[\n]"#),
+    ("n_string_single_doublequote", br#"""#),
+    ("n_string_single_quote", b"['single quote']"),
+    ("n_string_start_escape_unclosed", br#"["\"#),
+    ("n_string_unescaped_ctrl_char", b"[\"a\x00a\"]"),
+    ("n_string_unescaped_newline", b"[\"new\nline\"]"),
+    ("n_string_unescaped_tab", b"[\"\t\"]"),
+    ("n_string_unicode_CapitalU", br#""\UA66D""#),
+    ("n_string_with_trailing_garbage", br#"""x"#),
+];
+
+/// Inputs whose handling RFC 8259 leaves to the implementation, with
+/// whether this crate accepts them.
+const IMPLEMENTATION_DEFINED: &[(&str, &[u8], bool)] = &[
+    // Lone surrogates are rejected unless `LoneSurrogatePolicy` says otherwise.
+    ("i_object_key_lone_2nd_surrogate", br#"{"\uDFAA":0}"#, false),
+    (
+        "i_string_1st_surrogate_but_2nd_missing",
+        br#"["\uDADA"]"#,
+        false,
+    ),
+    (
+        "i_string_1st_valid_surrogate_2nd_invalid",
+        br#"["\uD888\u1234"]"#,
+        false,
+    ),
+    (
+        "i_string_incomplete_surrogate_and_escape_valid",
+        br#"["\uD800\n"]"#,
+        false,
+    ),
+    (
+        "i_string_incomplete_surrogate_pair",
+        br#"["\uDd1ea"]"#,
+        false,
+    ),
+    (
+        "i_string_incomplete_surrogates_escape_valid",
+        br#"["\uD800\uD800\n"]"#,
+        false,
+    ),
+    ("i_string_invalid_lonely_surrogate", br#"["\ud800"]"#, false),
+    ("i_string_invalid_surrogate", br#"["\ud800abc"]"#, false),
+    (
+        "i_string_inverted_surrogates_U+1D11E",
+        br#"["\uDd1e\uD834"]"#,
+        false,
+    ),
+    ("i_string_lone_second_surrogate", br#"["\uDFAA"]"#, false),
+    // Input must be UTF-8, without a byte order mark.
+    ("i_string_invalid_utf-8", b"[\"\xFF\"]", false),
+    ("i_string_lone_utf8_continuation_byte", b"[\"\x81\"]", false),
+    (
+        "i_string_not_in_unicode_range",
+        b"[\"\xF4\xBF\xBF\xBF\"]",
+        false,
+    ),
+    (
+        "i_string_overlong_sequence_2_bytes",
+        b"[\"\xC0\xAF\"]",
+        false,
+    ),
+    ("i_string_truncated-utf-8", b"[\"\xE0\xFF\"]", false),
+    (
+        "i_string_UTF-8_invalid_sequence",
+        b"[\"\xE6\x97\xA5\xD1\x88\xFA\"]",
+        false,
+    ),
+    (
+        "i_string_UTF-16LE_with_BOM",
+        b"\xFF\xFE[\x00\"\x00\xE9\x00\"\x00]\x00",
+        false,
+    ),
+    (
+        "i_structure_UTF-8_BOM_empty_object",
+        b"\xEF\xBB\xBF{}",
+        false,
+    ),
+    // Exponents beyond the range of `f64` round to infinity or zero.
+    ("i_number_double_huge_neg_exp", b"[123.456e-789]", true),
+    ("i_number_huge_exp", b"[0.4e00669999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999969999999006]", true),
+    ("i_number_neg_int_huge_exp", b"[-1e+9999]", true),
+    ("i_number_pos_double_huge_exp", b"[1.5e+9999]", true),
+    ("i_number_real_neg_overflow", b"[-123123e100000]", true),
+    ("i_number_real_pos_overflow", b"[123123e100000]", true),
+    ("i_number_real_underflow", b"[123e-10000000]", true),
+    // Integers must fit in an `i64` or `u64`.
+    (
+        "i_number_too_big_neg_int",
+        b"[-123123123123123123123123123123]",
+        false,
+    ),
+    (
+        "i_number_too_big_pos_int",
+        b"[100000000000000000000]",
+        false,
+    ),
+    (
+        "i_number_very_big_negative_int",
+        b"[-237462374673276894279832749832423479823246327846]",
+        false,
+    ),
+];
+
+/// Parses `input` with the string parser, if it is valid UTF-8.
+fn parse_str(input: &[u8]) -> Option<Result<JsonValue, ParseError>> {
+    std::str::from_utf8(input).ok().map(JsonValue::parse)
+}
+
+/// Collects the events of `input` from the reader parser.
+fn parse_bytes(input: &[u8]) -> Result<Vec<ParserEvent<'static>>, ParseError> {
+    parse_reader(input).collect()
+}
+
+/// Collects the events of `input` from the push parser, fed one byte at a time.
+fn parse_pushed(input: &[u8]) -> Result<Vec<ParserEvent<'static>>, ParseError> {
+    let mut push = PushParser::default();
+    let mut events = Vec::new();
+    for byte in input {
+        push.feed(std::slice::from_ref(byte)).unwrap();
+        while let Some(event) = push.next_event() {
+            events.push(event?);
+        }
+    }
+    push.finish();
+    while let Some(event) = push.next_event() {
+        events.push(event?);
+    }
+    Ok(events)
+}
+
+/// Runs `input` through every parser, checks that they agree and returns
+/// whether it was accepted.
+fn accepts(name: &str, input: &[u8]) -> bool {
+    let bytes = parse_bytes(input);
+    let pushed = parse_pushed(input);
+    assert_eq!(
+        bytes, pushed,
+        "{}: the reader and push parsers disagree",
+        name
+    );
+    // The event parsers report empty input as an empty stream of events,
+    // which holds no JSON text.
+    let accepted = bytes.as_ref().is_ok_and(|events| !events.is_empty());
+    if let Some(value) = parse_str(input) {
+        assert_eq!(
+            value.is_ok(),
+            accepted,
+            "{}: the string and reader parsers disagree ({:?} vs {:?})",
+            name,
+            value,
+            bytes
+        );
+    }
+    accepted
+}
+
+/// Returns `true` if `ours` holds the same data as `theirs`.
+fn same_value(ours: &JsonValue, theirs: &SerdeValue) -> bool {
+    match (ours, theirs) {
+        (JsonValue::Null, SerdeValue::Null) => true,
+        (JsonValue::Boolean(a), SerdeValue::Bool(b)) => a == b,
+        (JsonValue::Number(n), SerdeValue::Number(m)) => match n {
+            // serde_json reads `-0` as a float.
+            JsonNumber::I64(n) => m.as_i64() == Some(*n) || m.as_f64() == Some(*n as f64),
+            JsonNumber::U64(n) => m.as_u64() == Some(*n),
+            JsonNumber::F64(n) => m.as_f64() == Some(*n),
+            _ => n.to_f64() == m.as_f64(),
+        },
+        (JsonValue::String(a), SerdeValue::String(b)) => a == b,
+        (JsonValue::Array(a), SerdeValue::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_value(a, b))
+        }
+        (JsonValue::Object(a), SerdeValue::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| same_value(a, b)))
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn test_accepts_valid_json() {
+    for &(name, input) in ACCEPT {
+        assert!(accepts(name, input), "{}: rejected", name);
+
+        let value = parse_str(input).unwrap().unwrap();
+        let expected: SerdeValue = serde_json::from_slice(input).unwrap();
+        assert!(
+            same_value(&value, &expected),
+            "{}: decoded {:?}, expected {:?}",
+            name,
+            value,
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_rejects_invalid_json() {
+    for &(name, input) in REJECT {
+        assert!(!accepts(name, input), "{}: accepted", name);
+    }
+}
+
+#[test]
+fn test_implementation_defined_json() {
+    for &(name, input, accepted) in IMPLEMENTATION_DEFINED {
+        assert_eq!(accepts(name, input), accepted, "{}", name);
+    }
+}
@@ -0,0 +1,202 @@
+//! Tests for `#[derive(ToJson, FromJson)]` (requires the `derive` feature).
+//!
+//! Every case converts a value to JSON text and back, and checks the
+//! JSON Pointer path of the errors from malformed input.
+#![cfg(feature = "derive")]
+
+use rill_json::{FromJson, FromJsonErrorKind, JsonValue, ToJson};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct User {
+    #[json(rename = "userId")]
+    id: u64,
+    name: String,
+    email: Option<String>,
+    #[json(default)]
+    tags: Vec<String>,
+    #[json(skip)]
+    cached: Option<u32>,
+    #[json(flatten)]
+    address: Address,
+}
+
+#[derive(Debug, PartialEq, Default, ToJson, FromJson)]
+struct Address {
+    city: String,
+    r#type: String,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Meters(f64);
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Point(i32, i32);
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Marker;
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+enum Shape {
+    Empty,
+    #[json(rename = "circle")]
+    Circle(Meters),
+    Line(Point, Point),
+    Rect {
+        width: u32,
+        #[json(rename = "h")]
+        height: u32,
+    },
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+enum Event {
+    Ping {
+        id: u8,
+        #[json(skip)]
+        seen: bool,
+    },
+    Batch {},
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+enum Never {}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Page<T> {
+    items: Vec<T>,
+    next: Option<Box<Page<T>>>,
+}
+
+fn round_trip<T: ToJson + FromJson + PartialEq + Debug>(value: T, json: &str) {
+    assert_eq!(value.to_json().stringify().unwrap(), json);
+    let parsed = JsonValue::parse(json).unwrap();
+    assert_eq!(T::from_json(&parsed).unwrap(), value);
+}
+
+fn from_str<T: FromJson>(json: &str) -> Result<T, rill_json::FromJsonError> {
+    T::from_json(&JsonValue::parse(json).unwrap())
+}
+
+#[test]
+fn test_derive_struct_attributes() {
+    round_trip(
+        User {
+            id: 7,
+            name: "Ada".into(),
+            email: None,
+            tags: vec!["admin".into()],
+            cached: None,
+            address: Address {
+                city: "London".into(),
+                r#type: "home".into(),
+            },
+        },
+        r#"{"city":"London","email":null,"name":"Ada","tags":["admin"],"type":"home","userId":7}"#,
+    );
+
+    // Missing `Option` and `default` members are filled in, and skipped
+    // fields are never read.
+    let user: User =
+        from_str(r#"{"userId": 1, "name": "Bo", "cached": 5, "city": "Paris", "type": "work"}"#)
+            .unwrap();
+    assert_eq!(user.email, None);
+    assert!(user.tags.is_empty());
+    assert_eq!(user.cached, None);
+    assert_eq!(user.address.city, "Paris");
+}
+
+#[test]
+fn test_derive_struct_shapes() {
+    round_trip(Meters(1.5), "1.5");
+    round_trip(Point(-1, 2), "[-1,2]");
+    round_trip(Marker, "null");
+
+    let mut pages = Page {
+        items: vec!['a'],
+        next: None,
+    };
+    pages = Page {
+        items: vec!['b', 'c'],
+        next: Some(Box::new(pages)),
+    };
+    round_trip(
+        pages,
+        r#"{"items":["b","c"],"next":{"items":["a"],"next":null}}"#,
+    );
+}
+
+#[test]
+fn test_derive_enums() {
+    round_trip(Shape::Empty, r#""Empty""#);
+    round_trip(Shape::Circle(Meters(2.0)), r#"{"circle":2.0}"#);
+    round_trip(
+        Shape::Line(Point(0, 0), Point(1, 1)),
+        r#"{"Line":[[0,0],[1,1]]}"#,
+    );
+    round_trip(
+        Shape::Rect {
+            width: 3,
+            height: 4,
+        },
+        r#"{"Rect":{"h":4,"width":3}}"#,
+    );
+
+    round_trip(Event::Ping { id: 1, seen: false }, r#"{"Ping":{"id":1}}"#);
+    round_trip(Event::Batch {}, r#"{"Batch":{}}"#);
+    assert!(from_str::<Never>(r#""Never""#).is_err());
+
+    let shapes: BTreeMap<String, Shape> = from_str(r#"{"a": {"Empty": null}}"#).unwrap();
+    assert_eq!(shapes["a"], Shape::Empty);
+}
+
+#[test]
+fn test_derive_error_paths() {
+    let error = from_str::<User>(r#"{"userId": 1, "city": "x", "type": "y"}"#).unwrap_err();
+    assert_eq!(error.kind, FromJsonErrorKind::MissingField("name"));
+    assert_eq!(error.to_string(), "Missing field 'name' at /name");
+
+    let error = from_str::<User>(r#"{"userId": -1, "name": "x"}"#).unwrap_err();
+    assert_eq!(error.kind, FromJsonErrorKind::OutOfRange("u64"));
+    assert_eq!(error.path, "/userId");
+
+    let error = from_str::<User>(r#"{"userId": 1, "name": "x", "tags": ["a", 2]}"#).unwrap_err();
+    assert_eq!(error.path, "/tags/1");
+
+    // Flattened fields report the member they read, not a nested path.
+    let error = from_str::<User>(r#"{"userId": 1, "name": "x", "type": "y"}"#).unwrap_err();
+    assert_eq!(error.path, "/city");
+
+    let error =
+        from_str::<Vec<Shape>>(r#"["Empty", {"Rect": {"width": 1, "h": true}}]"#).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Expected u32, found a boolean at /1/Rect/h"
+    );
+
+    let error = from_str::<Shape>(r#"{"Line": [[0, 0]]}"#).unwrap_err();
+    assert_eq!(
+        error.kind,
+        FromJsonErrorKind::InvalidLength {
+            expected: 2,
+            found: 1
+        }
+    );
+    assert_eq!(error.path, "/Line");
+
+    let error = from_str::<Shape>(r#"{"Line": [[0, 0], [1]]}"#).unwrap_err();
+    assert_eq!(error.path, "/Line/1");
+
+    let error = from_str::<Shape>(r#""Square""#).unwrap_err();
+    assert_eq!(
+        error.kind,
+        FromJsonErrorKind::UnknownVariant("Square".into())
+    );
+
+    let error = from_str::<Shape>(r#"{"Empty": null, "Line": []}"#).unwrap_err();
+    assert_eq!(
+        error.message,
+        "Expected a string or an object with a single member, found an object"
+    );
+}